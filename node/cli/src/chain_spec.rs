@@ -17,14 +17,16 @@
 //! Substrate chain configurations.
 
 use primitives::{Pair, Public, crypto::UncheckedInto};
-pub use node_primitives::{AccountId, Balance};
+pub use node_primitives::{AccountId, Balance, CurrencyId};
 use node_runtime::{
 	AuthorityDiscoveryConfig, BabeConfig, BalancesConfig, ContractsConfig, CouncilConfig, DemocracyConfig,
 	ElectionsConfig, GrandpaConfig, ImOnlineConfig, IndicesConfig, SessionConfig, SessionKeys, StakerStatus,
 	StakingConfig, SudoConfig, SystemConfig, TechnicalCommitteeConfig, WASM_BINARY, DexConfig, TokenConfig,
-	GenericAssetConfig,
+	GatewayConfig, GenericAssetConfig,
 };
 use node_runtime::constants::{time::*, currency::*};
+use dex::{OrderPair, RewardParams, FeeTier};
+use token::TokenInfo;
 pub use node_runtime::GenesisConfig;
 use substrate_service;
 use hex_literal::hex;
@@ -110,6 +112,8 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 	const ENDOWMENT: Balance = 10_000_000 * DOLLARS;
 	const STASH: Balance = 100 * DOLLARS;
 
+	let (token_config, dex_config) = market_genesis(&endowed_accounts[0], &endowed_accounts);
+
 	GenesisConfig {
 		system: Some(SystemConfig {
 			code: WASM_BINARY.to_vec(),
@@ -165,14 +169,12 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 		sudo: Some(SudoConfig {
 			key: endowed_accounts[0].clone(),
 		}),
-		dex: Some(DexConfig {
-			key: endowed_accounts[0].clone(),
-			bp: 1u64,
-		}),
-		token: Some(TokenConfig {
-			key: endowed_accounts[0].clone(),
-			bt: 1u64,
-			//basic_token: vec![(vec![1u8,2],1000u64),(vec![3u8,4],1000u64)],
+		dex: Some(dex_config),
+		token: Some(token_config),
+		gateway: Some(GatewayConfig {
+			relayers: vec![],
+			threshold: 1,
+			endpoint: vec![],
 		}),
 		generic_asset: Some(GenericAssetConfig {
 			assets: vec![1],
@@ -232,6 +234,41 @@ pub fn get_authority_keys_from_seed(seed: &str) -> (AccountId, AccountId, Grandp
 	)
 }
 
+/// Genesis of a market trading token 0 against token 1, both issued by `issuer`
+/// and held by each of `holders`.
+pub fn market_genesis(issuer: &AccountId, holders: &[AccountId]) -> (TokenConfig, DexConfig) {
+	const TOKEN_ENDOWMENT: u64 = 1_000_000_000;
+
+	let token = |symbol: &[u8], name: &[u8]| TokenInfo {
+		symbol: symbol.to_vec(),
+		name: name.to_vec(),
+		decimals: 3,
+		issuer: issuer.clone(),
+		max_supply: u64::max_value(),
+	};
+	let balances = (0..2)
+		.flat_map(|token| holders.iter().map(move |who| (token, who.clone(), TOKEN_ENDOWMENT)))
+		.collect();
+
+	(
+		TokenConfig {
+			key: issuer.clone(),
+			tokens: vec![token(b"BTC", b"Bitcoin"), token(b"USDT", b"Tether USD")],
+			balances,
+		},
+		DexConfig {
+			pairs: vec![(
+				OrderPair { first: CurrencyId::Token(0), second: CurrencyId::Token(1) },
+				RewardParams::default(),
+			)],
+			reward_token: None,
+			// 0.1% maker and 0.2% taker fee
+			fee_schedule: vec![FeeTier { volume: 0, maker_fee: 1_000, taker_fee: 2_000 }],
+			match_caps: Default::default(),
+		},
+	)
+}
+
 /// Helper function to create GenesisConfig for testing
 pub fn testnet_genesis(
 	initial_authorities: Vec<(AccountId, AccountId, GrandpaId, BabeId, ImOnlineId)>,
@@ -259,6 +296,8 @@ pub fn testnet_genesis(
 	const ENDOWMENT: Balance = 10_000_000 * DOLLARS;
 	const STASH: Balance = 100 * DOLLARS;
 
+	let (token_config, dex_config) = market_genesis(&endowed_accounts[0], &endowed_accounts);
+
 	let desired_seats = (endowed_accounts.len() / 2 - initial_authorities.len()) as u32;
 
 	GenesisConfig {
@@ -316,14 +355,12 @@ pub fn testnet_genesis(
 		sudo: Some(SudoConfig {
 			key: root_key,
 		}),
-		dex: Some(DexConfig {
-			key: endowed_accounts[0].clone(),
-			bp: 1u64,
-		}),
-		token: Some(TokenConfig {
-			key: endowed_accounts[0].clone(),
-			bt: 1u64,
-			//basic_token: vec![(vec![1u8,2],1000u64),(vec![3u8,4],1000u64)],
+		dex: Some(dex_config),
+		token: Some(token_config),
+		gateway: Some(GatewayConfig {
+			relayers: vec![],
+			threshold: 1,
+			endpoint: vec![],
 		}),
 		generic_asset: Some(GenericAssetConfig {
 			assets: vec![1],
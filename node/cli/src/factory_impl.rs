@@ -18,18 +18,23 @@
 //! using the cli to manufacture transactions and distribute them
 //! to accounts.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 
 use codec::{Encode, Decode};
 use keyring::sr25519::Keyring;
+use node_primitives::CurrencyId;
 use node_runtime::{
 	Call, CheckedExtrinsic, UncheckedExtrinsic, SignedExtra, BalancesCall, ExistentialDeposit,
 	MinimumPeriod,
 };
+use dex::{OrderPair, OrderType};
 use primitives::{sr25519, crypto::Pair};
 use sr_primitives::{generic::Era, traits::{Block as BlockT, Header as HeaderT, SignedExtension}};
-use transaction_factory::RuntimeAdapter;
+use transaction_factory::{Currency, RuntimeAdapter, Side};
 use transaction_factory::modes::Mode;
 use inherents::InherentData;
 use timestamp;
@@ -44,8 +49,17 @@ pub struct FactoryState<N> {
 	round: u32,
 	block_in_round: u32,
 	num: u32,
+
+	/// The next nonce of every account signing in the DEX modes.
+	nonces: RefCell<HashMap<node_primitives::AccountId, node_primitives::Index>>,
 }
 
+/// The pair the DEX modes trade, listed in the development genesis.
+const TRADED_PAIR: OrderPair<CurrencyId> = OrderPair {
+	first: CurrencyId::Token(0),
+	second: CurrencyId::Token(1),
+};
+
 type Number = <<node_primitives::Block as BlockT>::Header as HeaderT>::Number;
 
 impl<Number> FactoryState<Number> {
@@ -56,12 +70,31 @@ impl<Number> FactoryState<Number> {
 			system::CheckEra::from(Era::mortal(256, phase)),
 			system::CheckNonce::from(index),
 			system::CheckWeight::new(),
-			balances::TakeFees::from(0),
+			node_runtime::TakeTokenFees::from(0),
 			Default::default(),
 		)
 	}
 }
 
+impl FactoryState<Number> {
+	fn signed_extrinsic(
+		&self,
+		sender: &node_primitives::AccountId,
+		key: &sr25519::Pair,
+		function: Call,
+		version: u32,
+		genesis_hash: &<node_primitives::Block as BlockT>::Hash,
+		prior_block_hash: &<node_primitives::Block as BlockT>::Hash,
+	) -> <node_primitives::Block as BlockT>::Extrinsic {
+		let index = self.extract_index(&sender, prior_block_hash);
+		let phase = self.extract_phase(*prior_block_hash);
+		sign::<Self>(CheckedExtrinsic {
+			signed: Some((sender.clone(), Self::build_extra(index, phase))),
+			function,
+		}, key, (version, genesis_hash.clone(), prior_block_hash.clone(), (), (), (), ()))
+	}
+}
+
 impl RuntimeAdapter for FactoryState<Number> {
 	type AccountId = node_primitives::AccountId;
 	type Balance = node_primitives::Balance;
@@ -85,6 +118,7 @@ impl RuntimeAdapter for FactoryState<Number> {
 			block_in_round: 0,
 			block_no: 0,
 			start_number: 0,
+			nonces: Default::default(),
 		}
 	}
 
@@ -138,17 +172,68 @@ impl RuntimeAdapter for FactoryState<Number> {
 		genesis_hash: &<Self::Block as BlockT>::Hash,
 		prior_block_hash: &<Self::Block as BlockT>::Hash,
 	) -> <Self::Block as BlockT>::Extrinsic {
-		let index = self.extract_index(&sender, prior_block_hash);
-		let phase = self.extract_phase(*prior_block_hash);
-		sign::<Self>(CheckedExtrinsic {
-			signed: Some((sender.clone(), Self::build_extra(index, phase))),
-			function: Call::Balances(
-				BalancesCall::transfer(
-					indices::address::Address::Id(destination.clone().into()),
-					(*amount).into()
-				)
+		let function = Call::Balances(
+			BalancesCall::transfer(
+				indices::address::Address::Id(destination.clone().into()),
+				(*amount).into()
 			)
-		}, key, (version, genesis_hash.clone(), prior_block_hash.clone(), (), (), (), ()))
+		);
+		self.signed_extrinsic(sender, key, function, version, genesis_hash, prior_block_hash)
+	}
+
+	fn mint_extrinsic(
+		&self,
+		sender: &Self::AccountId,
+		key: &Self::Secret,
+		destination: &Self::AccountId,
+		currency: Currency,
+		amount: u64,
+		version: u32,
+		genesis_hash: &<Self::Block as BlockT>::Hash,
+		prior_block_hash: &<Self::Block as BlockT>::Hash,
+	) -> <Self::Block as BlockT>::Extrinsic {
+		let currency = match currency {
+			Currency::Base => TRADED_PAIR.first,
+			Currency::Quote => TRADED_PAIR.second,
+		};
+		let token = match currency {
+			CurrencyId::Token(token) => token,
+			_ => unreachable!("Both currencies of the traded pair are tokens; qed"),
+		};
+		let function = Call::Token(token::Call::mint_token(token, destination.clone(), amount));
+		self.signed_extrinsic(sender, key, function, version, genesis_hash, prior_block_hash)
+	}
+
+	fn order_extrinsic(
+		&self,
+		sender: &Self::AccountId,
+		key: &Self::Secret,
+		side: Side,
+		amount: u64,
+		price: u64,
+		version: u32,
+		genesis_hash: &<Self::Block as BlockT>::Hash,
+		prior_block_hash: &<Self::Block as BlockT>::Hash,
+	) -> <Self::Block as BlockT>::Extrinsic {
+		let ordertype = match side {
+			Side::Buy => OrderType::Buy,
+			Side::Sell => OrderType::Sell,
+		};
+		let function = Call::Dex(dex::Call::put_order_and_match(TRADED_PAIR, ordertype, amount, price));
+		self.signed_extrinsic(sender, key, function, version, genesis_hash, prior_block_hash)
+	}
+
+	fn cancel_order_extrinsic(
+		&self,
+		sender: &Self::AccountId,
+		key: &Self::Secret,
+		order: u128,
+		version: u32,
+		genesis_hash: &<Self::Block as BlockT>::Hash,
+		prior_block_hash: &<Self::Block as BlockT>::Hash,
+	) -> <Self::Block as BlockT>::Extrinsic {
+		let function = Call::Dex(dex::Call::cancel_order(TRADED_PAIR, order));
+		self.signed_extrinsic(sender, key, function, version, genesis_hash, prior_block_hash)
 	}
 
 	fn inherent_extrinsics(&self) -> InherentData {
@@ -188,7 +273,7 @@ impl RuntimeAdapter for FactoryState<Number> {
 
 	fn extract_index(
 		&self,
-		_account_id: &Self::AccountId,
+		account_id: &Self::AccountId,
 		_block_hash: &<Self::Block as BlockT>::Hash,
 	) -> Self::Index {
 		// TODO get correct index for account via api. See #2587.
@@ -196,6 +281,13 @@ impl RuntimeAdapter for FactoryState<Number> {
 		// without a preceding purge of the database.
 		if self.mode == Mode::MasterToN || self.mode == Mode::MasterTo1 {
 			self.block_no() as Self::Index
+		} else if let Mode::Dex(_) = self.mode {
+			// several extrinsics of an account go into one block, every one of them is
+			// included so counting them gives the index
+			let mut nonces = self.nonces.borrow_mut();
+			let nonce = nonces.entry(account_id.clone()).or_insert(0);
+			*nonce += 1;
+			*nonce - 1
 		} else {
 			match self.round() {
 				0 =>
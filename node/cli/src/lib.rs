@@ -71,7 +71,8 @@ impl GetLogFilter for CustomSubcommands {
 /// Please note: this command currently only works on an empty database!
 #[derive(Debug, StructOpt, Clone)]
 pub struct FactoryCmd {
-	/// How often to repeat. This option only has an effect in mode `MasterToNToM`.
+	/// How often to repeat. This option only has an effect in mode `MasterToNToM`
+	/// and in the DEX modes, where it is the number of trading blocks.
 	#[structopt(long="rounds", default_value = "1")]
 	pub rounds: u64,
 
@@ -91,12 +92,21 @@ pub struct FactoryCmd {
 	///               B -> E, C -> F, D -> G, ...{n}
 	///               ... x `rounds`
 	///
-	/// These three modes control manufacturing.
+	/// DexCrossing: Mint both tokens of the listed pair to `num` randomly
+	///              created traders, then fill `rounds` blocks with one order
+	///              from every trader. Every other order crosses an ask the
+	///              trader before just placed.
+	///
+	/// DexMixed: Like `DexCrossing`, but traders cycle through resting bids,
+	///           resting asks, orders crossing the spread and cancellations.
+	///
+	/// These five modes control manufacturing.
 	#[structopt(long="mode", default_value = "MasterToN")]
 	pub mode: transaction_factory::Mode,
 
 	/// Number of transactions to generate. In mode `MasterNToNToM` this is
-	/// the number of transactions per round.
+	/// the number of transactions per round, in the DEX modes the number of
+	/// traders.
 	#[structopt(long="num", default_value = "8")]
 	pub num: u64,
 
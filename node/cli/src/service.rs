@@ -467,7 +467,7 @@ mod tests {
 				let check_era = system::CheckEra::from(Era::Immortal);
 				let check_nonce = system::CheckNonce::from(index);
 				let check_weight = system::CheckWeight::new();
-				let take_fees = balances::TakeFees::from(0);
+				let take_fees = node_runtime::TakeTokenFees::from(0);
 				let extra = (
 					check_version,
 					check_genesis,
@@ -72,6 +72,36 @@ pub type BlockId = generic::BlockId<Block>;
 /// Opaque, encoded, unchecked extrinsic.
 pub type UncheckedExtrinsic = OpaqueExtrinsic;
 
+/// Identifier of a generic asset.
+pub type AssetId = u64;
+
+/// Identifier of a token of the token module.
+pub type TokenId = u32;
+
+/// One of the currencies of the chain, as traded on the DEX.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum CurrencyId {
+	/// The native currency of the balances module.
+	Native,
+	/// A generic asset.
+	Asset(AssetId),
+	/// A token of the token module.
+	Token(TokenId),
+}
+
+impl Default for CurrencyId {
+	fn default() -> Self {
+		CurrencyId::Native
+	}
+}
+
+impl From<TokenId> for CurrencyId {
+	fn from(token: TokenId) -> Self {
+		CurrencyId::Token(token)
+	}
+}
+
 /// A result of execution of a contract.
 #[derive(Eq, PartialEq, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
@@ -91,6 +121,40 @@ pub enum ContractExecResult {
 	Error,
 }
 
+/// Balance of an account in one token.
+#[derive(Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct TokenBalance {
+	/// The token.
+	pub token: TokenId,
+	/// The part of the balance no lock holds.
+	pub free: u64,
+	/// The part of the balance held by locks.
+	pub locked: u64,
+}
+
+/// Metadata and supply of a token.
+#[derive(Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct TokenMetadata {
+	/// The token.
+	pub token: TokenId,
+	/// Ticker symbol.
+	pub symbol: Vec<u8>,
+	/// Human readable name.
+	pub name: Vec<u8>,
+	/// Number of decimals of the display unit.
+	pub decimals: u8,
+	/// The account allowed to mint the token.
+	pub issuer: AccountId,
+	/// The most of the token that may ever exist.
+	pub max_supply: u64,
+	/// The amount of the token in existence.
+	pub total_issuance: u64,
+}
+
 client::decl_runtime_apis! {
 	/// The API to query account account nonce (aka index).
 	pub trait AccountNonceApi {
@@ -111,4 +175,30 @@ client::decl_runtime_apis! {
 			input_data: Vec<u8>,
 		) -> ContractExecResult;
 	}
+
+	/// The API to query the DEX.
+	pub trait DexApi {
+		/// Get the index of the fee tier `account` trades in on pairs quoted in `currency`, `None`
+		/// when no fee schedule is set.
+		fn fee_tier(account: AccountId, currency: CurrencyId) -> Option<u32>;
+
+		/// Get the volume `account` traded on pairs quoted in `currency` within the fee schedule's
+		/// rolling window.
+		fn traded_volume(account: AccountId, currency: CurrencyId) -> u64;
+	}
+
+	/// The API to query tokens of the token module.
+	pub trait TokenApi {
+		/// Get the balance of every token `account` holds.
+		fn balances(account: AccountId) -> Vec<TokenBalance>;
+
+		/// Get the metadata and supply of every token.
+		fn tokens() -> Vec<TokenMetadata>;
+
+		/// Get the amount of `token` in existence, `None` for unknown tokens.
+		fn total_issuance(token: TokenId) -> Option<u64>;
+
+		/// Get up to `count` holders of `token` from position `start` on, in no particular order.
+		fn holders(token: TokenId, start: u32, count: u32) -> Vec<AccountId>;
+	}
 }
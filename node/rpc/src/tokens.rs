@@ -0,0 +1,126 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Node-specific RPC methods for querying tokens.
+
+use std::sync::Arc;
+
+use client::blockchain::HeaderBackend;
+use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use node_primitives::{
+	AccountId, Block, BlockId, TokenApi as TokenRuntimeApi, TokenBalance, TokenId, TokenMetadata,
+};
+use sr_primitives::traits::{
+	self,
+	Block as BlockT,
+};
+
+/// Token RPC methods.
+#[rpc]
+pub trait TokenApi<BlockHash> {
+	/// Returns the free and locked balance of every token `account` holds.
+	#[rpc(name = "token_balances")]
+	fn balances(&self, account: AccountId, at: Option<BlockHash>) -> Result<Vec<TokenBalance>>;
+
+	/// Returns the metadata and supply of every token.
+	#[rpc(name = "token_list")]
+	fn list(&self, at: Option<BlockHash>) -> Result<Vec<TokenMetadata>>;
+
+	/// Returns the amount of `token` in existence, `null` for unknown tokens.
+	#[rpc(name = "token_totalIssuance")]
+	fn total_issuance(&self, token: TokenId, at: Option<BlockHash>) -> Result<Option<u64>>;
+
+	/// Returns up to `count` holders of `token` from position `start` on.
+	///
+	/// Pages hold at most 1000 holders. Holders are in no particular order and an account
+	/// emptying its holding moves the last holder into its position.
+	#[rpc(name = "token_holders")]
+	fn holders(
+		&self,
+		token: TokenId,
+		start: u32,
+		count: u32,
+		at: Option<BlockHash>,
+	) -> Result<Vec<AccountId>>;
+}
+
+/// An implementation of token specific RPC methods.
+pub struct Tokens<C> {
+	client: Arc<C>,
+}
+
+impl<C> Tokens<C> {
+	/// Create new `Tokens` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Tokens { client }
+	}
+}
+
+impl<C> Tokens<C> where C: HeaderBackend<Block> {
+	fn block(&self, at: Option<<Block as BlockT>::Hash>) -> BlockId {
+		BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash
+		))
+	}
+}
+
+fn runtime_error<E: std::fmt::Debug>(e: E) -> Error {
+	Error {
+		code: ErrorCode::ServerError(crate::constants::RUNTIME_ERROR),
+		message: "Unable to query tokens.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C> TokenApi<<Block as BlockT>::Hash> for Tokens<C>
+where
+	C: Send + Sync + 'static,
+	C: traits::ProvideRuntimeApi,
+	C: HeaderBackend<Block>,
+	C::Api: TokenRuntimeApi<Block>,
+{
+	fn balances(
+		&self,
+		account: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<TokenBalance>> {
+		self.client.runtime_api().balances(&self.block(at), account).map_err(runtime_error)
+	}
+
+	fn list(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<TokenMetadata>> {
+		self.client.runtime_api().tokens(&self.block(at)).map_err(runtime_error)
+	}
+
+	fn total_issuance(
+		&self,
+		token: TokenId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<u64>> {
+		self.client.runtime_api().total_issuance(&self.block(at), token).map_err(runtime_error)
+	}
+
+	fn holders(
+		&self,
+		token: TokenId,
+		start: u32,
+		count: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<AccountId>> {
+		self.client.runtime_api().holders(&self.block(at), token, start, count).map_err(runtime_error)
+	}
+}
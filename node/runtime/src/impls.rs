@@ -16,12 +16,15 @@
 
 //! Some configurable implementations as associated type for the substrate runtime.
 
-use node_primitives::Balance;
+use rstd::prelude::*;
+use node_primitives::{AccountId, Balance, CurrencyId};
 use sr_primitives::weights::{Weight, WeightMultiplier};
-use sr_primitives::traits::{Convert, Saturating};
+use sr_primitives::traits::{Convert, Saturating, SaturatedConversion, EnsureOrigin};
 use sr_primitives::Fixed64;
-use support::traits::{OnUnbalanced, Currency};
-use crate::{Balances, Authorship, MaximumBlockWeight, NegativeImbalance};
+use support::traits::{OnUnbalanced, Currency, MultiCurrency};
+use crate::{
+	Balances, GenericAsset, Token, Dex, Call, Authorship, MaximumBlockWeight, NegativeImbalance,
+};
 use crate::constants::fee::TARGET_BLOCK_FULLNESS;
 
 pub struct Author;
@@ -68,6 +71,181 @@ impl Convert<Weight, Balance> for WeightToFee {
 	}
 }
 
+/// The native currency, the generic assets and the tokens behind one `MultiCurrency`, so the
+/// DEX can trade any of them against each other. Token balances are `u64` and saturate.
+pub struct Currencies;
+
+impl MultiCurrency<AccountId> for Currencies {
+	type CurrencyId = CurrencyId;
+	type Balance = Balance;
+
+	fn currency_exists(currency: CurrencyId) -> bool {
+		match currency {
+			CurrencyId::Native => true,
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::currency_exists(id),
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::currency_exists(id),
+		}
+	}
+
+	fn can_hold(currency: CurrencyId, who: &AccountId) -> bool {
+		match currency {
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::can_hold(id, who),
+			_ => true,
+		}
+	}
+
+	fn total_issuance(currency: CurrencyId) -> Balance {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::total_issuance(()),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::total_issuance(id),
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::total_issuance(id).into(),
+		}
+	}
+
+	fn free_balance(currency: CurrencyId, who: &AccountId) -> Balance {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::free_balance((), who),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::free_balance(id, who),
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::free_balance(id, who).into(),
+		}
+	}
+
+	fn reserved_balance(currency: CurrencyId, who: &AccountId) -> Balance {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::reserved_balance((), who),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::reserved_balance(id, who),
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::reserved_balance(id, who).into(),
+		}
+	}
+
+	fn transfer(currency: CurrencyId, source: &AccountId, dest: &AccountId, value: Balance)
+		-> Result<(), &'static str>
+	{
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::transfer((), source, dest, value),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::transfer(id, source, dest, value),
+			CurrencyId::Token(id) =>
+				<Token as MultiCurrency<_>>::transfer(id, source, dest, value.saturated_into()),
+		}
+	}
+
+	fn reserve(currency: CurrencyId, who: &AccountId, value: Balance) -> Result<(), &'static str> {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::reserve((), who, value),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::reserve(id, who, value),
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::reserve(id, who, value.saturated_into()),
+		}
+	}
+
+	fn unreserve(currency: CurrencyId, who: &AccountId, value: Balance) -> Balance {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::unreserve((), who, value),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::unreserve(id, who, value),
+			CurrencyId::Token(id) => Self::token_remainder(value,
+				<Token as MultiCurrency<_>>::unreserve(id, who, value.saturated_into())),
+		}
+	}
+
+	fn repatriate_reserved(
+		currency: CurrencyId,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		value: Balance,
+	) -> Result<Balance, &'static str> {
+		match currency {
+			CurrencyId::Native =>
+				<Balances as MultiCurrency<_>>::repatriate_reserved((), slashed, beneficiary, value),
+			CurrencyId::Asset(id) =>
+				<GenericAsset as MultiCurrency<_>>::repatriate_reserved(id, slashed, beneficiary, value),
+			CurrencyId::Token(id) => <Token as MultiCurrency<_>>::repatriate_reserved(
+				id, slashed, beneficiary, value.saturated_into(),
+			).map(|remaining| Self::token_remainder(value, remaining)),
+		}
+	}
+
+	fn slash(currency: CurrencyId, who: &AccountId, value: Balance) -> Balance {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::slash((), who, value),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::slash(id, who, value),
+			CurrencyId::Token(id) => Self::token_remainder(value,
+				<Token as MultiCurrency<_>>::slash(id, who, value.saturated_into())),
+		}
+	}
+
+	fn slash_reserved(currency: CurrencyId, who: &AccountId, value: Balance) -> Balance {
+		match currency {
+			CurrencyId::Native => <Balances as MultiCurrency<_>>::slash_reserved((), who, value),
+			CurrencyId::Asset(id) => <GenericAsset as MultiCurrency<_>>::slash_reserved(id, who, value),
+			CurrencyId::Token(id) => Self::token_remainder(value,
+				<Token as MultiCurrency<_>>::slash_reserved(id, who, value.saturated_into())),
+		}
+	}
+}
+
+impl Currencies {
+	// what is left of `value` when a token operation on its saturated `u64` left `remaining`
+	fn token_remainder(value: Balance, remaining: u64) -> Balance {
+		value - (value.min(u64::max_value() as Balance) - Balance::from(remaining))
+	}
+}
+
+/// Root, as referenda are enacted, or whatever `Other` accepts.
+pub struct EnsureRootOr<Other>(rstd::marker::PhantomData<Other>);
+
+impl<
+	O: Into<Result<system::RawOrigin<AccountId>, O>> + From<system::RawOrigin<AccountId>>,
+	Other: EnsureOrigin<O>,
+> EnsureOrigin<O> for EnsureRootOr<Other> {
+	type Success = ();
+	fn try_origin(o: O) -> Result<(), O> {
+		system::EnsureRoot::<AccountId>::try_origin(o).or_else(|o| Other::try_origin(o).map(|_| ()))
+	}
+}
+
+/// Token balances and the books of the DEX, as contracts see them.
+pub struct DexMarket;
+
+impl contracts::ContractMarket<AccountId, Call> for DexMarket {
+	type CurrencyId = CurrencyId;
+
+	fn token_balance(token: u32, who: &AccountId) -> (u64, u64) {
+		(Token::free_token((token, who.clone())), Token::locked_token((token, who.clone())))
+	}
+
+	fn best_price(first: CurrencyId, second: CurrencyId, sell: bool) -> Option<u64> {
+		Dex::best_price(dex::OrderPair { first, second }, Self::order_type(sell))
+	}
+
+	fn open_orders(who: &AccountId, currency: CurrencyId) -> Vec<u128> {
+		Dex::open_orders_of((who.clone(), currency))
+	}
+
+	fn open_order_count(who: &AccountId, currency: CurrencyId) -> u32 {
+		Dex::open_order_count(who, currency)
+	}
+
+	fn place_order_call(
+		first: CurrencyId,
+		second: CurrencyId,
+		sell: bool,
+		amount: u64,
+		price: u64,
+	) -> Option<Call> {
+		let pair = dex::OrderPair { first, second };
+		Some(Call::Dex(dex::Call::put_order_and_match(pair, Self::order_type(sell), amount, price)))
+	}
+
+	fn cancel_order_call(index: u128) -> Option<Call> {
+		Dex::order_info(index).map(|order| Call::Dex(dex::Call::cancel_order(order.pair, index)))
+	}
+}
+
+impl DexMarket {
+	fn order_type(sell: bool) -> dex::OrderType {
+		if sell { dex::OrderType::Sell } else { dex::OrderType::Buy }
+	}
+}
+
 /// A struct that updates the weight multiplier based on the saturation level of the previous block.
 /// This should typically be called once per-block.
 ///
@@ -27,7 +27,7 @@ use support::{
 use primitives::u32_trait::{_1, _2, _3, _4};
 use node_primitives::{
 	AccountId, AccountIndex, Balance, BlockNumber, Hash, Index,
-	Moment, Signature, ContractExecResult,
+	Moment, Signature, ContractExecResult, AssetId, CurrencyId, TokenId, TokenBalance, TokenMetadata,
 };
 use babe::{AuthorityId as BabeId};
 use grandpa::fg_primitives::{self, ScheduledChange};
@@ -51,6 +51,7 @@ use version::NativeVersion;
 use primitives::OpaqueMetadata;
 use grandpa::{AuthorityId as GrandpaId, AuthorityWeight as GrandpaWeight};
 use im_online::sr25519::{AuthorityId as ImOnlineId, AuthoritySignature as ImOnlineSignature};
+use gateway::sr25519::RelayerId as GatewayRelayerId;
 use authority_discovery_primitives::{AuthorityId as EncodedAuthorityId, Signature as EncodedSignature};
 use codec::{Encode, Decode};
 use system::offchain::TransactionSubmitter;
@@ -62,10 +63,14 @@ pub use balances::Call as BalancesCall;
 pub use contracts::Gas;
 pub use support::StorageValue;
 pub use staking::StakerStatus;
+pub use dex::TakeTokenFees;
 
 /// Implementations of some helper traits passed into runtime modules as associated types.
 pub mod impls;
-use impls::{CurrencyToVoteHandler, WeightMultiplierUpdateHandler, Author, WeightToFee};
+use impls::{
+	CurrencyToVoteHandler, WeightMultiplierUpdateHandler, Author, WeightToFee, Currencies, DexMarket,
+	EnsureRootOr,
+};
 
 /// Constant values used within the runtime.
 pub mod constants;
@@ -84,8 +89,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to equal spec_version. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 160,
-	impl_version: 161,
+	spec_version: 162,
+	impl_version: 162,
 	apis: RUNTIME_API_VERSIONS,
 };
 
@@ -413,6 +418,7 @@ impl contracts::Trait for Runtime {
 	type MaxDepth = contracts::DefaultMaxDepth;
 	type MaxValueSize = contracts::DefaultMaxValueSize;
 	type BlockGasLimit = contracts::DefaultBlockGasLimit;
+	type Market = DexMarket;
 }
 
 impl sudo::Trait for Runtime {
@@ -420,23 +426,54 @@ impl sudo::Trait for Runtime {
 	type Proposal = Call;
 }
 
+parameter_types! {
+	pub const VolumeWindow: BlockNumber = 30 * DAYS;
+	pub const OrderRetention: BlockNumber = 7 * DAYS;
+	pub const NativeCurrencyId: CurrencyId = CurrencyId::Native;
+}
+
 impl dex::Trait for Runtime {
 	type Event = Event;
+	type CurrencyId = CurrencyId;
+	type Currencies = Currencies;
+	type NativeCurrencyId = NativeCurrencyId;
+	type Signature = Signature;
+	type VolumeWindow = VolumeWindow;
+	type OrderRetention = OrderRetention;
+	type ParameterOrigin =
+		EnsureRootOr<collective::EnsureProportionAtLeast<_3, _4, AccountId, CouncilCollective>>;
+}
+
+parameter_types! {
+	pub const TokenDeposit: Balance = 100 * DOLLARS;
+	pub const TokenHolderDeposit: Balance = 1 * CENTS;
 }
 
 impl token::Trait for Runtime {
 	type Event = Event;
 	type Token = Token;
+	type Currency = Balances;
+	type TokenDeposit = TokenDeposit;
+	type OnAccountFrozen = Dex;
+	type HolderDeposit = TokenHolderDeposit;
+	type DustRemoval = ();
 }
 
 impl generic_asset::Trait for Runtime {
 	type Event = Event;
-	type Balance = u128;
-	type AssetId = u64;
+	type Balance = Balance;
+	type AssetId = AssetId;
 }
 
 type SubmitTransaction = TransactionSubmitter<ImOnlineId, Runtime, UncheckedExtrinsic>;
 
+impl gateway::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type RelayerId = GatewayRelayerId;
+	type SubmitTransaction = TransactionSubmitter<GatewayRelayerId, Runtime, UncheckedExtrinsic>;
+}
+
 impl im_online::Trait for Runtime {
 	type AuthorityId = ImOnlineId;
 	type Call = Call;
@@ -485,7 +522,7 @@ impl system::offchain::CreateTransaction<Runtime, UncheckedExtrinsic> for Runtim
 			system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
 			system::CheckNonce::<Runtime>::from(index),
 			system::CheckWeight::<Runtime>::new(),
-			balances::TakeFees::<Runtime>::from(tip),
+			dex::TakeTokenFees::<Runtime>::from(tip),
 			Default::default(),
 		);
 		let raw_payload = SignedPayload::new(call, extra).ok()?;
@@ -524,7 +561,8 @@ construct_runtime!(
 		AuthorityDiscovery: authority_discovery::{Module, Call, Config<T>},
 		Offences: offences::{Module, Call, Storage, Event},
 		Dex: dex::{Module, Call, Storage, Event<T>, Config<T>},
-		Token: token,
+		Token: token::{Module, Call, Storage, Event<T>, Config<T>},
+		Gateway: gateway::{Module, Call, Storage, Event<T>, Config<T>},
         GenericAsset:generic_asset,
 	}
 );
@@ -546,7 +584,7 @@ pub type SignedExtra = (
 	system::CheckEra<Runtime>,
 	system::CheckNonce<Runtime>,
 	system::CheckWeight<Runtime>,
-	balances::TakeFees<Runtime>,
+	dex::TakeTokenFees<Runtime>,
 	contracts::CheckBlockGasLimit<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
@@ -717,6 +755,46 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl node_primitives::DexApi<Block> for Runtime {
+		fn fee_tier(account: AccountId, currency: CurrencyId) -> Option<u32> {
+			Dex::fee_tier_of(&account, currency)
+		}
+
+		fn traded_volume(account: AccountId, currency: CurrencyId) -> u64 {
+			Dex::traded_volume(&account, currency)
+		}
+	}
+
+	impl node_primitives::TokenApi<Block> for Runtime {
+		fn balances(account: AccountId) -> Vec<TokenBalance> {
+			Token::balances_of(&account).into_iter()
+				.map(|(token, free, locked)| TokenBalance { token, free, locked })
+				.collect()
+		}
+
+		fn tokens() -> Vec<TokenMetadata> {
+			(0..Token::next_token_id())
+				.filter_map(|token| Token::token_info(token).map(|info| TokenMetadata {
+					token,
+					symbol: info.symbol,
+					name: info.name,
+					decimals: info.decimals,
+					issuer: info.issuer,
+					max_supply: info.max_supply,
+					total_issuance: Token::total_issuance(token),
+				}))
+				.collect()
+		}
+
+		fn total_issuance(token: TokenId) -> Option<u64> {
+			Token::token_info(token).map(|_| Token::total_issuance(token))
+		}
+
+		fn holders(token: TokenId, start: u32, count: u32) -> Vec<AccountId> {
+			Token::holders(token, start, count)
+		}
+	}
+
 	impl substrate_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
 			let seed = seed.as_ref().map(|s| rstd::str::from_utf8(&s).expect("Seed is an utf8 string"));
@@ -1187,6 +1187,60 @@ where
 	}
 }
 
+/// The single currency of this module, under the unit currency id.
+impl<T: Trait<I>, I: Instance> support::traits::MultiCurrency<T::AccountId> for Module<T, I> {
+	type CurrencyId = ();
+	type Balance = T::Balance;
+
+	fn currency_exists(_currency: ()) -> bool {
+		true
+	}
+
+	fn total_issuance(_currency: ()) -> Self::Balance {
+		<Self as Currency<_>>::total_issuance()
+	}
+
+	fn free_balance(_currency: (), who: &T::AccountId) -> Self::Balance {
+		<Self as Currency<_>>::free_balance(who)
+	}
+
+	fn reserved_balance(_currency: (), who: &T::AccountId) -> Self::Balance {
+		<Self as ReservableCurrency<_>>::reserved_balance(who)
+	}
+
+	fn transfer(_currency: (), source: &T::AccountId, dest: &T::AccountId, value: Self::Balance) -> Result {
+		<Self as Currency<_>>::transfer(source, dest, value)
+	}
+
+	fn reserve(_currency: (), who: &T::AccountId, value: Self::Balance) -> Result {
+		<Self as ReservableCurrency<_>>::reserve(who, value)
+	}
+
+	fn unreserve(_currency: (), who: &T::AccountId, value: Self::Balance) -> Self::Balance {
+		<Self as ReservableCurrency<_>>::unreserve(who, value)
+	}
+
+	fn repatriate_reserved(
+		_currency: (),
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		value: Self::Balance,
+	) -> result::Result<Self::Balance, &'static str> {
+		<Self as ReservableCurrency<_>>::repatriate_reserved(slashed, beneficiary, value)
+	}
+
+	fn slash(_currency: (), who: &T::AccountId, value: Self::Balance) -> Self::Balance {
+		// dropping the imbalance takes the slashed funds out of the issuance
+		let (_, remaining) = <Self as Currency<_>>::slash(who, value);
+		remaining
+	}
+
+	fn slash_reserved(_currency: (), who: &T::AccountId, value: Self::Balance) -> Self::Balance {
+		let (_, remaining) = <Self as ReservableCurrency<_>>::slash_reserved(who, value);
+		remaining
+	}
+}
+
 /// Require the transactor pay for themselves and maybe include a tip to gain additional priority
 /// in the queue.
 #[derive(Encode, Decode, Clone, Eq, PartialEq)]
@@ -1207,7 +1261,7 @@ impl<T: Trait<I>, I: Instance> TakeFees<T, I> {
 	///      and the time it consumes.
 	///   - (optional) _tip_: if included in the transaction, it will be added on top. Only signed
 	///      transactions can have a tip.
-	fn compute_fee(len: usize, info: DispatchInfo, tip: T::Balance) -> T::Balance {
+	pub fn compute_fee(len: usize, info: DispatchInfo, tip: T::Balance) -> T::Balance {
 		let len_fee = if info.pay_length_fee() {
 			let len = T::Balance::from(len as u32);
 			let base = T::TransactionBaseFee::get();
@@ -141,6 +141,50 @@ pub trait ComputeDispatchFee<Call, Balance> {
 	fn compute_dispatch_fee(call: &Call) -> Balance;
 }
 
+/// Token balances and exchange orders the runtime lets contracts read and place.
+///
+/// Orders are never placed directly: the calls built here are dispatched as the contract once
+/// its execution succeeded, like calls passed to `ext_dispatch_call`.
+pub trait ContractMarket<AccountId, Call> {
+	/// The identifier of a currency traded on the exchange.
+	type CurrencyId: Codec + Clone;
+
+	/// The free and locked balance `who` has of `token`.
+	fn token_balance(token: u32, who: &AccountId) -> (u64, u64);
+
+	/// The best price in the book of `first` against `second`, on the sell side if `sell`.
+	fn best_price(first: Self::CurrencyId, second: Self::CurrencyId, sell: bool) -> Option<u64>;
+
+	/// The indices of the orders `who` has open in `currency`.
+	fn open_orders(who: &AccountId, currency: Self::CurrencyId) -> Vec<u128>;
+
+	/// The number of orders `who` has open in `currency`, cheap enough to price `open_orders` by.
+	fn open_order_count(who: &AccountId, currency: Self::CurrencyId) -> u32;
+
+	/// The call placing an order of `amount` at `price`, `None` if orders are not supported.
+	fn place_order_call(
+		first: Self::CurrencyId,
+		second: Self::CurrencyId,
+		sell: bool,
+		amount: u64,
+		price: u64,
+	) -> Option<Call>;
+
+	/// The call cancelling the order `index`, `None` if there is no such order.
+	fn cancel_order_call(index: u128) -> Option<Call>;
+}
+
+impl<AccountId, Call> ContractMarket<AccountId, Call> for () {
+	type CurrencyId = ();
+
+	fn token_balance(_: u32, _: &AccountId) -> (u64, u64) { (0, 0) }
+	fn best_price(_: (), _: (), _: bool) -> Option<u64> { None }
+	fn open_orders(_: &AccountId, _: ()) -> Vec<u128> { Vec::new() }
+	fn open_order_count(_: &AccountId, _: ()) -> u32 { 0 }
+	fn place_order_call(_: (), _: (), _: bool, _: u64, _: u64) -> Option<Call> { None }
+	fn cancel_order_call(_: u128) -> Option<Call> { None }
+}
+
 /// Information for managing an acocunt and its sub trie abstraction.
 /// This is the required info to cache for an account
 #[derive(Encode, Decode)]
@@ -414,6 +458,9 @@ pub trait Trait: timestamp::Trait {
 
 	/// The maximum amount of gas that could be expended per block.
 	type BlockGasLimit: Get<Gas>;
+
+	/// Token balances and exchange orders contracts have access to.
+	type Market: ContractMarket<Self::AccountId, <Self as Trait>::Call>;
 }
 
 /// Simple contract address determiner.
@@ -949,12 +996,18 @@ pub struct Schedule {
 
 	/// The maximum length of a subject used for PRNG generation.
 	pub max_subject_len: u32,
+
+	/// Gas cost of a token balance, best price or open orders query.
+	pub market_query_cost: Gas,
+
+	/// Gas cost per order an open orders query returns.
+	pub market_query_per_item_cost: Gas,
 }
 
 impl Default for Schedule {
 	fn default() -> Schedule {
 		Schedule {
-			version: 0,
+			version: 1,
 			put_code_per_byte_cost: 1,
 			grow_mem_cost: 1,
 			regular_op_cost: 1,
@@ -972,6 +1025,8 @@ impl Default for Schedule {
 			max_table_size: 16 * 1024,
 			enable_println: false,
 			max_subject_len: 32,
+			market_query_cost: 100,
+			market_query_per_item_cost: 10,
 		}
 	}
 }
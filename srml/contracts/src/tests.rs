@@ -23,7 +23,7 @@ use crate::account_db::{AccountDb, DirectAccountDb, OverlayAccountDb};
 use crate::{
 	BalanceOf, ComputeDispatchFee, ContractAddressFor, ContractInfo, ContractInfoOf, GenesisConfig,
 	Module, RawAliveContractInfo, RawEvent, Trait, TrieId, TrieIdFromParentCounter, Schedule,
-	TrieIdGenerator, CheckBlockGasLimit,
+	TrieIdGenerator, CheckBlockGasLimit, ContractMarket,
 };
 use assert_matches::assert_matches;
 use hex_literal::*;
@@ -183,6 +183,7 @@ impl Trait for Test {
 	type MaxDepth = MaxDepth;
 	type MaxValueSize = MaxValueSize;
 	type BlockGasLimit = BlockGasLimit;
+	type Market = TestMarket;
 }
 
 type Balances = balances::Module<Test>;
@@ -223,6 +224,44 @@ impl ComputeDispatchFee<Call, u64> for DummyComputeDispatchFee {
 	}
 }
 
+/// A book of currency 1 against 2 bid at 90 and asked at 110. Every account has order 7 and the
+/// order numbered like itself open in currency 1, but only order 7 can be cancelled. Orders are
+/// placed and cancelled with `set_balance` calls carrying their arguments.
+pub struct TestMarket;
+impl ContractMarket<u64, Call> for TestMarket {
+	type CurrencyId = u32;
+
+	fn token_balance(token: u32, who: &u64) -> (u64, u64) {
+		(u64::from(token) * 1000 + *who, 5)
+	}
+
+	fn best_price(first: u32, second: u32, sell: bool) -> Option<u64> {
+		match (first, second, sell) {
+			(1, 2, false) => Some(90),
+			(1, 2, true) => Some(110),
+			_ => None,
+		}
+	}
+
+	fn open_orders(who: &u64, currency: u32) -> Vec<u128> {
+		if currency == 1 { vec![u128::from(*who), 7] } else { vec![] }
+	}
+
+	fn open_order_count(who: &u64, currency: u32) -> u32 {
+		Self::open_orders(who, currency).len() as u32
+	}
+
+	fn place_order_call(first: u32, second: u32, sell: bool, amount: u64, price: u64) -> Option<Call> {
+		let pair = u64::from(first) * 10 + u64::from(second);
+		let price = if sell { price } else { price + 1_000_000 };
+		Some(Call::Balances(balances::Call::set_balance(pair, amount, price)))
+	}
+
+	fn cancel_order_call(index: u128) -> Option<Call> {
+		if index == 7 { Some(Call::Balances(balances::Call::set_balance(7, 0, 0))) } else { None }
+	}
+}
+
 const ALICE: u64 = 1;
 const BOB: u64 = 2;
 const CHARLIE: u64 = 3;
@@ -1634,4 +1634,138 @@ mod tests {
 		assert_eq!(output, ExecReturnValue { status: 17, data: hex!("5566778899").to_vec() });
 		assert!(!output.is_success());
 	}
+
+	/// Queries the token balance of account 42, the ask of the book of currency 1 against 2 and
+	/// the open orders in currency 1, and returns the three results one after another.
+	const CODE_MARKET_QUERIES: &str = r#"
+(module
+	(import "env" "ext_token_balance" (func $ext_token_balance (param i32 i32 i32)))
+	(import "env" "ext_dex_best_price" (func $ext_dex_best_price (param i32 i32 i32)))
+	(import "env" "ext_dex_open_orders" (func $ext_dex_open_orders (param i32 i32)))
+	(import "env" "ext_scratch_read" (func $ext_scratch_read (param i32 i32 i32)))
+	(import "env" "ext_return" (func $ext_return (param i32 i32)))
+	(import "env" "memory" (memory 1 1))
+
+	(func (export "call")
+		(call $ext_token_balance
+			(i32.const 3) ;; The token
+			(i32.const 8) ;; Pointer to the encoded account
+			(i32.const 8) ;; Length of the encoded account
+		)
+		(call $ext_scratch_read (i32.const 100) (i32.const 0) (i32.const 16))
+
+		(call $ext_dex_best_price
+			(i32.const 16) ;; Pointer to the encoded pair
+			(i32.const 8) ;; Length of the encoded pair
+			(i32.const 1) ;; The sell side
+		)
+		(call $ext_scratch_read (i32.const 116) (i32.const 0) (i32.const 9))
+
+		(call $ext_dex_open_orders
+			(i32.const 24) ;; Pointer to the encoded currency
+			(i32.const 4) ;; Length of the encoded currency
+		)
+		(call $ext_scratch_read (i32.const 125) (i32.const 0) (i32.const 33))
+
+		(call $ext_return
+			(i32.const 100)
+			(i32.const 58)
+		)
+	)
+	(func (export "deploy"))
+
+	;; Account 42
+	(data (i32.const 8) "\2A\00\00\00\00\00\00\00")
+	;; The pair of currency 1 against 2
+	(data (i32.const 16) "\01\00\00\00\02\00\00\00")
+	;; Currency 1
+	(data (i32.const 24) "\01\00\00\00")
+)
+"#;
+
+	#[test]
+	fn market_queries() {
+		let mut gas_meter = GasMeter::with_limit(50_000, 1);
+		let output = execute(
+			CODE_MARKET_QUERIES,
+			vec![],
+			MockExt::default(),
+			&mut gas_meter,
+		).unwrap();
+
+		// the open orders are those of the contract, 69
+		let mut expected = (3042u64, 5u64).encode();
+		expected.extend(Some(110u64).encode());
+		expected.extend(vec![69u128, 7].encode());
+		assert_eq!(output, ExecReturnValue { status: STATUS_SUCCESS, data: expected });
+		// three queries, the last one charged for each of its two orders
+		let schedule = crate::Schedule::default();
+		assert!(gas_meter.gas_left() <=
+			50_000 - 3 * schedule.market_query_cost - 2 * schedule.market_query_per_item_cost);
+	}
+
+	const CODE_DEX_ORDERS: &str = r#"
+(module
+	(import "env" "ext_dex_place_order" (func $ext_dex_place_order (param i32 i32 i32 i64 i64)))
+	(import "env" "ext_dex_cancel_order" (func $ext_dex_cancel_order (param i32 i32)))
+	(import "env" "memory" (memory 1 1))
+
+	(func (export "call")
+		(call $ext_dex_place_order
+			(i32.const 8) ;; Pointer to the encoded pair
+			(i32.const 8) ;; Length of the encoded pair
+			(i32.const 1) ;; A sell order
+			(i64.const 500) ;; The amount
+			(i64.const 110) ;; The price
+		)
+		(call $ext_dex_cancel_order
+			(i32.const 16) ;; Pointer to the encoded order index
+			(i32.const 16) ;; Length of the encoded order index
+		)
+	)
+	(func (export "deploy"))
+
+	;; The pair of currency 1 against 2
+	(data (i32.const 8) "\01\00\00\00\02\00\00\00")
+	;; Order 7
+	(data (i32.const 16) "\07\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00")
+)
+"#;
+
+	#[test]
+	fn dex_orders_are_dispatched() {
+		let mut mock_ext = MockExt::default();
+		let mut gas_meter = GasMeter::with_limit(50_000, 1);
+		let _ = execute(
+			CODE_DEX_ORDERS,
+			vec![],
+			&mut mock_ext,
+			&mut gas_meter,
+		).unwrap();
+
+		assert_eq!(
+			&mock_ext.dispatches,
+			&[
+				DispatchEntry(Call::Balances(balances::Call::set_balance(12, 500, 110))),
+				DispatchEntry(Call::Balances(balances::Call::set_balance(7, 0, 0))),
+			]
+		);
+		// both dispatch fees were charged
+		assert!(gas_meter.gas_left() <= 50_000 - 2 * 69);
+	}
+
+	#[test]
+	fn cancelling_an_unknown_order_traps() {
+		let code = CODE_DEX_ORDERS.replace(r#""\07\00"#, r#""\08\00"#);
+		let mut mock_ext = MockExt::default();
+		assert_matches!(
+			execute(
+				&code,
+				vec![],
+				&mut mock_ext,
+				&mut GasMeter::with_limit(50_000, 1),
+			),
+			Err(ExecError { reason: "during execution", buffer: _ })
+		);
+	}
 }
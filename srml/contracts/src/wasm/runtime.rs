@@ -16,7 +16,7 @@
 
 //! Environment definition of the wasm smart-contract runtime.
 
-use crate::{Schedule, Trait, CodeHash, ComputeDispatchFee, BalanceOf};
+use crate::{Schedule, Trait, CodeHash, ComputeDispatchFee, ContractMarket, BalanceOf};
 use crate::exec::{
 	Ext, ExecResult, ExecError, ExecReturnValue, StorageKey, TopicOf, STATUS_SUCCESS,
 };
@@ -128,6 +128,10 @@ pub enum RuntimeToken {
 	ReturnData(u32),
 	/// Dispatch fee calculated by `T::ComputeDispatchFee`.
 	ComputedDispatchFee(Gas),
+	/// A query of `T::Market`.
+	MarketQuery,
+	/// A query of `T::Market` returning the given number of items.
+	MarketQueryItems(u32),
 	/// (topic_count, data_bytes): A buffer of the given size is posted as an event indexed with the
 	/// given number of topics.
 	DepositEvent(u32, u32),
@@ -169,6 +173,11 @@ impl<T: Trait> Token<T> for RuntimeToken {
 					)
 			},
 			ComputedDispatchFee(gas) => Some(gas),
+			MarketQuery => Some(metadata.market_query_cost),
+			MarketQueryItems(item_count) => metadata
+				.market_query_per_item_cost
+				.checked_mul(item_count.into())
+				.and_then(|items_cost| items_cost.checked_add(metadata.market_query_cost)),
 		};
 
 		value.unwrap_or_else(|| Bounded::max_value())
@@ -287,6 +296,31 @@ fn write_sandbox_memory<T: Trait>(
 	Ok(())
 }
 
+/// Charge the gas meter with the dispatch fee of `call` and add it to the list of to-be-dispatched
+/// calls.
+///
+/// Returns `Err` if there is not enough gas.
+fn note_dispatch_call<E: Ext>(
+	ctx: &mut Runtime<E>,
+	call: <<E as Ext>::T as Trait>::Call,
+) -> Result<(), sandbox::HostError> {
+	let fee = {
+		let balance_fee = <<E as Ext>::T as Trait>::ComputeDispatchFee::compute_dispatch_fee(&call);
+		approx_gas_for_balance(ctx.gas_meter.gas_price(), balance_fee)
+	};
+	charge_gas(&mut ctx.gas_meter, ctx.schedule, RuntimeToken::ComputedDispatchFee(fee))?;
+
+	ctx.ext.note_dispatch_call(call);
+
+	Ok(())
+}
+
+type AccountIdOf<E> = <<E as Ext>::T as system::Trait>::AccountId;
+type MarketCurrencyOf<E> = <<<E as Ext>::T as Trait>::Market as ContractMarket<
+	AccountIdOf<E>,
+	<<E as Ext>::T as Trait>::Call,
+>>::CurrencyId;
+
 // ***********************************************************
 // * AFTER MAKING A CHANGE MAKE SURE TO UPDATE COMPLEXITY.MD *
 // ***********************************************************
@@ -630,18 +664,98 @@ define_env!(Env, <E: Ext>,
 		let call: <<E as Ext>::T as Trait>::Call =
 			read_sandbox_memory_as(ctx, call_ptr, call_len)?;
 
-		// Charge gas for dispatching this call.
-		let fee = {
-			let balance_fee = <<E as Ext>::T as Trait>::ComputeDispatchFee::compute_dispatch_fee(&call);
-			approx_gas_for_balance(ctx.gas_meter.gas_price(), balance_fee)
-		};
-		charge_gas(&mut ctx.gas_meter, ctx.schedule, RuntimeToken::ComputedDispatchFee(fee))?;
+		note_dispatch_call(ctx, call)
+	},
+
+	// Stores the free and locked balance of `token` the given account has into the scratch
+	// buffer.
+	//
+	// `who_ptr`, `who_len` - the pointer and the length of a buffer that encodes `T::AccountId`.
+	//
+	// The data is encoded as `(u64, u64)`. The current contents of the scratch buffer are
+	// overwritten.
+	ext_token_balance(ctx, token: u32, who_ptr: u32, who_len: u32) => {
+		let who: AccountIdOf<E> = read_sandbox_memory_as(ctx, who_ptr, who_len)?;
+		charge_gas(ctx.gas_meter, ctx.schedule, RuntimeToken::MarketQuery)?;
+
+		ctx.scratch_buf.clear();
+		let balance = <<E as Ext>::T as Trait>::Market::token_balance(token, &who);
+		balance.encode_to(&mut ctx.scratch_buf);
+		Ok(())
+	},
 
-		ctx.ext.note_dispatch_call(call);
+	// Stores the best price in the book of a pair into the scratch buffer.
+	//
+	// `pair_ptr`, `pair_len` - the pointer and the length of a buffer that encodes the pair as
+	// two currency ids. `sell` - non-zero for the sell side of the book, zero for the buy side.
+	//
+	// The data is encoded as `Option<u64>`, `None` for an empty side. The current contents of the
+	// scratch buffer are overwritten.
+	ext_dex_best_price(ctx, pair_ptr: u32, pair_len: u32, sell: u32) => {
+		let (first, second): (MarketCurrencyOf<E>, MarketCurrencyOf<E>) =
+			read_sandbox_memory_as(ctx, pair_ptr, pair_len)?;
+		charge_gas(ctx.gas_meter, ctx.schedule, RuntimeToken::MarketQuery)?;
 
+		ctx.scratch_buf.clear();
+		let price = <<E as Ext>::T as Trait>::Market::best_price(first, second, sell != 0);
+		price.encode_to(&mut ctx.scratch_buf);
 		Ok(())
 	},
 
+	// Stores the indices of the orders the current contract has open in a currency into the
+	// scratch buffer. Charged per order before they are read.
+	//
+	// `currency_ptr`, `currency_len` - the pointer and the length of a buffer that encodes the
+	// currency id.
+	//
+	// The data is encoded as `Vec<u128>`. The current contents of the scratch buffer are
+	// overwritten.
+	ext_dex_open_orders(ctx, currency_ptr: u32, currency_len: u32) => {
+		let currency: MarketCurrencyOf<E> = read_sandbox_memory_as(ctx, currency_ptr, currency_len)?;
+		let count = <<E as Ext>::T as Trait>::Market::open_order_count(ctx.ext.address(), currency.clone());
+		charge_gas(ctx.gas_meter, ctx.schedule, RuntimeToken::MarketQueryItems(count))?;
+
+		ctx.scratch_buf.clear();
+		let orders = <<E as Ext>::T as Trait>::Market::open_orders(ctx.ext.address(), currency);
+		orders.encode_to(&mut ctx.scratch_buf);
+		Ok(())
+	},
+
+	// Adds an order of the current contract to the list of to-be-dispatched calls, charging the
+	// dispatch fee of placing it. Traps if the runtime offers no exchange.
+	//
+	// `pair_ptr`, `pair_len` - the pointer and the length of a buffer that encodes the pair as
+	// two currency ids. `sell` - non-zero for a sell order, zero for a buy order.
+	//
+	// Like calls passed to `ext_dispatch_call` the order is placed only after the execution
+	// of the calling extrinsic succeeded, and a failure to place it is not reported back.
+	ext_dex_place_order(ctx, pair_ptr: u32, pair_len: u32, sell: u32, amount: u64, price: u64) => {
+		let (first, second): (MarketCurrencyOf<E>, MarketCurrencyOf<E>) =
+			read_sandbox_memory_as(ctx, pair_ptr, pair_len)?;
+		let call = <<E as Ext>::T as Trait>::Market::place_order_call(
+			first,
+			second,
+			sell != 0,
+			amount,
+			price,
+		).ok_or(sandbox::HostError)?;
+
+		note_dispatch_call(ctx, call)
+	},
+
+	// Adds the cancellation of an order to the list of to-be-dispatched calls, charging the
+	// dispatch fee of cancelling it. Traps if there is no such order.
+	//
+	// `index_ptr`, `index_len` - the pointer and the length of a buffer that encodes the index of
+	// the order as `u128`. Only orders of the current contract can be cancelled.
+	ext_dex_cancel_order(ctx, index_ptr: u32, index_len: u32) => {
+		let index: u128 = read_sandbox_memory_as(ctx, index_ptr, index_len)?;
+		let call = <<E as Ext>::T as Trait>::Market::cancel_order_call(index)
+			.ok_or(sandbox::HostError)?;
+
+		note_dispatch_call(ctx, call)
+	},
+
 	// Record a request to restore the caller contract to the specified contract.
 	//
 	// At the finalization stage, i.e. when all changes from the extrinsic that invoked this
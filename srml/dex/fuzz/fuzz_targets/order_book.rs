@@ -0,0 +1,9 @@
+//! Feed arbitrary operations to the order book of the mock runtime. Run with
+//! `cargo fuzz run order_book` from `srml/dex`.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	dex::harness::run(data);
+});
@@ -0,0 +1,150 @@
+//! Paying transaction fees in a whitelisted currency instead of the native one.
+//!
+//! The native fee is computed exactly like `balances::TakeFees` computes it and then converted
+//! at the `FeeRate` root set for the currency.
+//!
+//! Native fees are split between the treasury and the block author by `TransactionPayment`. That
+//! handler only takes native imbalances, so fees paid in other currencies are collected in
+//! `transaction_fee_pot` instead, which `ParameterOrigin` spends with `spend_transaction_fees`.
+
+use rstd::prelude::*;
+use codec::{Encode, Decode};
+use sr_primitives::{
+	traits::{SignedExtension, SaturatedConversion},
+	transaction_validity::{
+		TransactionPriority, ValidTransaction, InvalidTransaction, TransactionValidityError,
+		TransactionValidity,
+	},
+	weights::DispatchInfo,
+};
+
+use crate::*;
+
+/// How fees in a whitelisted currency are derived from the native fee.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum FeeRate {
+	/// `currency` units are charged for every `native` units of native fee.
+	Fixed { native: u64, currency: u64 },
+	/// The best price the book of the currency against `NativeCurrencyId` offers, as long as
+	/// orders worth at least `min_depth` of the currency rest at that price. A thin quote is
+	/// cheap to move, so fees are not accepted in the currency while there is none deeper.
+	Dex { min_depth: u64 },
+}
+
+/// Require the transactor pay for themselves and maybe include a tip to gain additional priority
+/// in the queue, in the native currency or in a currency whitelisted for fees. The tip is given
+/// in native units either way; paying in another currency, it is converted at the fee rate of
+/// that currency and paid in it along with the fee.
+///
+/// Like `TakeFees` the whole fee is charged up front, for the weight the call declares. Calls do
+/// not report the weight they actually used, so there is nothing to refund after dispatch.
+/// Fees paid in another currency go to the `transaction_fee_pot`, see the module docs.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct TakeTokenFees<T: Trait> {
+	#[codec(compact)]
+	tip: <T as balances::Trait>::Balance,
+	currency: Option<T::CurrencyId>,
+}
+
+impl<T: Trait> TakeTokenFees<T> {
+	/// Pay in the native currency. Used only in client/factory code.
+	pub fn from(tip: <T as balances::Trait>::Balance) -> Self {
+		Self { tip, currency: None }
+	}
+
+	/// Pay in `currency`. The `tip` is given in native units, converted and paid in `currency`
+	/// along with the fee.
+	pub fn in_currency(currency: T::CurrencyId, tip: <T as balances::Trait>::Balance) -> Self {
+		Self { tip, currency: Some(currency) }
+	}
+}
+
+#[cfg(feature = "std")]
+impl<T: Trait> rstd::fmt::Debug for TakeTokenFees<T> {
+	fn fmt(&self, f: &mut rstd::fmt::Formatter) -> rstd::fmt::Result {
+		write!(f, "TakeTokenFees({:?}, {:?})", self.tip, self.currency)
+	}
+}
+
+impl<T: Trait> SignedExtension for TakeTokenFees<T> {
+	type AccountId = T::AccountId;
+	type Call = <T as system::Trait>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+	fn additional_signed(&self) -> rstd::result::Result<(), TransactionValidityError> { Ok(()) }
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: DispatchInfo,
+		len: usize,
+	) -> TransactionValidity {
+		let currency = match self.currency {
+			Some(currency) => currency,
+			None => return balances::TakeFees::<T>::from(self.tip).validate(who, call, info, len),
+		};
+
+		let fee = balances::TakeFees::<T>::compute_fee(len, info, self.tip);
+		let amount = match <Module<T>>::fee_in_currency(currency, fee) {
+			Some(amount) => amount,
+			None => return InvalidTransaction::Payment.into(),
+		};
+		if T::Currencies::transfer(currency, who, &<Module<T>>::transaction_fee_pot(), amount).is_err() {
+			return InvalidTransaction::Payment.into();
+		}
+
+		let mut r = ValidTransaction::default();
+		// prioritized by the native value of the fee, whatever it was paid in
+		r.priority = fee.saturated_into::<TransactionPriority>();
+		Ok(r)
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The amount of `currency` paying a native `fee`, rounded up. `None` when `currency` is not
+	/// whitelisted for fees or its rate is not known.
+	pub fn fee_in_currency(currency: T::CurrencyId, fee: <T as balances::Trait>::Balance) -> Option<BalanceOf<T>> {
+		let (native, units) = match Self::fee_rate(currency)? {
+			FeeRate::Fixed { native, currency } => (native, currency),
+			FeeRate::Dex { min_depth } => Self::dex_fee_rate(currency, min_depth)?,
+		};
+		if native == 0 { return None; }
+		let fee: u128 = fee.saturated_into();
+		let amount = fee.checked_mul(units as u128)?
+			.checked_add(native as u128 - 1)? / native as u128;
+		Some(amount.saturated_into())
+	}
+
+	// a bid for the currency in native is what selling it for the fee fetches, otherwise the ask
+	// for native in the currency is what buying the fee costs, either only if `min_depth` of the
+	// currency is offered at it
+	fn dex_fee_rate(currency: T::CurrencyId, min_depth: u64) -> Option<(u64, u64)> {
+		let native = T::NativeCurrencyId::get();
+		let bid = Self::best_level(OrderPair { first: currency, second: native }, OrderType::Buy);
+		if let Some((bid, depth)) = bid {
+			if depth >= min_depth {
+				return Some((bid, 1));
+			}
+		}
+		let (ask, depth) = Self::best_level(OrderPair { first: native, second: currency }, OrderType::Sell)?;
+		if depth.saturating_mul(ask) >= min_depth { Some((1, ask)) } else { None }
+	}
+
+	/// The best price on one side of the book of `pair`.
+	pub fn best_price(pair: OrderPairOf<T>, ordertype: OrderType) -> Option<u64> {
+		Self::best_level(pair, ordertype).map(|(price, _)| price)
+	}
+
+	/// The best price on one side of the book of `pair` and the amount resting at it.
+	pub fn best_level(pair: OrderPairOf<T>, ordertype: OrderType) -> Option<(u64, u64)> {
+		let header = Self::bidlist_header_for((pair, ordertype))?;
+		Self::bidlist_cache(&header.index()).map(|node| (node.data.price, node.data.sum))
+	}
+
+	/// The account transaction fees paid in other currencies than the native one go to.
+	pub fn transaction_fee_pot() -> T::AccountId {
+		TX_FEE_MODULE_ID.into_account()
+	}
+}
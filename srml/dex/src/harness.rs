@@ -0,0 +1,352 @@
+//! Random operation sequences against the mock runtime, checked against the invariants of the
+//! order book after every step.
+//!
+//! Operations are decoded from a byte string, so the property tests and the fuzz target in
+//! `fuzz/` drive the exact same code. A violated invariant panics.
+
+use std::collections::BTreeSet;
+
+use runtime_io::with_externalities;
+use sr_primitives::traits::{OnInitialize, OnFinalize};
+use support::{StorageValue, traits::Currency};
+use token::TokenId;
+
+use crate::mock::*;
+use crate::{OrderPair, OrderType, OrderStatus, OffchainOrder, FeeTier};
+
+/// Accounts trading, `FIRST_ACCOUNT` onwards.
+const ACCOUNTS: u64 = 4;
+const FIRST_ACCOUNT: u64 = 10;
+/// The account after the traders, which starts out with nothing and only ever gets tokens by
+/// transfer.
+const NEWCOMER: u64 = FIRST_ACCOUNT + ACCOUNTS;
+/// What every account holds of the native currency, to pay holder deposits with.
+const NATIVE_ENDOWMENT: u64 = 1_000_000;
+/// Tokens, each listed against each other.
+const TOKENS: u32 = 3;
+/// What every account holds of every token at the start.
+const ENDOWMENT: u64 = 1_000_000;
+/// Prices are drawn from `MIN_PRICE` on, few enough levels for orders to cross often.
+const MIN_PRICE: u64 = 90;
+const PRICE_LEVELS: u64 = 20;
+/// Amounts are drawn from 1 to `MAX_AMOUNT`, small enough that nothing overflows.
+const MAX_AMOUNT: u64 = 64;
+
+/// The token parameters of a run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Params {
+	/// The `HolderDeposit` of the mock runtime.
+	pub holder_deposit: u64,
+	/// The minimum balance of every token.
+	pub min_balance: u64,
+}
+
+/// One step of a run.
+#[derive(Debug, Clone)]
+pub enum Operation {
+	/// `put_order_and_match` by `who`.
+	Place { who: u64, pair: usize, side: OrderType, amount: u64, price: u64 },
+	/// `cancel_order` of the `pick`th open order of `who`, or of some index if it has none.
+	Cancel { who: u64, pick: u8 },
+	/// `settle_signed_orders` of a `maker` quote and a `taker` order crossing it by `spread`.
+	Settle { maker: u64, taker: u64, pair: usize, side: OrderType, amount: u64, price: u64, spread: u64, fill: u64 },
+	/// `transfer_free_token` between any two of the traders and the newcomer.
+	Transfer { who: u64, dest: u64, token: TokenId, amount: u64 },
+	/// Finish the block and start the next one.
+	NextBlock,
+}
+
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+	fn byte(&mut self) -> Option<u8> {
+		let (first, rest) = self.0.split_first()?;
+		self.0 = rest;
+		Some(*first)
+	}
+
+	fn below(&mut self, bound: u64) -> Option<u64> {
+		self.byte().map(|b| b as u64 % bound)
+	}
+
+	fn side(&mut self) -> Option<OrderType> {
+		self.byte().map(|b| if b % 2 == 0 { OrderType::Buy } else { OrderType::Sell })
+	}
+}
+
+impl Operation {
+	fn decode(input: &mut Input) -> Option<Self> {
+		let pairs = pairs().len() as u64;
+		Some(match input.byte()? % 9 {
+			0..=3 => Operation::Place {
+				who: FIRST_ACCOUNT + input.below(ACCOUNTS)?,
+				pair: input.below(pairs)? as usize,
+				side: input.side()?,
+				amount: 1 + input.below(MAX_AMOUNT)?,
+				price: MIN_PRICE + input.below(PRICE_LEVELS)?,
+			},
+			4..=5 => Operation::Cancel {
+				who: FIRST_ACCOUNT + input.below(ACCOUNTS)?,
+				pick: input.byte()?,
+			},
+			6 => {
+				let amount = 1 + input.below(MAX_AMOUNT)?;
+				Operation::Settle {
+					maker: FIRST_ACCOUNT + input.below(ACCOUNTS)?,
+					taker: FIRST_ACCOUNT + input.below(ACCOUNTS)?,
+					pair: input.below(pairs)? as usize,
+					side: input.side()?,
+					amount,
+					price: MIN_PRICE + input.below(PRICE_LEVELS)?,
+					spread: input.below(PRICE_LEVELS)?,
+					fill: 1 + input.below(amount)?,
+				}
+			},
+			7 => Operation::Transfer {
+				who: FIRST_ACCOUNT + input.below(ACCOUNTS + 1)?,
+				dest: FIRST_ACCOUNT + input.below(ACCOUNTS + 1)?,
+				token: input.below(TOKENS as u64)? as TokenId,
+				amount: 1 + input.below(MAX_AMOUNT)?,
+			},
+			_ => Operation::NextBlock,
+		})
+	}
+}
+
+/// Every pair of two of the tokens, the first one being traded for the second.
+fn pairs() -> Vec<OrderPair<TokenId>> {
+	(0..TOKENS)
+		.flat_map(|first| (first + 1..TOKENS).map(move |second| OrderPair { first, second }))
+		.collect()
+}
+
+/// Everyone who may hold tokens during a run.
+fn holders() -> Vec<AccountId> {
+	let mut holders: Vec<_> = (FIRST_ACCOUNT..=NEWCOMER).collect();
+	holders.extend(vec![Dex::fee_pot(), Dex::reward_pot(), Dex::transaction_fee_pot()]);
+	holders
+}
+
+/// Run the operations `data` decodes to in a fresh market, checking the invariants after each.
+pub fn run(data: &[u8]) {
+	run_with(data, Params::default())
+}
+
+/// `run` with holder deposits and minimum balances set as in `params`.
+pub fn run_with(data: &[u8], params: Params) {
+	with_externalities(&mut new_test_ext(), || {
+		open_market(params);
+		let mut input = Input(data);
+		while let Some(operation) = Operation::decode(&mut input) {
+			apply(&operation);
+			check_conservation();
+			check_deposits(params);
+			check_books();
+		}
+	});
+}
+
+fn open_market(params: Params) {
+	for symbol in [b"AAA", b"BBB", b"CCC"].iter().take(TOKENS as usize) {
+		let token = new_token(&symbol[..]);
+		TokenT::set_min_balance(Origin::signed(1), token, params.min_balance).expect("1 issued the token");
+	}
+	set_holder_deposit(params.holder_deposit);
+	for who in FIRST_ACCOUNT..=NEWCOMER {
+		let _ = Balances::deposit_creating(&who, NATIVE_ENDOWMENT);
+	}
+	for pair in pairs() {
+		Dex::add_new_order_pair(pair).expect("pairs are distinct");
+	}
+	for token in 0..TOKENS {
+		for who in FIRST_ACCOUNT..FIRST_ACCOUNT + ACCOUNTS {
+			TokenT::depositing_token(&who, token, ENDOWMENT).expect("supply is uncapped");
+		}
+	}
+	// takers pay, makers get a rebate out of what takers paid
+	crate::FeeSchedule::put(vec![FeeTier { volume: 0, maker_fee: -500, taker_fee: 2_000 }]);
+	System::set_block_number(1);
+}
+
+fn apply(operation: &Operation) {
+	match *operation {
+		Operation::Place { who, pair, side, amount, price } => {
+			let pair = pairs()[pair].clone();
+			let opposite = match side {
+				OrderType::Buy => OrderType::Sell,
+				OrderType::Sell => OrderType::Buy,
+			};
+			let resting = book(&pair, opposite);
+			if Dex::put_order_and_match(Origin::signed(who), pair.clone(), side, amount, price).is_ok() {
+				check_priority(&pair, side, amount, price, &resting);
+			}
+		},
+		Operation::Cancel { who, pick } => {
+			let open: Vec<u128> = (0..TOKENS)
+				.flat_map(|token| Dex::open_orders_of((who, token)))
+				.collect();
+			let index = match open.len() {
+				0 => pick as u128,
+				len => open[pick as usize % len],
+			};
+			let pair = Dex::order_info(index).map(|order| order.pair).unwrap_or_default();
+			let _ = Dex::cancel_order(Origin::signed(who), pair, index);
+		},
+		Operation::Settle { maker, taker, pair, side, amount, price, spread, fill } => {
+			let pair = pairs()[pair].clone();
+			let (taker_side, taker_price) = match side {
+				OrderType::Buy => (OrderType::Sell, price.saturating_sub(spread).max(1)),
+				OrderType::Sell => (OrderType::Buy, price + spread),
+			};
+			// the quote outlives the taker's order, which makes it the maker
+			let order = |who, ordertype, price, expiry| sign_order(OffchainOrder {
+				maker: who, pair: pair.clone(), ordertype, amount, price, nonce: 0, expiry,
+			});
+			let _ = Dex::settle_signed_orders(Origin::signed(maker), order(maker, side, price, u64::max_value()),
+				order(taker, taker_side, taker_price, u64::max_value() - 1), fill);
+		},
+		Operation::Transfer { who, dest, token, amount } => {
+			let _ = TokenT::transfer_free_token(Origin::signed(who), dest, token, amount);
+		},
+		Operation::NextBlock => {
+			let now = System::block_number();
+			Dex::on_finalize(now);
+			System::set_block_number(now + 1);
+			Dex::on_initialize(now + 1);
+		},
+	}
+}
+
+/// No token is created or destroyed by trading: what all holders have free and locked adds up to
+/// what was issued, less the dust burned.
+fn check_conservation() {
+	for token in 0..TOKENS {
+		let total: u64 = holders().iter()
+			.map(|who| TokenT::free_token((token, *who)) + TokenT::locked_token((token, *who)))
+			.sum();
+		assert_eq!(total, TokenT::total_issuance(token), "token {} is not conserved", token);
+		assert_eq!(TokenT::total_issuance(token) + dust_removed(token), ENDOWMENT * ACCOUNTS,
+			"token {} issuance changed by more than the dust", token);
+	}
+}
+
+/// Every holder deposit is reserved from its payer for a holding that still exists, and the
+/// newcomer holds nothing it was not paid for.
+fn check_deposits(params: Params) {
+	let mut reserved = vec![0u64; holders().len()];
+	for token in 0..TOKENS {
+		for who in holders() {
+			let holds = TokenT::free_token((token, who)) + TokenT::locked_token((token, who)) != 0;
+			match TokenT::holder_deposit((token, who)) {
+				Some((payer, deposit)) => {
+					assert!(holds, "{} has a deposit for token {} it does not hold", who, token);
+					assert_eq!(deposit, params.holder_deposit, "{} has the wrong deposit for token {}", who, token);
+					let at = holders().iter().position(|holder| *holder == payer)
+						.expect("only holders pay deposits");
+					reserved[at] += deposit;
+				},
+				None => assert!(who != NEWCOMER || !holds || params.holder_deposit == 0,
+					"the newcomer holds token {} without a deposit", token),
+			}
+		}
+	}
+	for (who, deposits) in holders().into_iter().zip(reserved) {
+		assert_eq!(Balances::reserved_balance(&who), deposits, "{} has reserved other than its deposits", who);
+	}
+}
+
+/// The orders resting on one side of a pair, by price level from the best one, each level by
+/// `(index, amount left)` in the order they match in. Checks the list holding them on the way.
+fn book(pair: &OrderPair<TokenId>, side: OrderType) -> Vec<(u64, Vec<(u128, u64)>)> {
+	let key = (pair.clone(), side);
+	let header = Dex::bidlist_header_for(&key).map(|header| header.index());
+	let tail = Dex::bidlist_tail_for(&key).map(|tail| tail.index());
+	assert_eq!(header.is_some(), tail.is_some(), "{:?} has a head or a tail only", key);
+
+	let mut levels = Vec::new();
+	let mut visited = BTreeSet::new();
+	let (mut prev, mut next) = (None, header);
+	while let Some(index) = next {
+		assert!(visited.insert(index), "{:?} loops back to node {}", key, index);
+		let node = Dex::bidlist_cache(index).expect("linked nodes are stored");
+		assert_eq!(node.index(), index);
+		assert_eq!(node.prev(), prev, "node {} is not linked back to its predecessor", index);
+
+		let level = &node.data;
+		assert!(!level.list.is_empty(), "node {} is an empty price level", index);
+		let mut orders = Vec::new();
+		for id in level.list.iter() {
+			let bid = Dex::bid_of(id).expect("listed bids are stored");
+			assert_eq!((&bid.pair, bid.order_type, bid.price), (pair, side, level.price),
+				"bid {} is listed at the wrong level", id);
+			assert!(bid.amount != 0, "bid {} is filled but still listed", id);
+			let order = Dex::order_info(id).expect("resting orders are stored");
+			assert_eq!(order.status, OrderStatus::Valid, "order {} rests but is closed", id);
+			assert_eq!(order.left, bid.amount, "order {} and its bid disagree on what is left", id);
+			orders.push((*id, bid.amount));
+		}
+		assert_eq!(level.sum, orders.iter().map(|(_, amount)| amount).sum::<u64>(),
+			"node {} does not sum up its bids", index);
+
+		levels.push((level.price, orders));
+		prev = Some(index);
+		next = node.next();
+	}
+	assert_eq!(tail, prev, "the tail of {:?} is not its last node", key);
+	levels
+}
+
+/// Every book is a consistent list, sorted by price and then by time, does not cross and holds
+/// exactly the open orders.
+fn check_books() {
+	let mut resting = BTreeSet::new();
+	for pair in pairs() {
+		let bids = book(&pair, OrderType::Buy);
+		let asks = book(&pair, OrderType::Sell);
+		for (levels, side) in [(&bids, OrderType::Buy), (&asks, OrderType::Sell)].iter() {
+			for window in levels.windows(2) {
+				let (better, worse) = (window[0].0, window[1].0);
+				match side {
+					OrderType::Buy => assert!(better > worse, "bids of {:?} are out of order", pair),
+					OrderType::Sell => assert!(better < worse, "asks of {:?} are out of order", pair),
+				}
+			}
+			for (_, orders) in levels.iter() {
+				assert!(orders.windows(2).all(|w| w[0].0 < w[1].0), "a level of {:?} is out of time order", pair);
+				for (id, _) in orders {
+					assert!(resting.insert(*id), "order {} rests twice", id);
+				}
+			}
+		}
+		if let (Some(bid), Some(ask)) = (bids.first(), asks.first()) {
+			assert!(bid.0 < ask.0, "the book of {:?} is crossed", pair);
+		}
+	}
+	for index in 1..=Dex::order_index() {
+		if Dex::order_info(index).map_or(false, |order| order.status == OrderStatus::Valid) {
+			assert!(resting.contains(&index), "open order {} is not in the book", index);
+		}
+	}
+}
+
+/// An order just placed filled the best prices first and each price first come, first served:
+/// compared to `resting`, the opposite side before, exactly the orders ahead of it are taken.
+fn check_priority(pair: &OrderPair<TokenId>, side: OrderType, amount: u64, price: u64,
+				  resting: &[(u64, Vec<(u128, u64)>)]) {
+	let index = Dex::order_index();
+	let mut unfilled = amount;
+	for (level, orders) in resting {
+		let crosses = match side {
+			OrderType::Buy => price >= *level,
+			OrderType::Sell => price <= *level,
+		};
+		for (id, left) in orders {
+			let taken = if crosses { unfilled.min(*left) } else { 0 };
+			unfilled -= taken;
+			let now = Dex::bid_of(id).map_or(0, |bid| bid.amount);
+			assert_eq!(now, left - taken, "order {} was filled out of priority by order {}", id, index);
+		}
+	}
+	let rests = Dex::bid_of(index).map_or(0, |bid| bid.amount);
+	assert_eq!(rests, unfilled, "order {} rests with the wrong amount on {:?}", index, pair);
+}
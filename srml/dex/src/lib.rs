@@ -92,11 +92,14 @@ extern crate serde_derive;
 
 use rstd::prelude::*;
 use sr_primitives::{
-	traits::{StaticLookup, Dispatchable}, weights::SimpleDispatchInfo, DispatchError,
+	traits::{StaticLookup, Dispatchable, Hash, Verify, AccountIdConversion, Saturating, One, Zero, Member,
+			 MaybeSerializeDebug, SaturatedConversion, EnsureOrigin}, weights::{SimpleDispatchInfo, Weight},
+	DispatchError, Permill, ModuleId,
 };
 use support::{StorageValue, StorageMap, Parameter, decl_module, decl_event, decl_storage, ensure,
               dispatch::Result};
 use support::storage::{generator};
+use support::traits::{Get, MultiCurrency};
 
 use rstd::marker::PhantomData;
 
@@ -107,21 +110,65 @@ use codec::{Encode, Decode, Codec};
 
 pub mod linked_node;
 use linked_node::*;
-use token::Token;
+mod migration;
+mod fee_payment;
+pub use fee_payment::{FeeRate, TakeTokenFees};
+use token::TokenId;
 
 
-#[cfg(test)]
-mod mock;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod mock;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod harness;
 #[cfg(test)]
 mod tests;
 
+/// Version of the storage layout this code works with.
+///
+/// Bump it together with a new step in `on_runtime_upgrade` whenever the layout changes.
+/// Version 1 prunes closed orders, version 2 names currencies by `Trait::CurrencyId`,
+/// version 3 indexes the open orders of each account.
+const STORAGE_VERSION: u32 = 3;
+
+/// The account liquidity-mining rewards are paid from.
+const MODULE_ID: ModuleId = ModuleId(*b"py/dexrw");
+
+/// The account trading fees are collected into and maker rebates are paid from.
+const FEE_MODULE_ID: ModuleId = ModuleId(*b"py/dexfe");
+
+/// The account transaction fees paid in other currencies than the native one are collected into.
+const TX_FEE_MODULE_ID: ModuleId = ModuleId(*b"py/dextx");
+
+/// Prefix of what makers of off-chain orders sign, so no other signed message passes for an order.
+const ORDER_SIGNING_CONTEXT: &[u8] = b"dex/order";
+
+/// Fee rates are expressed in parts per million.
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+/// Traded volume is tracked in this many buckets per `VolumeWindow`.
+const VOLUME_BUCKETS: u32 = 30;
+
+/// Orders looked at per block while cleaning up orders stored before pruning existed.
+const ORDER_MIGRATION_BATCH: u128 = 500;
+
+/// Weight of a batch of a migration step, taken from the extrinsics of the block it runs in.
+const ORDER_MIGRATION_BATCH_WEIGHT: Weight = 500_000;
+
+/// Pairs liquidity-mining rewards accrue on per block, taking turns when more are listed.
+const REWARD_PAIRS_PER_BLOCK: u32 = 10;
+
+/// Weight of accruing the rewards of one pair, taken from the extrinsics of the block.
+const REWARD_PAIR_WEIGHT: Weight = 100_000;
+
 #[derive(PartialEq, Eq, Clone, Encode, Decode, Default)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
-pub struct OrderPair {
-	pub first: Vec<u8>,
-	pub second: Vec<u8>,
+pub struct OrderPair<CurrencyId> {
+	pub first: CurrencyId,
+	pub second: CurrencyId,
 }
 
+pub type OrderPairOf<T> = OrderPair<<T as Trait>::CurrencyId>;
+
 #[derive(PartialEq, Eq, Clone, Copy, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
 pub enum OrderType {
@@ -192,10 +239,10 @@ impl Default for OrderStatus {
 
 #[derive(PartialEq, Eq, Clone, Encode, Decode, Default)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
-pub struct OrderInformation<Who, BlockNumber>{
+pub struct OrderInformation<Who, BlockNumber, CurrencyId>{
 	who: Who,
 	ordertype: OrderType,   // buy or sell
-	pair: OrderPair,
+	pair: OrderPair<CurrencyId>,
 	amount: u64,
 	price: u64,
 	left: u64,              // the leftover of balance in this order
@@ -205,10 +252,10 @@ pub struct OrderInformation<Who, BlockNumber>{
 	fill_index: Vec<u128>,  // index of other orders that matched with this order
 }
 
-impl <Who, BlockNumber> OrderInformation<Who, BlockNumber>{
+impl <Who, BlockNumber, CurrencyId> OrderInformation<Who, BlockNumber, CurrencyId>{
 	pub fn new(who: Who,
 			   ordertype: OrderType,
-			   pair: OrderPair,
+			   pair: OrderPair<CurrencyId>,
 			   amount: u64,
 			   price: u64,
 			   index: u128,
@@ -235,25 +282,59 @@ impl <Who, BlockNumber> OrderInformation<Who, BlockNumber>{
 		}
 		return true;
 	}
+}
 
-	pub fn lock_each_token(){
+pub type OrderInfo<T> = OrderInformation<
+	<T as system::Trait>::AccountId,
+	<T as system::Trait>::BlockNumber,
+	<T as Trait>::CurrencyId,
+>;
 
-	}
+/// An order message signed by its maker off-chain and settled on-chain by a relayer.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct OffchainOrder<AccountId, BlockNumber, CurrencyId>{
+	pub maker: AccountId,
+	pub pair: OrderPair<CurrencyId>,
+	pub ordertype: OrderType,  // buy or sell
+	pub amount: u64,
+	pub price: u64,
+	pub nonce: u64,            // void once the maker's `MinOrderNonce` is above it
+	pub expiry: BlockNumber,   // last block in which the order can be settled
 }
 
-pub type OrderInfo<T> = OrderInformation<<T as system::Trait>::AccountId, <T as system::Trait>::BlockNumber>;
+pub type OffchainOrderT<T> = OffchainOrder<
+	<T as system::Trait>::AccountId,
+	<T as system::Trait>::BlockNumber,
+	<T as Trait>::CurrencyId,
+>;
+
+/// An off-chain order together with the maker's signature over its SCALE encoding.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SignedOrder<AccountId, BlockNumber, CurrencyId, Signature>{
+	pub order: OffchainOrder<AccountId, BlockNumber, CurrencyId>,
+	pub signature: Signature,
+}
+
+pub type SignedOrderT<T> = SignedOrder<
+	<T as system::Trait>::AccountId,
+	<T as system::Trait>::BlockNumber,
+	<T as Trait>::CurrencyId,
+	<T as Trait>::Signature,
+>;
 
 
 /// 盘口 记录 详情
 #[derive(PartialEq, Eq, Clone, Encode, Decode, Default)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
-pub struct BidDetail<AccountId, BlockNumber>
+pub struct BidDetail<AccountId, BlockNumber, CurrencyId>
 	where
 		AccountId: Clone,
 		BlockNumber: Copy,
 {
 	id: u128,
-	pair: OrderPair,
+	pair: OrderPair<CurrencyId>,
 	order_type: OrderType,
 	user: AccountId,
 	price: u64,
@@ -264,8 +345,42 @@ pub struct BidDetail<AccountId, BlockNumber>
 pub type BidDetailT<T> = BidDetail<
 	<T as system::Trait>::AccountId,
 	<T as system::Trait>::BlockNumber,
+	<T as Trait>::CurrencyId,
 >;
 
+pub type BalanceOf<T> = <<T as Trait>::Currencies as MultiCurrency<<T as system::Trait>::AccountId>>::Balance;
+
+
+/// Liquidity-mining program of a pair.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct RewardParams {
+	pub emission: u64,  // reward points handed out each block
+	pub band: Permill,  // how far from the best bid/ask an order may rest and still earn
+}
+
+/// Bounds on the bookkeeping of matches and on the orders liquidity mining looks at.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct MatchCaps {
+	pub fill_index: u32,      // counterparty indexes an order keeps, the full record is in `MatchOrder` events
+	pub sampled_orders: u32,  // resting orders per side of a pair earning rewards in one block
+}
+
+impl Default for MatchCaps {
+	fn default() -> Self {
+		MatchCaps { fill_index: 16, sampled_orders: 100 }
+	}
+}
+
+/// One step of the volume-tiered fee schedule, rates in parts per million.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct FeeTier {
+	pub volume: u64,     // volume traded in a quote currency within `VolumeWindow` to reach the tier
+	pub maker_fee: i32,  // a negative maker fee is a rebate
+	pub taker_fee: u32,
+}
 
 pub struct LinkedMultiKey<T: Trait>(PhantomData<T>);
 impl<T: Trait> LinkedNodeCollection for LinkedMultiKey<T> {
@@ -277,6 +392,28 @@ impl<T: Trait> LinkedNodeCollection for LinkedMultiKey<T> {
 pub trait Trait: system::Trait + balances::Trait + token::Trait {
 	/// The overarching event type.
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+	/// Identifies a currency that can be traded. Version 1 pairs name a `TokenId`.
+	type CurrencyId: Parameter + Member + Copy + Default + MaybeSerializeDebug + From<TokenId>;
+
+	/// The currencies orders are placed and settled in. Funds backing an open order are reserved.
+	type Currencies: MultiCurrency<Self::AccountId, CurrencyId = Self::CurrencyId>;
+
+	/// The currency transaction fees are computed in, as `TakeTokenFees` converts them.
+	type NativeCurrencyId: Get<Self::CurrencyId>;
+
+	/// The signature makers use to sign off-chain orders.
+	type Signature: Parameter + Verify<Signer = <Self as system::Trait>::AccountId>;
+
+	/// Number of blocks traded volume is counted over to pick an account's fee tier.
+	type VolumeWindow: Get<Self::BlockNumber>;
+
+	/// Number of blocks a finished or canceled order stays in state before it is archived, at
+	/// least one: orders closed in a block are archived in a later one.
+	type OrderRetention: Get<Self::BlockNumber>;
+
+	/// The origin that may list and delist pairs and set fees, reward programs and match caps.
+	type ParameterOrigin: EnsureOrigin<Self::Origin>;
 }
 
 decl_module! {
@@ -284,74 +421,288 @@ decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event() = default;
 
-	    fn put_order_and_match(origin, orderpair:OrderPair, ordertype:OrderType, amount:u64, price:u64) -> Result{
+	    fn put_order_and_match(origin, orderpair:OrderPairOf<T>, ordertype:OrderType, amount:u64, price:u64) -> Result{
 		    let sender = ensure_signed(origin)?;
+		    Self::ensure_migrated()?;
 		    Self::check_order(sender,orderpair,ordertype,amount,price)?;
 
 		    Ok(())
 		}
 
-		fn cancel_order(origin, orderpair:OrderPair, index:u128) -> Result {
+		fn cancel_order(origin, orderpair:OrderPairOf<T>, index:u128) -> Result {
 		    let sender = ensure_signed(origin)?;
+		    Self::ensure_migrated()?;
 		    Self::do_cancel_order(&sender,orderpair,index)?;
             Ok(())
 		}
 
-		fn on_finalize() {
-            // handle the match of new orders
+		/// Settle `fill_amount` between two crossing off-chain orders, given in any order, at the
+		/// maker's price. The order valid for longer is the maker, the sell order when both expire
+		/// together. Any account may relay the orders; the makers pay nothing for quoting.
+		fn settle_signed_orders(origin, first_order:SignedOrderT<T>, second_order:SignedOrderT<T>, fill_amount:u64) -> Result {
+		    let relayer = ensure_signed(origin)?;
+		    Self::ensure_migrated()?;
+		    Self::do_settle_signed_orders(&relayer,first_order,second_order,fill_amount)?;
+		    Ok(())
+		}
+
+		/// Void a single off-chain order signed by the sender.
+		fn cancel_signed_order(origin, order:OffchainOrderT<T>) -> Result {
+		    let sender = ensure_signed(origin)?;
+		    Self::ensure_migrated()?;
+		    ensure!(order.maker == sender, "not permitted");
+		    let hash = T::Hashing::hash_of(&order);
+		    <SignedOrderCanceled<T>>::insert(hash, true);
+		    Self::deposit_event(RawEvent::CancelSignedOrder(sender,hash));
+		    Ok(())
+		}
+
+		/// Void every off-chain order of the sender with a nonce lower than `nonce`.
+		fn set_min_order_nonce(origin, nonce:u64) -> Result {
+		    let sender = ensure_signed(origin)?;
+		    Self::ensure_migrated()?;
+		    ensure!(nonce > Self::min_order_nonce(&sender), "nonce can only increase");
+		    <MinOrderNonce<T>>::insert(&sender, nonce);
+		    Ok(())
+		}
+
+		/// Set the per-block emission and price band of a pair's liquidity-mining program.
+		fn set_reward_params(origin, orderpair:OrderPairOf<T>, emission:u64, band:Permill) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    Self::is_vaild_pair(&orderpair)?;
+		    <RewardParamsOf<T>>::insert(orderpair, RewardParams{ emission, band });
+		    Ok(())
+		}
+
+		/// Set the currency reward points are paid out in.
+		fn set_reward_token(origin, currency:T::CurrencyId) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    ensure!( T::Currencies::currency_exists(currency) , "invalid tokentype");
+		    <RewardToken<T>>::put(currency);
+		    Ok(())
+		}
+
+		/// Pay the sender's accrued reward points out of the reward pot, one unit per point.
+		fn claim_rewards(origin) -> Result {
+		    let sender = ensure_signed(origin)?;
+		    Self::ensure_migrated()?;
+		    let points = Self::reward_points(&sender);
+		    ensure!( points != 0u64 , "no rewards to claim");
+		    let currency = Self::reward_token().ok_or("no reward token set")?;
+		    T::Currencies::transfer(currency, &Self::reward_pot(), &sender, Self::balance(points))?;
+		    <RewardPoints<T>>::remove(&sender);
+		    Self::deposit_event(RawEvent::ClaimRewards(sender,points));
+		    Ok(())
+		}
+
+		/// Replace the fee schedule. Tiers must be sorted by volume, starting at zero so every
+		/// account has one, and no maker rebate may exceed the taker fee of the same tier. An empty
+		/// schedule disables fees.
+		fn set_fee_schedule(origin, schedule:Vec<FeeTier>) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    ensure!( schedule.first().map_or(true, |tier| tier.volume == 0) , "the first fee tier must start at zero volume");
+		    ensure!( schedule.windows(2).all(|w| w[0].volume < w[1].volume) , "fee tiers must be sorted by volume");
+		    for tier in schedule.iter() {
+		        ensure!( tier.taker_fee <= FEE_DENOMINATOR , "fee rate above 100%");
+		        ensure!( tier.maker_fee <= FEE_DENOMINATOR as i32 , "fee rate above 100%");
+		        ensure!( tier.maker_fee >= -(tier.taker_fee as i32) , "maker rebate exceeds the taker fee");
+		    }
+		    FeeSchedule::put(schedule);
+		    Ok(())
+		}
+
+		/// Allow transaction fees to be paid in `currency` at `rate`, or disallow it with `None`.
+		fn set_fee_currency(origin, currency:T::CurrencyId, rate:Option<FeeRate>) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    match rate {
+		        Some(rate) => {
+		            ensure!( T::Currencies::currency_exists(currency) , "invalid tokentype");
+		            match rate {
+		                FeeRate::Fixed{ native, .. } => ensure!( native != 0u64 , "fee rate of zero native units"),
+		                FeeRate::Dex{ min_depth } => ensure!( min_depth != 0u64 , "fee rate of zero depth"),
+		            }
+		            <FeeCurrencies<T>>::insert(currency, rate);
+		        },
+		        None => <FeeCurrencies<T>>::remove(currency),
+		    }
+		    Ok(())
+		}
+
+		/// Pay `amount` of the transaction fees collected in `currency` out to `dest`.
+		fn spend_transaction_fees(origin, currency:T::CurrencyId, dest:T::AccountId, amount:u64) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    T::Currencies::transfer(currency, &Self::transaction_fee_pot(), &dest, Self::balance(amount))?;
+		    Self::deposit_event(RawEvent::SpendTransactionFees(currency, dest, amount));
+		    Ok(())
+		}
+
+		/// Open `orderpair` for trading.
+		fn list_pair(origin, orderpair:OrderPairOf<T>) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    ensure!( orderpair.first != orderpair.second , "a pair needs two currencies");
+		    ensure!( T::Currencies::currency_exists(orderpair.first) && T::Currencies::currency_exists(orderpair.second) ,
+		             "invalid tokentype");
+		    Self::add_new_order_pair(orderpair.clone())?;
+		    Self::deposit_event(RawEvent::ListPair(orderpair));
+		    Ok(())
+		}
+
+		/// Close `orderpair` to new orders and end its liquidity-mining program. Orders resting on
+		/// the book stay there until their owners cancel them.
+		fn delist_pair(origin, orderpair:OrderPairOf<T>) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    let mut pair_list = Self::order_pair_list();
+		    let position = pair_list.iter().position(|pair| *pair == orderpair).ok_or("order pair is not listed")?;
+		    pair_list.remove(position);
+		    <OrderPairList<T>>::put(pair_list);
+		    <RewardParamsOf<T>>::remove(&orderpair);
+		    Self::deposit_event(RawEvent::DelistPair(orderpair));
+		    Ok(())
+		}
+
+		/// Set how many counterparties an order keeps track of and how many resting orders per
+		/// side of a pair earn rewards each block. Neither cap may be zero.
+		fn set_match_caps(origin, caps:MatchCaps) -> Result {
+		    T::ParameterOrigin::ensure_origin(origin)?;
+		    Self::ensure_migrated()?;
+		    ensure!(caps.fill_index != 0, "fill index cap of zero");
+		    ensure!(caps.sampled_orders != 0, "sampled orders cap of zero");
+		    Caps::put(caps);
+		    Ok(())
+		}
+
+		fn on_initialize(n: T::BlockNumber) {
+            if Self::storage_version() < STORAGE_VERSION {
+                Self::on_runtime_upgrade();
+            }
+            for index in <ClosedOrdersAt<T>>::take(n) {
+                Self::archive_order(index);
+            }
+            Self::accrue_liquidity_rewards();
 		}
 
 	}
 }
 
 decl_event!(
-	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-		KeyChanged(AccountId),
+	pub enum Event<T> where
+		AccountId = <T as system::Trait>::AccountId,
+		Hash = <T as system::Trait>::Hash,
+		OrderPair = OrderPairOf<T>,
+		CurrencyId = <T as Trait>::CurrencyId,
+	{
 		// who index pair type(sell/buy) amount price
 		NewOrder(AccountId,u128,OrderPair,OrderType,u64,u64),
         // who index
 		CancelOrder(AccountId,u128),
 		// who1 who2 index1 index2 OrderPair amount price
 		MatchOrder(AccountId,AccountId,u128,u128,OrderPair,u64,u64),
+		// relayer maker taker maker_hash taker_hash OrderPair amount price
+		SettleSignedOrder(AccountId,AccountId,AccountId,Hash,Hash,OrderPair,u64,u64),
+		// who hash
+		CancelSignedOrder(AccountId,Hash),
+		// who amount
+		ClaimRewards(AccountId,u64),
+		// receipt of a closed order removed from state
+		// who index pair type(sell/buy) amount price left status
+		ArchiveOrder(AccountId,u128,OrderPair,OrderType,u64,u64,u64,OrderStatus),
+		// pair opened for trading
+		ListPair(OrderPair),
+		// pair closed to new orders
+		DelistPair(OrderPair),
+		// currency dest amount of transaction fees paid out of the pot
+		SpendTransactionFees(CurrencyId,AccountId,u64),
 	}
 );
 
 decl_storage! {
 	trait Store for Module<T: Trait> as Dex {
-		/// The `AccountId` of the sudo key.
-		Key get(key) config(): T::AccountId;
         //BP get(bp) config(): OrderPair;
 		/// each order have an uinque u128 index
 		pub OrderIndex get(order_index): u128 = 0;
 
         /// Order pair list
-        pub OrderPairList get(order_pair_list): Vec<OrderPair> ;
+        pub OrderPairList get(order_pair_list): Vec<OrderPairOf<T>> ;
 
 		/// save the data
-        pub BidListHeaderFor get(bidlist_header_for): map (OrderPair,OrderType) => Option<MultiNodeIndex<(OrderPair,OrderType), BidT<T>>>;
-        pub BidListTailFor get(bidlist_tail_for): map (OrderPair,OrderType) => Option<MultiNodeIndex<(OrderPair,OrderType), BidT<T>>>;
+        pub BidListHeaderFor get(bidlist_header_for): map (OrderPairOf<T>,OrderType) => Option<MultiNodeIndex<(OrderPairOf<T>,OrderType), BidT<T>>>;
+        pub BidListTailFor get(bidlist_tail_for): map (OrderPairOf<T>,OrderType) => Option<MultiNodeIndex<(OrderPairOf<T>,OrderType), BidT<T>>>;
         pub BidListCache get(bidlist_cache): map u128 => Option<Node<BidT<T>>>;
         NodeId get(nodeid):u128;
         pub BidOf get(bid_of):map u128 => Option<BidDetailT<T>>;
 
         // real order record
         pub OrderInfor get(order_info): map u128 => Option<OrderInfo<T>>;
+        /// open orders of each account on pairs with each currency
+        pub OpenOrdersOf get(open_orders_of): map (T::AccountId, T::CurrencyId) => Vec<u128>;
+        /// closed orders to archive at the start of each block
+        pub ClosedOrdersAt get(closed_orders_at): map T::BlockNumber => Vec<u128>;
+        /// version of the storage layout, behind `STORAGE_VERSION` until migrations finish
+        pub StorageVersion get(storage_version) build(|_| STORAGE_VERSION): u32;
+        /// last legacy order index looked at by the clean-up
+        OrderMigrationCursor get(order_migration_cursor): u128;
+        /// last order index the running migration step looks at, fixed when the step starts
+        OrderMigrationEnd get(order_migration_end): Option<u128>;
+
+        /// amount already settled of each off-chain order, keyed by order hash
+        pub SignedOrderFilled get(signed_order_filled): map T::Hash => u64;
+        /// off-chain orders voided by their maker
+        pub SignedOrderCanceled get(signed_order_canceled): map T::Hash => bool;
+        /// off-chain orders with a lower nonce than this are no longer valid
+        pub MinOrderNonce get(min_order_nonce): map T::AccountId => u64;
+
+        /// liquidity-mining program of each pair
+        pub RewardParamsOf get(reward_params_of): map OrderPairOf<T> => RewardParams;
+        /// the currency reward points are paid out in
+        pub RewardToken get(reward_token) config(): Option<T::CurrencyId>;
+        /// unclaimed reward points of each account
+        pub RewardPoints get(reward_points): map T::AccountId => u64;
+        /// position in the pair list the next block's rewards start accruing at
+        RewardCursor get(reward_cursor): u32;
+
+        /// fee tiers by ascending volume, trading is free while empty
+        pub FeeSchedule get(fee_schedule) config(): Vec<FeeTier>;
+        /// traded volume of each account by quote currency, bucketed by block
+        pub TradedVolume get(traded_volume_buckets): map (T::AccountId, T::CurrencyId) => Vec<(T::BlockNumber, u64)>;
+
+        /// currencies transaction fees may be paid in, with their rate against the native fee
+        pub FeeCurrencies get(fee_rate): map T::CurrencyId => Option<FeeRate>;
+
+        /// bounds on match bookkeeping and liquidity mining
+        pub Caps get(match_caps) config(): MatchCaps;
 	}
 		add_extra_genesis {
-		    config(bp):  u64;
+		    // pairs open for trading from genesis, with their liquidity-mining program
+		    config(pairs): Vec<(OrderPairOf<T>, RewardParams)>;
             build(|config: &GenesisConfig<T>|  {
-                OrderPairList::put(vec![OrderPair{
-                    first: vec![1u8,2],
-                    second: vec![3u8,4],
-                }]);
+                for (pair, params) in config.pairs.iter() {
+                    <Module<T>>::add_new_order_pair(pair.clone()).expect("pairs in the dex genesis are unique");
+                    if params.emission != 0 {
+                        <RewardParamsOf<T>>::insert(pair, *params);
+                    }
+                }
 			});
 		}
 }
 
+/// Accounts frozen on a token, or taken off its allowlist, have their orders on it cancelled.
+impl<T: Trait> token::OnAccountFrozen<T::AccountId> for Module<T> {
+	fn on_account_frozen(token:TokenId, who:&T::AccountId) {
+		Self::cancel_open_orders(who, token.into());
+	}
+}
+
 impl<T: Trait> Module<T> {
 	pub fn check_order(
 		who: T::AccountId,
-		pair: OrderPair,
+		pair: OrderPairOf<T>,
 		ordertype: OrderType,
 		amount: u64,
 		price: u64,) -> Result{
@@ -360,6 +711,8 @@ impl<T: Trait> Module<T> {
         ensure!( price != 0u64 , "price can not be 0.");
 		ensure!( amount != 0u64 , "amount can not be 0.");
 		Self::is_vaild_pair(&pair)?;
+		Self::ensure_can_trade(&who,&pair)?;
+		Self::ensure_book_settles(&pair,ordertype,amount,price)?;
 		Self::enough_token_and_lock(&who,ordertype.clone(),&pair,price,amount)?;
 
 		// save the new order and deposit new event
@@ -368,20 +721,143 @@ impl<T: Trait> Module<T> {
 																				   unique_index as u128,
 																        <system::Module<T>>::block_number(), ) });
         // match order and change the status of old order and modify tokens
-		Self::insert_and_match_order(order.ordertype.clone(),&mut order);
+		Self::insert_and_match_order(order.ordertype.clone(),&mut order)?;
 		Ok(())
 	}
 
 	pub fn enough_token_and_lock(who:&T::AccountId, ordertype:OrderType,
-								 pair:&OrderPair, price:u64, amount:u64) -> Result {
-		//T::Token::lock   <T as Token::Trait>::lock <token::Module<T>>::
-		<token::Module<T>>::lock(&who,(||{match ordertype {
-			OrderType::Buy => &pair.second,
-			OrderType::Sell => &pair.first, }})(),
-					   (||{match ordertype {
-						   OrderType::Buy => price*amount,
-						   OrderType::Sell => amount, }})()
-		)?;
+								 pair:&OrderPairOf<T>, price:u64, amount:u64) -> Result {
+		let (currency, value) = match ordertype {
+			OrderType::Buy => (pair.second, price.checked_mul(amount).ok_or("overflow in order amount")?),
+			OrderType::Sell => (pair.first, amount),
+		};
+		Self::lock_for_order(who, currency, value)
+	}
+
+	/// Check that every resting order a new order would match can be settled, before anything is
+	/// locked or matched: a failed dispatch keeps its storage changes.
+	fn ensure_book_settles(pair:&OrderPairOf<T>, ordertype:OrderType, amount:u64, price:u64) -> Result {
+		let find_type = match ordertype {
+			OrderType::Buy => OrderType::Sell,
+			OrderType::Sell => OrderType::Buy,
+		};
+		let mut need_fill = amount;
+		let mut next = Self::bidlist_header_for((pair.clone(), find_type)).map(|header| header.index());
+		while let Some(node) = next.and_then(|index| Self::bidlist_cache(index)) {
+			let crosses = match ordertype {
+				OrderType::Buy => price >= node.data.price,
+				OrderType::Sell => price <= node.data.price,
+			};
+			if need_fill == 0 || !crosses {
+				break;
+			}
+			for id in node.data.list.iter() {
+				if need_fill == 0 {
+					break;
+				}
+				if let Some(bid) = Self::bid_of(id) {
+					let fill = rstd::cmp::min(need_fill, bid.amount);
+					Self::ensure_maker_settles(&bid, fill)?;
+					need_fill -= fill;
+				}
+			}
+			next = node.next();
+		}
+		Ok(())
+	}
+
+	/// The owner of the resting `bid` may trade and reserved enough to settle `amount` of it.
+	fn ensure_maker_settles(bid:&BidDetailT<T>, amount:u64) -> Result {
+		ensure!( <OrderInfor<T>>::exists(bid.id) , "cann't find this maker order");
+		Self::ensure_can_trade(&bid.user, &bid.pair)?;
+		let (currency, value) = match bid.order_type {
+			OrderType::Buy => (bid.pair.second, amount.checked_mul(bid.price).ok_or("overflow in settlement amount")?),
+			OrderType::Sell => (bid.pair.first, amount),
+		};
+		ensure!( T::Currencies::reserved_balance(currency, &bid.user) >= Self::balance(value) ,
+				 "reserved balance too low");
+		Ok(())
+	}
+
+	// order amounts are u64 whatever the balance type of the currency
+	fn balance(value:u64) -> BalanceOf<T> {
+		value.saturated_into()
+	}
+
+	/// Both currencies of `pair` must be ones `who` may receive and move, the settlement of a
+	/// match moves both. This keeps out accounts with token balances still to migrate.
+	fn ensure_can_trade(who:&T::AccountId, pair:&OrderPairOf<T>) -> Result {
+		ensure!( T::Currencies::can_hold(pair.first, who) && T::Currencies::can_hold(pair.second, who) ,
+				 "account may not trade this pair");
+		Ok(())
+	}
+
+	/// Add an open order to `OpenOrdersOf` under both currencies of its pair.
+	pub fn index_open_order(order:&OrderInfo<T>) {
+		for currency in [order.pair.first, order.pair.second].iter() {
+			<OpenOrdersOf<T>>::mutate((order.who.clone(), *currency), |indexes| if !indexes.contains(&order.index) {
+				indexes.push(order.index);
+			});
+		}
+	}
+
+	/// The number of orders `who` has open in `currency`, read without decoding them.
+	pub fn open_order_count(who:&T::AccountId, currency:T::CurrencyId) -> u32 {
+		<OpenOrdersOf<T>>::decode_len((who.clone(), currency)).unwrap_or(0) as u32
+	}
+
+	fn unindex_open_order(order:&OrderInfo<T>) {
+		for currency in [order.pair.first, order.pair.second].iter() {
+			let key = (order.who.clone(), *currency);
+			let mut indexes = Self::open_orders_of(&key);
+			indexes.retain(|index| *index != order.index);
+			if indexes.is_empty() {
+				<OpenOrdersOf<T>>::remove(&key);
+			} else {
+				<OpenOrdersOf<T>>::insert(&key, indexes);
+			}
+		}
+	}
+
+	/// Cancel every open order of `who` on a pair with `currency`.
+	pub fn cancel_open_orders(who:&T::AccountId, currency:T::CurrencyId) {
+		for index in Self::open_orders_of((who.clone(), currency)) {
+			if let Some(order) = Self::order_info(index) {
+				let _ = Self::do_cancel_order(who, order.pair, index);
+			}
+		}
+	}
+
+	/// Reserve `value` of free `currency` of `who` for an order.
+	fn lock_for_order(who:&T::AccountId, currency:T::CurrencyId, value:u64) -> Result {
+		ensure!( T::Currencies::free_balance(currency, who) >= Self::balance(value) , "Insufficient available balance");
+		T::Currencies::reserve(currency, who, Self::balance(value))
+	}
+
+	/// Release up to `value` of the `currency` `who` reserved for orders.
+	fn unlock_for_order(who:&T::AccountId, currency:T::CurrencyId, value:u64) {
+		T::Currencies::unreserve(currency, who, Self::balance(value));
+	}
+
+	/// Pay the seller `amount * price` and the buyer `amount` out of what each reserved. A buyer
+	/// who reserved at `lock_price` above `price` gets the difference back. Nothing moves unless
+	/// both may trade and reserved enough.
+	fn exchange(seller:&T::AccountId, buyer:&T::AccountId, pair:&OrderPairOf<T>, amount:u64, price:u64,
+				lock_price:u64) -> Result {
+		let money = amount.checked_mul(price).ok_or("overflow in settlement amount")?;
+		let locked = amount.checked_mul(rstd::cmp::max(price, lock_price)).ok_or("overflow in settlement amount")?;
+		Self::ensure_can_trade(seller, pair)?;
+		Self::ensure_can_trade(buyer, pair)?;
+		ensure!( T::Currencies::reserved_balance(pair.second, buyer) >= Self::balance(locked) , "reserved balance too low");
+		ensure!( T::Currencies::reserved_balance(pair.first, seller) >= Self::balance(amount) , "reserved balance too low");
+
+		if locked > money {
+			Self::unlock_for_order(buyer, pair.second, locked - money);
+		}
+		let unpaid = T::Currencies::repatriate_reserved(pair.second, buyer, seller, Self::balance(money))?;
+		ensure!( unpaid.is_zero() , "reserved balance too low");
+		let undelivered = T::Currencies::repatriate_reserved(pair.first, seller, buyer, Self::balance(amount))?;
+		ensure!( undelivered.is_zero() , "reserved balance too low");
 		Ok(())
 	}
 
@@ -391,13 +867,14 @@ impl<T: Trait> Module<T> {
 		let unique_index = OrderIndex::get() + 1;
 		let order : OrderInfo<T> = func(unique_index);
 		<OrderInfor<T>>::insert(unique_index.clone(),&order);
+		Self::index_open_order(&order);
 		OrderIndex::put(unique_index);
 		Self::deposit_event(RawEvent::NewOrder(order.who.clone(),unique_index,order.pair.clone(),
 		                    order.ordertype.clone(),order.amount,order.price));
 		order
 	}
 
-	pub fn insert_and_match_order(find_type: OrderType, order_info: &mut OrderInfo<T>){
+	pub fn insert_and_match_order(find_type: OrderType, order_info: &mut OrderInfo<T>) -> Result {
         // change the order information into biddetail
 		let mut new_biddetail = BidDetail{
 			id: order_info.index.clone(),
@@ -414,7 +891,7 @@ impl<T: Trait> Module<T> {
 			OrderType::Sell => OrderType::Buy,
 		};
 
-		Self::do_match(find_type, &mut new_biddetail);
+		Self::do_match(find_type, &mut new_biddetail)?;
 
 		if new_biddetail.amount == 0 {
 			//已被匹配完毕，则删除
@@ -424,9 +901,10 @@ impl<T: Trait> Module<T> {
 			//println!("如果还有剩余，则将其更新到bid_list中");
 			Self::insert_bid_list(&new_biddetail);
 		}
+		Ok(())
 	}
 
-	fn do_match(find_type: OrderType, in_bid_detail: &mut BidDetailT<T>) {
+	fn do_match(find_type: OrderType, in_bid_detail: &mut BidDetailT<T>) -> Result {
 		let mut need_fill: u64 = in_bid_detail.amount;
 		let mut remove_from_wait_bid_list: Vec<BidT<T>> = Vec::new();
 
@@ -488,19 +966,16 @@ impl<T: Trait> Module<T> {
 							//let maker_fee: u64 = Self::calculation_maker_fee(amount); // 手续费
 							//let taker_fee: u64 = Self::calculation_taker_fee(amount); // 手续费
 
-							let mut aaaaa = T::AccountId::default();
-							let mut bbbbb = T::AccountId::default();
-							match in_bid_detail.order_type{
-								OrderType::Buy => { aaaaa = maker_user; bbbbb = taker_user; }, // maker user = matchbid
-								OrderType::Sell =>{ aaaaa = taker_user; bbbbb = maker_user; }, // taker user = in_bid_detail
-							}
-							<token::Module<T>>::exchange_token(&aaaaa,&bbbbb,
-															   in_bid_detail.pair.first.clone(),
-															   in_bid_detail.pair.second.clone(),amount,match_bid.price,
-							                                   in_bid_detail.price);
-
+							let (seller, buyer) = match in_bid_detail.order_type {
+								OrderType::Buy => (&maker_user, &taker_user),
+								OrderType::Sell => (&taker_user, &maker_user),
+							};
 							Self::modify_order_and_generate_the_deal_record(match_bid.id,in_bid_detail.id,
-							                                                amount,match_bid.price);
+							                                                amount,match_bid.price)?;
+							Self::exchange(seller,buyer,&in_bid_detail.pair,amount,match_bid.price,
+							               in_bid_detail.price)?;
+							Self::charge_trade_fees(&maker_user,&taker_user,&in_bid_detail.pair,
+													in_bid_detail.order_type == OrderType::Buy,amount,match_bid.price)?;
 							if fill_num == 0 {
 								break;
 							}
@@ -521,9 +996,10 @@ impl<T: Trait> Module<T> {
 		}
 		// remove full matched bids
 		Self::remove_from_bid(&in_bid_detail.pair, find_type, &remove_from_wait_bid_list);
+		Ok(())
 	}
 
-	pub fn do_cancel_order(who:&T::AccountId, order2:OrderPair, index:u128) -> Result{
+	pub fn do_cancel_order(who:&T::AccountId, order2:OrderPairOf<T>, index:u128) -> Result{
 		let mut order = if let Some(mut order) = Self::order_info(index) {
 			if order.who != *who{ return Err("not permitted");}
             match order.status {
@@ -534,12 +1010,13 @@ impl<T: Trait> Module<T> {
 						OrderType::Buy => order.pair.second.clone(),
 						OrderType::Sell => order.pair.first.clone() ,
 					};
-					<token::Module<T>>::unlock(&order.who,&tokentype,order.left);
+					Self::unlock_for_order(&order.who,tokentype,order.left);
 
 				},
 				_ => return Err("Canceled or Finished"),
 			}
 			<OrderInfor<T>>::insert(order.index,order.clone());
+			Self::schedule_archive(order.index);
 			order
 		}else { return Err("cant find order"); };
 		if let Some(mut in_bid_detail) = <BidOf<T>>::get(index){
@@ -549,16 +1026,303 @@ impl<T: Trait> Module<T> {
 		Ok(())
 	}
 
-	pub fn is_vaild_pair(orderpair:&OrderPair) -> Result{
+	pub fn do_settle_signed_orders(relayer:&T::AccountId, first_order:SignedOrderT<T>,
+								   second_order:SignedOrderT<T>, fill_amount:u64) -> Result{
+		ensure!( fill_amount != 0u64 , "amount can not be 0.");
+		let first_hash = Self::check_signed_order(&first_order, fill_amount)?;
+		let second_hash = Self::check_signed_order(&second_order, fill_amount)?;
+		let (first, second) = (first_order.order, second_order.order);
+
+		// the relayer does not get to pick the price: the order valid for longer quoted it
+		let first_is_maker = first.expiry > second.expiry
+			|| (first.expiry == second.expiry && first.ordertype == OrderType::Sell);
+		let ((maker, maker_hash), (taker, taker_hash)) = if first_is_maker {
+			((first, first_hash), (second, second_hash))
+		} else {
+			((second, second_hash), (first, first_hash))
+		};
+
+		ensure!( maker.pair == taker.pair , "order pairs do not match");
+		ensure!( maker.ordertype != taker.ordertype , "orders are on the same side");
+		let (seller, buyer) = match maker.ordertype {
+			OrderType::Sell => (&maker, &taker),
+			OrderType::Buy => (&taker, &maker),
+		};
+		ensure!( buyer.price >= seller.price , "order prices do not cross");
+		Self::ensure_can_trade(&maker.maker, &maker.pair)?;
+		Self::ensure_can_trade(&taker.maker, &taker.pair)?;
+
+		// the maker's quote sets the price
+		let price = maker.price;
+		let money = fill_amount.checked_mul(price).ok_or("overflow in settlement amount")?;
+		let pair = &maker.pair;
+
+		// check both sides first, nothing is locked for off-chain orders yet
+		ensure!( T::Currencies::free_balance(pair.first, &seller.maker) >= Self::balance(fill_amount),
+				 "Insufficient available balance");
+		ensure!( T::Currencies::free_balance(pair.second, &buyer.maker) >= Self::balance(money),
+				 "Insufficient available balance");
+		Self::lock_for_order(&seller.maker, pair.first, fill_amount)?;
+		Self::lock_for_order(&buyer.maker, pair.second, money)?;
+		Self::exchange(&seller.maker, &buyer.maker, pair, fill_amount, price, price)?;
+		Self::charge_trade_fees(&maker.maker, &taker.maker, pair, maker.ordertype == OrderType::Sell,
+								fill_amount, price)?;
+
+		<SignedOrderFilled<T>>::mutate(maker_hash, |filled| *filled += fill_amount);
+		<SignedOrderFilled<T>>::mutate(taker_hash, |filled| *filled += fill_amount);
+		Self::deposit_event(RawEvent::SettleSignedOrder(relayer.clone(), maker.maker.clone(), taker.maker.clone(),
+														 maker_hash, taker_hash, maker.pair.clone(), fill_amount, price));
 		Ok(())
 	}
 
-	pub fn token_exchange(){
-		//<token::Module<T>>::exchange_token();
-		//<Self as Token<_>>::transfer(&sender,&dest,&tokentype,value)?;
+	/// What the maker of `order` signs: the order bound to this module and, by the genesis hash, to
+	/// this chain, so it cannot be replayed on another chain running the same runtime.
+	pub fn order_signing_payload(order:&OffchainOrderT<T>) -> Vec<u8> {
+		let genesis_hash = <system::Module<T>>::block_hash(T::BlockNumber::zero());
+		(ORDER_SIGNING_CONTEXT, genesis_hash, order).encode()
 	}
 
+	/// Check the signature, nonce, cancellation, expiry and remaining amount of an off-chain order.
+	/// Returns the hash the order is tracked by.
+	pub fn check_signed_order(signed_order:&SignedOrderT<T>, fill_amount:u64) -> rstd::result::Result<T::Hash, &'static str>{
+		let order = &signed_order.order;
+		ensure!( signed_order.signature.verify(&Self::order_signing_payload(order)[..], &order.maker) ,
+				 "invalid order signature");
+		ensure!( order.price != 0u64 , "price can not be 0.");
+		Self::is_vaild_pair(&order.pair)?;
+		ensure!( <system::Module<T>>::block_number() <= order.expiry , "order expired");
+		ensure!( order.nonce >= Self::min_order_nonce(&order.maker) , "order nonce is no longer valid");
+
+		let hash = T::Hashing::hash_of(order);
+		ensure!( !Self::signed_order_canceled(hash) , "Canceled or Finished");
+		let filled = Self::signed_order_filled(hash);
+		ensure!( filled.checked_add(fill_amount).map_or(false, |total| total <= order.amount),
+				 "fill amount exceeds the order's remaining amount");
+		Ok(hash)
+	}
 
+	/// The account holding the funds liquidity-mining rewards are paid from.
+	pub fn reward_pot() -> T::AccountId {
+		MODULE_ID.into_account()
+	}
+
+	/// Split each pair's emission pro-rata over the orders resting within its band. At most
+	/// `REWARD_PAIRS_PER_BLOCK` pairs are visited per block, in turns from `RewardCursor`; a
+	/// pair visited once every few blocks earns the emission of all of them.
+	fn accrue_liquidity_rewards() {
+		let pairs = Self::order_pair_list();
+		let count = pairs.len() as u32;
+		if count == 0 { return; }
+		let rounds = ((count + REWARD_PAIRS_PER_BLOCK - 1) / REWARD_PAIRS_PER_BLOCK) as u128;
+		let start = Self::reward_cursor() % count;
+		let end = rstd::cmp::min(start + REWARD_PAIRS_PER_BLOCK, count);
+		<system::Module<T>>::register_extra_weight_unchecked(REWARD_PAIR_WEIGHT * (end - start) as Weight);
+		RewardCursor::put(end % count);
+
+		for pair in pairs[start as usize..end as usize].iter() {
+			let params = Self::reward_params_of(pair);
+			if params.emission == 0u64 { continue; }
+			let emission = params.emission as u128 * rounds;
+
+			let mut makers: Vec<(T::AccountId, u64)> = Vec::new();
+			Self::sample_resting_orders(pair, OrderType::Buy, params.band, &mut makers);
+			Self::sample_resting_orders(pair, OrderType::Sell, params.band, &mut makers);
+
+			let total: u128 = makers.iter().map(|(_, amount)| *amount as u128).sum();
+			if total == 0 { continue; }
+			for (who, amount) in makers {
+				let points = rstd::cmp::min(emission * amount as u128 / total, u64::max_value() as u128) as u64;
+				if points != 0u64 {
+					<RewardPoints<T>>::mutate(&who, |p| *p = p.saturating_add(points));
+				}
+			}
+		}
+	}
+
+	// walk one side of the book from the best price until leaving the band
+	fn sample_resting_orders(pair:&OrderPairOf<T>, ordertype:OrderType, band:Permill,
+							 makers:&mut Vec<(T::AccountId, u64)>) {
+		if let Some(header) = Self::bidlist_header_for((pair.clone(), ordertype)) {
+			let mut index = header.index();
+			let mut best_price: Option<u64> = None;
+			let mut sampled = 0u32;
+			let cap = Self::match_caps().sampled_orders;
+
+			while let Some(node) = Self::bidlist_cache(&index) {
+				let best = *best_price.get_or_insert(node.data.price);
+				let distance = band * best;
+				let within = match ordertype {
+					OrderType::Buy => node.data.price >= best.saturating_sub(distance),
+					OrderType::Sell => node.data.price <= best.saturating_add(distance),
+				};
+				if !within { break; }
+
+				for id in node.data.list.iter() {
+					if sampled >= cap { return; }
+					if let Some(bid) = Self::bid_of(id) {
+						makers.push((bid.user, bid.amount));
+						sampled += 1;
+					}
+				}
+
+				if let Some(next) = node.next() {
+					index = next;
+				} else {
+					break;
+				}
+			}
+		}
+	}
+
+	/// The account holding collected trading fees.
+	pub fn fee_pot() -> T::AccountId {
+		FEE_MODULE_ID.into_account()
+	}
+
+	/// Volume `who` traded within the last `VolumeWindow` blocks on the pairs quoted in `currency`,
+	/// in units of `currency`. Volumes in different quote currencies are never added up.
+	pub fn traded_volume(who:&T::AccountId, currency:T::CurrencyId) -> u64 {
+		let (start, bucket_len) = Self::volume_window();
+		Self::traded_volume_buckets((who.clone(), currency)).iter()
+			.filter(|(bucket, _)| *bucket + bucket_len > start)
+			.fold(0u64, |sum, (_, volume)| sum.saturating_add(*volume))
+	}
+
+	/// Index of the fee tier `who` trades in on pairs quoted in `currency`, `None` while no fee
+	/// schedule is set. The first tier starts at zero volume, so every account reaches it.
+	pub fn fee_tier_of(who:&T::AccountId, currency:T::CurrencyId) -> Option<u32> {
+		let schedule = Self::fee_schedule();
+		if schedule.is_empty() { return None; }
+		let volume = Self::traded_volume(who, currency);
+		let reached = schedule.iter().take_while(|tier| tier.volume <= volume).count();
+		Some(reached.saturating_sub(1) as u32)
+	}
+
+	// first block of the window and the length of one bucket
+	fn volume_window() -> (T::BlockNumber, T::BlockNumber) {
+		let window = T::VolumeWindow::get();
+		let bucket_len = rstd::cmp::max(window / T::BlockNumber::from(VOLUME_BUCKETS), One::one());
+		(<system::Module<T>>::block_number().saturating_sub(window), bucket_len)
+	}
+
+	fn record_volume(who:&T::AccountId, currency:T::CurrencyId, volume:u64) {
+		let (start, bucket_len) = Self::volume_window();
+		let now = <system::Module<T>>::block_number();
+		let current = now - now % bucket_len;
+		<TradedVolume<T>>::mutate((who.clone(), currency), |buckets| {
+			buckets.retain(|(bucket, _)| *bucket + bucket_len > start);
+			match buckets.last_mut() {
+				Some((bucket, total)) if *bucket == current => *total = total.saturating_add(volume),
+				_ => buckets.push((current, volume)),
+			}
+		});
+	}
+
+	fn fee_amount(value:u64, rate:u32) -> u64 {
+		(value as u128 * rate as u128 / FEE_DENOMINATOR as u128) as u64
+	}
+
+	/// Take each side's fee out of what it just received and pay the maker rebate, if any.
+	/// Rebates are paid from fees already collected in the same currency and never exceed them.
+	fn charge_trade_fees(maker:&T::AccountId, taker:&T::AccountId, pair:&OrderPairOf<T>,
+						 maker_is_seller:bool, amount:u64, price:u64) -> Result {
+		let volume = amount.saturating_mul(price);
+		let schedule = Self::fee_schedule();
+		if let (Some(maker_tier), Some(taker_tier)) = (Self::fee_tier_of(maker, pair.second),
+													   Self::fee_tier_of(taker, pair.second)) {
+			let maker_tier = schedule[maker_tier as usize];
+			let taker_tier = schedule[taker_tier as usize];
+			let (maker_token, maker_proceeds, taker_token, taker_proceeds) = if maker_is_seller {
+				(pair.second, volume, pair.first, amount)
+			} else {
+				(pair.first, amount, pair.second, volume)
+			};
+			let pot = Self::fee_pot();
+
+			let taker_fee = Self::fee_amount(taker_proceeds, taker_tier.taker_fee);
+			if taker_fee != 0u64 {
+				Self::move_fee(taker_token, taker, &pot, taker_fee)?;
+			}
+			if maker_tier.maker_fee >= 0 {
+				let maker_fee = Self::fee_amount(maker_proceeds, maker_tier.maker_fee as u32);
+				if maker_fee != 0u64 {
+					Self::move_fee(maker_token, maker, &pot, maker_fee)?;
+				}
+			} else {
+				let rebate = rstd::cmp::min(Self::fee_amount(maker_proceeds, (-maker_tier.maker_fee) as u32),
+											T::Currencies::free_balance(maker_token, &pot).saturated_into());
+				if rebate != 0u64 {
+					Self::move_fee(maker_token, &pot, maker, rebate)?;
+				}
+			}
+		}
+		Self::record_volume(maker, pair.second, volume);
+		Self::record_volume(taker, pair.second, volume);
+		Ok(())
+	}
+
+	/// Move a fee or rebate of `value` from `who` to `dest` through the reserve, like settlement
+	/// moves tokens. Neither side then needs a holder deposit or minimum balance, which a fee may not
+	/// cover, and the move cannot fail once the exchange is done.
+	fn move_fee(currency:T::CurrencyId, who:&T::AccountId, dest:&T::AccountId, value:u64) -> Result {
+		T::Currencies::reserve(currency, who, Self::balance(value))?;
+		let unpaid = T::Currencies::repatriate_reserved(currency, who, dest, Self::balance(value))?;
+		ensure!( unpaid.is_zero() , "fee not fully paid");
+		Ok(())
+	}
+
+	fn schedule_archive(index:u128) {
+		if let Some(order) = Self::order_info(index) {
+			Self::unindex_open_order(&order);
+		}
+		// the current block's queue has already been drained
+		let retention = rstd::cmp::max(T::OrderRetention::get(), One::one());
+		let at = <system::Module<T>>::block_number() + retention;
+		<ClosedOrdersAt<T>>::mutate(at, |indexes| indexes.push(index));
+	}
+
+	/// Deposit the receipt of a closed order and remove it from state.
+	fn archive_order(index:u128) {
+		if let Some(order) = Self::order_info(index) {
+			if order.status == OrderStatus::Valid { return; }
+			<OrderInfor<T>>::remove(index);
+			Self::deposit_event(RawEvent::ArchiveOrder(order.who, index, order.pair, order.ordertype,
+													   order.amount, order.price, order.left, order.status));
+		}
+	}
+
+	/// Bring storage written by an older runtime up to `STORAGE_VERSION`, one step per version.
+	/// Runs at the start of every block so it takes effect right after `set_code`; a step
+	/// returns `false` while it needs more blocks to finish.
+	pub fn on_runtime_upgrade() {
+		let mut version = Self::storage_version();
+		if version == STORAGE_VERSION {
+			return;
+		}
+		while version < STORAGE_VERSION {
+			<system::Module<T>>::register_extra_weight_unchecked(ORDER_MIGRATION_BATCH_WEIGHT);
+			let done = match version {
+				0 => migration::prune_closed_orders::<T>(),
+				1 => migration::migrate_to_token_ids::<T>(),
+				2 => migration::index_open_orders::<T>(),
+				_ => true,
+			};
+			if !done { break; }
+			version += 1;
+			StorageVersion::put(version);
+		}
+	}
+
+	/// Extrinsics wait for the migrations to finish, so no order is read or written in an older layout.
+	fn ensure_migrated() -> Result {
+		ensure!( Self::storage_version() == STORAGE_VERSION , "dex storage is being migrated");
+		Ok(())
+	}
+
+	pub fn is_vaild_pair(orderpair:&OrderPairOf<T>) -> Result{
+		ensure!( Self::order_pair_list().contains(orderpair) , "order pair is not listed");
+		Ok(())
+	}
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 	fn insert_bid_list(in_bid_detail: &BidDetailT<T>) {
@@ -615,12 +1379,12 @@ impl<T: Trait> Module<T> {
 					};
 
 					let n = Node::new(new_bid);
-					n.init_storage_withkey::<LinkedMultiKey<T>, (OrderPair, OrderType)>((
+					n.init_storage_withkey::<LinkedMultiKey<T>, (OrderPairOf<T>, OrderType)>((
 						in_bid_detail.pair.clone(),
 						in_bid_detail.order_type,
 					));
 
-					let _=node.add_option_node_before_withkey::<LinkedMultiKey<T>, (OrderPair,OrderType)>(n,(in_bid_detail.pair.clone(),in_bid_detail.order_type));
+					let _=node.add_option_node_before_withkey::<LinkedMultiKey<T>, (OrderPairOf<T>,OrderType)>(n,(in_bid_detail.pair.clone(),in_bid_detail.order_type));
 
 					finish = true;
 					break;
@@ -647,7 +1411,7 @@ impl<T: Trait> Module<T> {
 				useless: <T as balances::Trait>::Balance::from(0),
 			};
 			let n = Node::new(new_bid);
-			n.init_storage_withkey::<LinkedMultiKey<T>, (OrderPair, OrderType)>((
+			n.init_storage_withkey::<LinkedMultiKey<T>, (OrderPairOf<T>, OrderType)>((
 				in_bid_detail.pair.clone(),
 				in_bid_detail.order_type,
 			));
@@ -657,7 +1421,7 @@ impl<T: Trait> Module<T> {
 			{
 				if let Some(mut tail_node) = Self::bidlist_cache(tail_index.index()) {
 					let _ = tail_node
-						.add_option_node_after_withkey::<LinkedMultiKey<T>, (OrderPair, OrderType)>(
+						.add_option_node_after_withkey::<LinkedMultiKey<T>, (OrderPairOf<T>, OrderType)>(
 							n,
 							(in_bid_detail.pair.clone(), in_bid_detail.order_type),
 						);
@@ -702,14 +1466,14 @@ impl<T: Trait> Module<T> {
 		//更新node
 	}
 
-	fn remove_from_bid(pair: &OrderPair, order_type: OrderType, remove_bid: &Vec<BidT<T>>) {
+	fn remove_from_bid(pair: &OrderPairOf<T>, order_type: OrderType, remove_bid: &Vec<BidT<T>>) {
 		for nn in 0..remove_bid.len() {
 			if let Some(header) = Self::bidlist_header_for((pair.clone(), order_type)) {
 				let mut index = header.index();
 				//println!("&&&&&&&&&&&&&&&&&&&remove_from_bid {:?}",index);
 				while let Some(mut node) = Self::bidlist_cache(&index) {
 					if node.data.price == remove_bid[nn].price {
-						let _=node.remove_option_node_withkey::<LinkedMultiKey<T>, (OrderPair,OrderType)>((pair.clone(),order_type));
+						let _=node.remove_option_node_withkey::<LinkedMultiKey<T>, (OrderPairOf<T>,OrderType)>((pair.clone(),order_type));
 						break;
 					}
 
@@ -765,46 +1529,38 @@ impl<T: Trait> Module<T> {
 	}
 
 	// add new orderpair
-	pub fn add_new_order_pair(pair: OrderPair) -> Result {
-		let mut pair_list: Vec<OrderPair> = OrderPairList::get();
+	pub fn add_new_order_pair(pair: OrderPairOf<T>) -> Result {
+		let mut pair_list: Vec<OrderPairOf<T>> = <OrderPairList<T>>::get();
 		if pair_list.contains(&pair) {
 			return Err("already exist orderpair");
 		} else {
 			pair_list.push(pair);
-			OrderPairList::put(pair_list);
+			<OrderPairList<T>>::put(pair_list);
 			Ok(())
 		}
 	}
 
+	/// Record a fill of `amount` at `price` between the orders `index_a` and `index_b`. Both are
+	/// read before either is written, so a missing order changes nothing.
 	pub fn modify_order_and_generate_the_deal_record(index_a:u128, index_b:u128, amount:u64, price:u64) -> Result {
-		let mut order_a = if let Some(mut order_a) =
-		Self::order_info(index_a)
-		{
-			order_a.left = match order_a.left.checked_sub(amount){
-			    Some(b) => b,
-			    None => 0,
-			};
-			order_a.fill_index.push(index_b);
-			if order_a.left == 0u64 { order_a.status = OrderStatus::Finished; }
-			order_a
-		} else {
-			return Err("cann't find this maker order");
-		};
+		let cap = Self::match_caps().fill_index as usize;
+		let mut order_a = Self::order_info(index_a).ok_or("cann't find this maker order")?;
+		let mut order_b = Self::order_info(index_b).ok_or("cann't find this taker order")?;
+
+		order_a.left = order_a.left.saturating_sub(amount);
+		if order_a.fill_index.len() < cap { order_a.fill_index.push(index_b); }
+		if order_a.left == 0u64 {
+			order_a.status = OrderStatus::Finished;
+			Self::schedule_archive(index_a);
+		}
 		<OrderInfor<T>>::insert(order_a.index,order_a.clone());
 
-		let mut order_b = if let Some(mut order_b) =
-		Self::order_info(index_b)
-		{
-			order_b.left = match order_b.left.checked_sub(amount){
-			    Some(b) => b,
-			    None => 0,
-		    };
-			order_b.fill_index.push(index_a);
-			if order_b.left == 0u64 { order_b.status = OrderStatus::Finished; }
-			order_b
-		} else {
-			return Err("cann't find this maker order");
-		};
+		order_b.left = order_b.left.saturating_sub(amount);
+		if order_b.fill_index.len() < cap { order_b.fill_index.push(index_a); }
+		if order_b.left == 0u64 {
+			order_b.status = OrderStatus::Finished;
+			Self::schedule_archive(index_b);
+		}
 		<OrderInfor<T>>::insert(order_b.index,order_b.clone());
 		Self::deposit_event(RawEvent::MatchOrder(order_a.who,order_b.who,index_a,index_b,
 												 order_a.pair,amount,price));
@@ -99,6 +99,9 @@ impl<K, T: NodeT> MultiNodeIndex<K, T>
 where
     K: Codec + Clone + Eq + PartialEq + Default,
 {
+    pub fn new(index: T::Index, multi_key: K) -> Self {
+        MultiNodeIndex { index, multi_key }
+    }
     pub fn index(&self) -> T::Index {
         self.index.clone()
     }
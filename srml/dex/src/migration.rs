@@ -0,0 +1,232 @@
+//! Storage layouts of earlier versions of this module and the steps migrating them.
+//!
+//! Entries of an older layout are read straight from storage, decoded with the types below.
+
+use rstd::prelude::*;
+use codec::{Encode, Decode};
+use support::{StorageMap, StorageValue};
+use support::storage::{hashed, unhashed};
+
+use crate::*;
+
+/// `OrderPair` up to version 1, naming tokens by their type bytes.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct OrderPairV1 {
+	pub first: Vec<u8>,
+	pub second: Vec<u8>,
+}
+
+/// `OrderInformation` up to version 1.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct OrderInformationV1<Who, BlockNumber> {
+	pub who: Who,
+	pub ordertype: OrderType,
+	pub pair: OrderPairV1,
+	pub amount: u64,
+	pub price: u64,
+	pub left: u64,
+	pub status: OrderStatus,
+	pub index: u128,
+	pub time: BlockNumber,
+	pub fill_index: Vec<u128>,
+}
+
+/// `BidDetail` up to version 1.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BidDetailV1<AccountId, BlockNumber> {
+	pub id: u128,
+	pub pair: OrderPairV1,
+	pub order_type: OrderType,
+	pub user: AccountId,
+	pub price: u64,
+	pub amount: u64,
+	pub time: BlockNumber,
+}
+
+pub type OrderInfoV1<T> = OrderInformationV1<<T as system::Trait>::AccountId, <T as system::Trait>::BlockNumber>;
+pub type BidDetailV1T<T> = BidDetailV1<<T as system::Trait>::AccountId, <T as system::Trait>::BlockNumber>;
+pub type BookIndexV1<T> = MultiNodeIndex<(OrderPairV1, OrderType), BidT<T>>;
+
+/// Unhashed key of the entry `key` of the map `item` of this module.
+pub fn map_key<K: Encode>(item: &[u8], key: &K) -> Vec<u8> {
+	let mut final_key = b"Dex ".to_vec();
+	final_key.extend_from_slice(item);
+	key.encode_to(&mut final_key);
+	final_key
+}
+
+/// Unhashed key of the value `item` of this module.
+pub fn value_key(item: &[u8]) -> Vec<u8> {
+	let mut final_key = b"Dex ".to_vec();
+	final_key.extend_from_slice(item);
+	final_key
+}
+
+fn take_old<K: Encode, V: Decode>(item: &[u8], key: &K) -> Option<V> {
+	hashed::take(&runtime_io::blake2_256, &map_key(item, key))
+}
+
+fn pair_from_v1<T: Trait>(pair: &OrderPairV1) -> Option<OrderPairOf<T>> {
+	Some(OrderPair {
+		first: <token::Module<T>>::legacy_token(&pair.first)?.into(),
+		second: <token::Module<T>>::legacy_token(&pair.second)?.into(),
+	})
+}
+
+/// The order stored at `key` in the layout of version 1. Entries that do not decode as one
+/// exactly are left alone, they are no order of an older version.
+fn get_order_v1<T: Trait>(key: &[u8]) -> Option<OrderInfoV1<T>> {
+	let raw = hashed::get_raw(&runtime_io::blake2_256, key)?;
+	let input = &mut &raw[..];
+	let order = OrderInfoV1::<T>::decode(input).ok()?;
+	if input.is_empty() { Some(order) } else { None }
+}
+
+// the last order index the running step looks at, taken when the step starts
+fn migration_end() -> u128 {
+	OrderMigrationEnd::get().unwrap_or_else(|| {
+		let end = OrderIndex::get();
+		OrderMigrationEnd::put(end);
+		end
+	})
+}
+
+// move the cursor past `last`, true once every order up to `end` has been looked at
+fn finish_batch(last: u128, end: u128) -> bool {
+	if last >= end {
+		OrderMigrationCursor::kill();
+		OrderMigrationEnd::kill();
+		true
+	} else {
+		OrderMigrationCursor::put(last);
+		false
+	}
+}
+
+/// Version 0 -> 1: archive the closed orders and cap `fill_index` of the open orders
+/// stored before pruning existed, a batch per block.
+pub fn prune_closed_orders<T: Trait>() -> bool {
+	let cursor = OrderMigrationCursor::get();
+	let end = migration_end();
+	let last = rstd::cmp::min(cursor.saturating_add(ORDER_MIGRATION_BATCH), end);
+	let cap = Caps::get().fill_index as usize;
+	for index in (cursor + 1)..=last {
+		let key = map_key(b"OrderInfor", &index);
+		if let Some(mut order) = get_order_v1::<T>(&key) {
+			if order.status == OrderStatus::Valid {
+				if order.fill_index.len() > cap {
+					order.fill_index.truncate(cap);
+					hashed::put(&runtime_io::blake2_256, &key, &order);
+				}
+			} else {
+				hashed::kill(&runtime_io::blake2_256, &key);
+				let pair = pair_from_v1::<T>(&order.pair).unwrap_or_default();
+				<Module<T>>::deposit_event(RawEvent::ArchiveOrder(order.who, index, pair, order.ordertype,
+																  order.amount, order.price, order.left, order.status));
+			}
+		}
+	}
+	finish_batch(last, end)
+}
+
+/// Version 1 -> 2: tokens are named by the `CurrencyId` of their `TokenId`. The book and settings of every listed pair
+/// are re-keyed on the first call, the stored orders are rewritten a batch per block.
+pub fn migrate_to_token_ids<T: Trait>() -> bool {
+	let cursor = OrderMigrationCursor::get();
+	if cursor == 0 {
+		migrate_pairs::<T>();
+	}
+	let end = migration_end();
+	let last = rstd::cmp::min(cursor.saturating_add(ORDER_MIGRATION_BATCH), end);
+	for index in (cursor + 1)..=last {
+		let key = map_key(b"OrderInfor", &index);
+		if let Some(order) = get_order_v1::<T>(&key) {
+			match pair_from_v1::<T>(&order.pair) {
+				Some(pair) => <OrderInfor<T>>::insert(index, OrderInformation {
+					who: order.who,
+					ordertype: order.ordertype,
+					pair,
+					amount: order.amount,
+					price: order.price,
+					left: order.left,
+					status: order.status,
+					index: order.index,
+					time: order.time,
+					fill_index: order.fill_index,
+				}),
+				// neither token was ever valid, so nothing can be locked for the order
+				None => hashed::kill(&runtime_io::blake2_256, &key),
+			}
+		}
+	}
+	finish_batch(last, end)
+}
+
+fn migrate_pairs<T: Trait>() {
+	let old_pairs: Vec<OrderPairV1> = unhashed::get(&runtime_io::twox_128(&value_key(b"OrderPairList")))
+		.unwrap_or_default();
+	let mut pairs = Vec::new();
+	for old_pair in old_pairs.iter() {
+		let pair = match pair_from_v1::<T>(old_pair) {
+			Some(pair) => pair,
+			None => continue,
+		};
+		for ordertype in [OrderType::Buy, OrderType::Sell].iter() {
+			let old_key = (old_pair.clone(), *ordertype);
+			let new_key = (pair.clone(), *ordertype);
+			if let Some(tail) = take_old::<_, BookIndexV1<T>>(b"BidListTailFor", &old_key) {
+				<BidListTailFor<T>>::insert(&new_key, MultiNodeIndex::new(tail.index(), new_key.clone()));
+			}
+			if let Some(header) = take_old::<_, BookIndexV1<T>>(b"BidListHeaderFor", &old_key) {
+				<BidListHeaderFor<T>>::insert(&new_key, MultiNodeIndex::new(header.index(), new_key.clone()));
+				// nodes only hold bid ids, the bids themselves name the pair
+				let mut next = Some(header.index());
+				while let Some(node) = next.and_then(|index| <BidListCache<T>>::get(index)) {
+					for id in node.data.list.iter() {
+						if let Some(bid) = take_old::<_, BidDetailV1T<T>>(b"BidOf", id) {
+							<BidOf<T>>::insert(id, BidDetail {
+								id: bid.id,
+								pair: pair.clone(),
+								order_type: bid.order_type,
+								user: bid.user,
+								price: bid.price,
+								amount: bid.amount,
+								time: bid.time,
+							});
+						}
+					}
+					next = node.next();
+				}
+			}
+		}
+		if let Some(params) = take_old::<_, RewardParams>(b"RewardParamsOf", old_pair) {
+			<RewardParamsOf<T>>::insert(&pair, params);
+		}
+		pairs.push(pair);
+	}
+	<OrderPairList<T>>::put(pairs);
+
+	let old_reward_token: Option<Vec<u8>> = unhashed::take(&runtime_io::twox_128(&value_key(b"RewardToken")));
+	if let Some(token) = old_reward_token.and_then(|tokentype| <token::Module<T>>::legacy_token(&tokentype)) {
+		<RewardToken<T>>::put(T::CurrencyId::from(token));
+	}
+}
+
+/// Version 2 -> 3: list the open orders in `OpenOrdersOf`, a batch per block. No order is
+/// placed meanwhile, the extrinsics of the module wait for the migration.
+pub fn index_open_orders<T: Trait>() -> bool {
+	let cursor = OrderMigrationCursor::get();
+	let end = migration_end();
+	let last = rstd::cmp::min(cursor.saturating_add(ORDER_MIGRATION_BATCH), end);
+	for index in (cursor + 1)..=last {
+		if let Some(order) = <OrderInfor<T>>::get(index) {
+			if order.status == OrderStatus::Valid {
+				<Module<T>>::index_open_order(&order);
+			}
+		}
+	}
+	finish_batch(last, end)
+}
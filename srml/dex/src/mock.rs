@@ -1,12 +1,14 @@
+use std::cell::RefCell;
 use runtime_io;
-use support::{impl_outer_origin, parameter_types,};
+use support::{impl_outer_origin, impl_outer_dispatch, parameter_types, assert_ok, StorageValue, traits::Get};
 use sr_primitives::Perbill;
 use primitives::{H256, Blake2Hasher};
-use sr_primitives::traits::{IdentityLookup, Convert, OpaqueKeys, OnInitialize, SaturatedConversion};
+use sr_primitives::traits::{IdentityLookup, Convert, OpaqueKeys, OnInitialize, SaturatedConversion, Verify, Lazy};
 use sr_primitives::testing::{Header, UintAuthorityId};
 use codec::{Decode,Encode};
 use crate::linked_node::*;
-use crate::Trait;
+use crate::{Trait, OffchainOrder, SignedOrder};
+use token::TokenId;
 
 /// The AccountId alias in this test module.
 pub type AccountId = u64;
@@ -17,6 +19,12 @@ impl_outer_origin!{
 	pub enum Origin for Test {}
 }
 
+impl_outer_dispatch! {
+	pub enum Call for Test where origin: Origin {
+		dex::Dex,
+	}
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Test;
 
@@ -68,17 +76,119 @@ impl balances::Trait for Test {
     type WeightToFee = ();
 }
 
+parameter_types! {
+    pub const TokenDeposit: Balance = 0;
+}
+
+thread_local! {
+    static HOLDER_DEPOSIT: RefCell<Balance> = RefCell::new(0);
+    static DUST: RefCell<Vec<(TokenId, u64)>> = RefCell::new(Vec::new());
+}
+
+pub struct HolderDeposit;
+impl Get<Balance> for HolderDeposit {
+    fn get() -> Balance { HOLDER_DEPOSIT.with(|v| *v.borrow()) }
+}
+
+/// Reserve `deposit` for every token holder created in the current test instead of nothing.
+pub fn set_holder_deposit(deposit: Balance) {
+    HOLDER_DEPOSIT.with(|v| *v.borrow_mut() = deposit);
+}
+
+/// Records the dust burned in the current test.
+pub struct RecordDust;
+impl token::OnTokenDust for RecordDust {
+    fn on_dust(token: TokenId, amount: u64) {
+        DUST.with(|v| v.borrow_mut().push((token, amount)));
+    }
+}
+
+/// How much of `token` was burned as dust in the current test.
+pub fn dust_removed(token: TokenId) -> u64 {
+    DUST.with(|v| v.borrow().iter().filter(|(t, _)| *t == token).map(|(_, amount)| amount).sum())
+}
+
 impl token::Trait for Test {
     type Token = token::Module<Self>;
     type Event = ();
+    type Currency = balances::Module<Self>;
+    type TokenDeposit = TokenDeposit;
+    type OnAccountFrozen = crate::Module<Self>;
+    type HolderDeposit = HolderDeposit;
+    type DustRemoval = RecordDust;
+}
+
+/// A signature that is valid when its account and message match, so tests need no keys.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+pub struct TestSignature(pub AccountId, pub Vec<u8>);
+
+impl Verify for TestSignature {
+    type Signer = AccountId;
+    fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId) -> bool {
+        *signer == self.0 && msg.get() == &self.1[..]
+    }
+}
+
+thread_local! {
+    static ORDER_RETENTION: RefCell<u64> = RefCell::new(10);
+}
+
+pub struct OrderRetention;
+impl Get<u64> for OrderRetention {
+    fn get() -> u64 { ORDER_RETENTION.with(|v| *v.borrow()) }
 }
 
+/// Keep closed orders for `blocks` in the current test instead of 10.
+pub fn set_order_retention(blocks: u64) {
+    ORDER_RETENTION.with(|v| *v.borrow_mut() = blocks);
+}
+
+parameter_types! {
+    pub const VolumeWindow: u64 = 30;
+    // the first token a test creates stands in for the native currency
+    pub const NativeCurrencyId: TokenId = 0;
+}
 impl Trait for Test {
     type Event = ();
+    type CurrencyId = TokenId;
+    type Currencies = token::Module<Self>;
+    type NativeCurrencyId = NativeCurrencyId;
+    type Signature = TestSignature;
+    type VolumeWindow = VolumeWindow;
+    type OrderRetention = OrderRetention;
+    type ParameterOrigin = system::EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+    pub const LaunchPeriod: u64 = 2;
+    pub const VotingPeriod: u64 = 2;
+    pub const EmergencyVotingPeriod: u64 = 1;
+    pub const MinimumDeposit: u64 = 1;
+    pub const EnactmentPeriod: u64 = 2;
+    pub const CooloffPeriod: u64 = 2;
+}
+/// Public referenda are the only way to pass a proposal, their enactment dispatches as root.
+impl democracy::Trait for Test {
+    type Proposal = Call;
+    type Event = ();
+    type Currency = balances::Module<Self>;
+    type EnactmentPeriod = EnactmentPeriod;
+    type LaunchPeriod = LaunchPeriod;
+    type VotingPeriod = VotingPeriod;
+    type EmergencyVotingPeriod = EmergencyVotingPeriod;
+    type MinimumDeposit = MinimumDeposit;
+    type ExternalOrigin = system::EnsureNever<()>;
+    type ExternalMajorityOrigin = system::EnsureNever<()>;
+    type ExternalDefaultOrigin = system::EnsureNever<()>;
+    type FastTrackOrigin = system::EnsureNever<()>;
+    type CancellationOrigin = system::EnsureNever<()>;
+    type VetoOrigin = system::EnsureNever<AccountId>;
+    type CooloffPeriod = CooloffPeriod;
 }
 
 pub type System = system::Module<Test>;
 pub type Balances = balances::Module<Test>;
+pub type Democracy = democracy::Module<Test>;
 
 use crate::Module;
 pub type Dex = Module<Test>;
@@ -114,7 +224,28 @@ pub fn node_test(){
 }
 
 pub fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
-    system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+    set_order_retention(10);
+    set_holder_deposit(0);
+    DUST.with(|v| v.borrow_mut().clear());
+    let mut ext: runtime_io::TestExternalities<Blake2Hasher> =
+        system::GenesisConfig::default().build_storage::<Test>().unwrap().into();
+    // as at genesis, storage starts out in the current layout
+    runtime_io::with_externalities(&mut ext, || crate::StorageVersion::put(crate::STORAGE_VERSION));
+    ext
+}
+
+/// Register a token issued by account 1, with 3 decimals and no supply cap.
+pub fn new_token(symbol: &[u8]) -> TokenId {
+    let token = TokenT::next_token_id();
+    assert_ok!(TokenT::create_token(Origin::signed(1), symbol.to_vec(), symbol.to_vec(), 3, u64::max_value()));
+    token
+}
+
+pub fn sign_order(order: OffchainOrder<AccountId, BlockNumber, TokenId>)
+    -> SignedOrder<AccountId, BlockNumber, TokenId, TestSignature>
+{
+    let signature = TestSignature(order.maker, Dex::order_signing_payload(&order));
+    SignedOrder { order, signature }
 }
 
 pub fn printorder(index:u128){
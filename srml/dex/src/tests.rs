@@ -2,9 +2,12 @@
 
 use super::*;
 use runtime_io::with_externalities;
-use sr_primitives::traits::OnInitialize;
+use sr_primitives::traits::{OnInitialize, OnFinalize, SignedExtension};
+use sr_primitives::weights::DispatchInfo;
 use support::{assert_ok, assert_noop, assert_eq_uvec, StorageLinkedMap,StorageMap};
 use mock::*;
+use migration::*;
+use support::storage::{hashed, unhashed};
 use support::traits::{Currency, ReservableCurrency};
 
 
@@ -14,23 +17,21 @@ fn basic_match_works() {
     // Verifies initial conditions of mock
     with_externalities(&mut new_test_ext(), || {
         // add a tokentype
-        let tokentype = vec![1u8,2u8];
-        let tokentype2 = vec![3u8,4u8];
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype.clone(),1000);
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype2.clone(),1000);
-        assert_eq!(TokenT::token_type_and_precision(tokentype.clone()),Some(1000));
-        assert_eq!(TokenT::token_type_and_precision(tokentype2.clone()),Some(1000));
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        assert!(TokenT::token_info(tokentype).is_some());
+        assert!(TokenT::token_info(tokentype2).is_some());
 
         // mint 10000 token of tokentype to AccountId -> 10
-        TokenT::depositing_token(&10,tokentype.clone(),10000);
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),10000);
+        TokenT::depositing_token(&10,tokentype,10000);
+        assert_eq!(TokenT::free_token((tokentype,10)),10000);
         // mint 10000 token of tokentype to AccountId -> 10
-        TokenT::depositing_token(&11,tokentype2.clone(),10000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+        assert_eq!(TokenT::free_token((tokentype2,11)),10000);
 
-        let order_pair:OrderPair = OrderPair{
-            first:tokentype.clone(),
-            second:tokentype2.clone(),
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
         };
 
         //add_new_order_pair
@@ -39,17 +40,17 @@ fn basic_match_works() {
         assert_eq!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Buy,100,100),Err("Insufficient available balance"));
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100));
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),9900);
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),100);
+        assert_eq!(TokenT::free_token((tokentype,10)),9900);
+        assert_eq!(TokenT::locked_token((tokentype,10)),100);
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,50,105));
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),5000);
-        assert_eq!(TokenT::locked_token((tokentype2.clone(),11)),0);  // 5250 - 5000 = 250 多出250怎么办
+        assert_eq!(TokenT::free_token((tokentype2,11)),5000);
+        assert_eq!(TokenT::locked_token((tokentype2,11)),0);  // 5250 - 5000 = 250 多出250怎么办
         // 需要增加算法 当前交易 额外解锁 =》 交易数量amount*（挂单价格 - 实际交易价格）
 
-        assert_eq!(TokenT::free_token((tokentype.clone(),11)),50); // buyer get 50
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),50); //left seller
-        assert_eq!(TokenT::free_token((tokentype2.clone(),10)),5000); // get money
+        assert_eq!(TokenT::free_token((tokentype,11)),50); // buyer get 50
+        assert_eq!(TokenT::locked_token((tokentype,10)),50); //left seller
+        assert_eq!(TokenT::free_token((tokentype2,10)),5000); // get money
 
     });
 }
@@ -60,23 +61,21 @@ fn muilt_match_test() {
     // Verifies initial conditions of mock
     with_externalities(&mut new_test_ext(), || {
         // add a tokentype
-        let tokentype = vec![1u8,2u8];
-        let tokentype2 = vec![3u8,4u8];
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype.clone(),1000);
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype2.clone(),1000);
-        assert_eq!(TokenT::token_type_and_precision(tokentype.clone()),Some(1000));
-        assert_eq!(TokenT::token_type_and_precision(tokentype2.clone()),Some(1000));
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        assert!(TokenT::token_info(tokentype).is_some());
+        assert!(TokenT::token_info(tokentype2).is_some());
 
         // mint 10000 token of tokentype to AccountId -> 10
-        TokenT::depositing_token(&10,tokentype.clone(),10000);
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),10000);
+        TokenT::depositing_token(&10,tokentype,10000);
+        assert_eq!(TokenT::free_token((tokentype,10)),10000);
         // mint 10000 token of tokentype2 to AccountId -> 11
-        TokenT::depositing_token(&11,tokentype2.clone(),10000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+        assert_eq!(TokenT::free_token((tokentype2,11)),10000);
 
-        let order_pair:OrderPair = OrderPair{
-            first:tokentype.clone(),
-            second:tokentype2.clone(),
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
         };
 
         //add_new_order_pair
@@ -85,33 +84,33 @@ fn muilt_match_test() {
         assert_eq!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Buy,100,100),Err("Insufficient available balance"));
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100));
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),9900);
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),100);
+        assert_eq!(TokenT::free_token((tokentype,10)),9900);
+        assert_eq!(TokenT::locked_token((tokentype,10)),100);
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,50,95));
         assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,50,105));
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),250);
-        assert_eq!(TokenT::locked_token((tokentype2.clone(),11)),4750);  // 50x95=4750
+        assert_eq!(TokenT::free_token((tokentype2,11)),250);
+        assert_eq!(TokenT::locked_token((tokentype2,11)),4750);  // 50x95=4750
         // 需要增加算法 当前交易 额外解锁 =》 交易数量amount*（挂单价格 - 实际交易价格）
 
-        assert_eq!(TokenT::free_token((tokentype.clone(),11)),50); // buyer get 50
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),50); //left seller
-        assert_eq!(TokenT::free_token((tokentype2.clone(),10)),5000); // get money
+        assert_eq!(TokenT::free_token((tokentype,11)),50); // buyer get 50
+        assert_eq!(TokenT::locked_token((tokentype,10)),50); //left seller
+        assert_eq!(TokenT::free_token((tokentype2,10)),5000); // get money
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,105));
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),9800);
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),150);
+        assert_eq!(TokenT::free_token((tokentype,10)),9800);
+        assert_eq!(TokenT::locked_token((tokentype,10)),150);
         //now 2 sell order  amount/price 100/105 and 50/100
 
         // mint 30000 token of tokentype2 to AccountId -> 12
-        TokenT::depositing_token(&12,tokentype2.clone(),30000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),12)),30000);
+        TokenT::depositing_token(&12,tokentype2,30000);
+        assert_eq!(TokenT::free_token((tokentype2,12)),30000);
 
         // id 12 buy 105 with price 120
         assert_ok!(Dex::put_order_and_match(Origin::signed(12),order_pair.clone(),OrderType::Buy,105,120));
-        assert_eq!(TokenT::free_token((tokentype2.clone(),12)),19225);//30000 - 5775 - 5000 = 19225
-        assert_eq!(TokenT::free_token((tokentype.clone(),12)),105);  // get share 105
-        assert_eq!(TokenT::locked_token((tokentype2.clone(),12)),0); // overlocked tokens are returned
+        assert_eq!(TokenT::free_token((tokentype2,12)),19225);//30000 - 5775 - 5000 = 19225
+        assert_eq!(TokenT::free_token((tokentype,12)),105);  // get share 105
+        assert_eq!(TokenT::locked_token((tokentype2,12)),0); // overlocked tokens are returned
 
         printorder(1);
         printorder(2);
@@ -126,23 +125,21 @@ fn cancel_test() {
     // Verifies initial conditions of mock
     with_externalities(&mut new_test_ext(), || {
         // add a tokentype
-        let tokentype = vec![1u8,2u8];
-        let tokentype2 = vec![3u8,4u8];
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype.clone(),1000);
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype2.clone(),1000);
-        assert_eq!(TokenT::token_type_and_precision(tokentype.clone()),Some(1000));
-        assert_eq!(TokenT::token_type_and_precision(tokentype2.clone()),Some(1000));
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        assert!(TokenT::token_info(tokentype).is_some());
+        assert!(TokenT::token_info(tokentype2).is_some());
 
         // mint 10000 token of tokentype to AccountId -> 10
-        TokenT::depositing_token(&10,tokentype.clone(),10000);
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),10000);
+        TokenT::depositing_token(&10,tokentype,10000);
+        assert_eq!(TokenT::free_token((tokentype,10)),10000);
         // mint 10000 token of tokentype2 to AccountId -> 11
-        TokenT::depositing_token(&11,tokentype2.clone(),10000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+        assert_eq!(TokenT::free_token((tokentype2,11)),10000);
 
-        let order_pair:OrderPair = OrderPair{
-            first:tokentype.clone(),
-            second:tokentype2.clone(),
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
         };
 
         //add_new_order_pair
@@ -151,33 +148,33 @@ fn cancel_test() {
         assert_eq!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Buy,100,100),Err("Insufficient available balance"));
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100));
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),9900);
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),100);
+        assert_eq!(TokenT::free_token((tokentype,10)),9900);
+        assert_eq!(TokenT::locked_token((tokentype,10)),100);
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,50,95));
         assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,50,105));
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),250);
-        assert_eq!(TokenT::locked_token((tokentype2.clone(),11)),4750);  // 50x95=4750
+        assert_eq!(TokenT::free_token((tokentype2,11)),250);
+        assert_eq!(TokenT::locked_token((tokentype2,11)),4750);  // 50x95=4750
         // 需要增加算法 当前交易 额外解锁 =》 交易数量amount*（挂单价格 - 实际交易价格）
 
-        assert_eq!(TokenT::free_token((tokentype.clone(),11)),50); // buyer get 50
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),50); //left seller
-        assert_eq!(TokenT::free_token((tokentype2.clone(),10)),5000); // get money
+        assert_eq!(TokenT::free_token((tokentype,11)),50); // buyer get 50
+        assert_eq!(TokenT::locked_token((tokentype,10)),50); //left seller
+        assert_eq!(TokenT::free_token((tokentype2,10)),5000); // get money
 
         assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,105));
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),9800);
-        assert_eq!(TokenT::locked_token((tokentype.clone(),10)),150);
+        assert_eq!(TokenT::free_token((tokentype,10)),9800);
+        assert_eq!(TokenT::locked_token((tokentype,10)),150);
         //now 2 sell order  amount/price 100/105 and 50/100
 
         // mint 30000 token of tokentype2 to AccountId -> 12
-        TokenT::depositing_token(&12,tokentype2.clone(),30000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),12)),30000);
+        TokenT::depositing_token(&12,tokentype2,30000);
+        assert_eq!(TokenT::free_token((tokentype2,12)),30000);
 
         // id 12 buy 105 with price 120
         assert_ok!(Dex::put_order_and_match(Origin::signed(12),order_pair.clone(),OrderType::Buy,105,120));
-        assert_eq!(TokenT::free_token((tokentype2.clone(),12)),19225);//30000 - 5775 - 5000 = 19225
-        assert_eq!(TokenT::free_token((tokentype.clone(),12)),105);  // get share 105
-        assert_eq!(TokenT::locked_token((tokentype2.clone(),12)),0); // overlocked tokens are returned
+        assert_eq!(TokenT::free_token((tokentype2,12)),19225);//30000 - 5775 - 5000 = 19225
+        assert_eq!(TokenT::free_token((tokentype,12)),105);  // get share 105
+        assert_eq!(TokenT::locked_token((tokentype2,12)),0); // overlocked tokens are returned
 
         printorder(1);
         printorder(2);
@@ -197,30 +194,28 @@ fn full_match_test() {
     // Verifies initial conditions of mock
     with_externalities(&mut new_test_ext(), || {
         // add a tokentype
-        let tokentype = vec![1u8,2u8];
-        let tokentype2 = vec![3u8,4u8];
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype.clone(),1000);
-        TokenT::add_new_tokentype(Origin::signed(1),tokentype2.clone(),1000);
-        let order_pair:OrderPair = OrderPair{
-            first:tokentype.clone(),
-            second:tokentype2.clone(),
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
         };
         assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
 
-        TokenT::depositing_token(&10,tokentype.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype.clone(),10)),20000);
-        TokenT::depositing_token(&11,tokentype2.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),11)),20000);
-        TokenT::depositing_token(&12,tokentype.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype.clone(),12)),20000);
-        TokenT::depositing_token(&13,tokentype2.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),13)),20000);
-        TokenT::depositing_token(&14,tokentype.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype.clone(),14)),20000);
-        TokenT::depositing_token(&15,tokentype2.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),15)),20000);
-        TokenT::depositing_token(&16,tokentype2.clone(),20000);
-        assert_eq!(TokenT::free_token((tokentype2.clone(),16)),20000);
+        TokenT::depositing_token(&10,tokentype,20000);
+        assert_eq!(TokenT::free_token((tokentype,10)),20000);
+        TokenT::depositing_token(&11,tokentype2,20000);
+        assert_eq!(TokenT::free_token((tokentype2,11)),20000);
+        TokenT::depositing_token(&12,tokentype,20000);
+        assert_eq!(TokenT::free_token((tokentype,12)),20000);
+        TokenT::depositing_token(&13,tokentype2,20000);
+        assert_eq!(TokenT::free_token((tokentype2,13)),20000);
+        TokenT::depositing_token(&14,tokentype,20000);
+        assert_eq!(TokenT::free_token((tokentype,14)),20000);
+        TokenT::depositing_token(&15,tokentype2,20000);
+        assert_eq!(TokenT::free_token((tokentype2,15)),20000);
+        TokenT::depositing_token(&16,tokentype2,20000);
+        assert_eq!(TokenT::free_token((tokentype2,16)),20000);
 
         println!("1");
         assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,105,120));
@@ -240,7 +235,7 @@ fn full_match_test() {
         printorder(2);
         printorder(3);
         printorder(4);
-        TokenT::depositing_token(&13,tokentype2.clone(),20000);
+        TokenT::depositing_token(&13,tokentype2,20000);
         assert_ok!(Dex::put_order_and_match(Origin::signed(13),order_pair.clone(),OrderType::Buy,105,120));
         printorder(5);
         println!("xx");
@@ -251,6 +246,739 @@ fn full_match_test() {
 
     });
 }
+#[test]
+fn unsettleable_matches_change_nothing() {
+    use support::traits::MultiCurrency;
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,u64::max_value() / 2);
+
+        assert_noop!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,u64::max_value() / 2,3),
+                     "overflow in order amount");
+
+        // the reservation behind order 1 went missing, so nobody can buy from it
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100));
+        <TokenT as MultiCurrency<_>>::unreserve(tokentype,&10,60);
+        assert_noop!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,50,100),
+                     "reserved balance too low");
+        assert_eq!(Dex::order_index(),1);
+        assert_eq!(Dex::order_info(1).unwrap().left,100);
+        assert_eq!(TokenT::free_token((tokentype,11)),0);
+    });
+}
+
+#[test]
+fn settle_signed_orders_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+
+        // 10 quotes a sell off-chain, 11 signs a crossing buy
+        let maker = sign_order(OffchainOrder{
+            maker:10, pair:order_pair.clone(), ordertype:OrderType::Sell,
+            amount:100, price:90, nonce:0, expiry:10,
+        });
+        let taker = sign_order(OffchainOrder{
+            maker:11, pair:order_pair.clone(), ordertype:OrderType::Buy,
+            amount:60, price:100, nonce:0, expiry:10,
+        });
+
+        // a forged signature is rejected
+        let mut forged = taker.clone();
+        forged.signature = TestSignature(12, taker.order.encode());
+        assert_eq!(Dex::settle_signed_orders(Origin::signed(20),maker.clone(),forged,50),Err("invalid order signature"));
+
+        // relayer 20 settles 50 at the maker's price
+        assert_ok!(Dex::settle_signed_orders(Origin::signed(20),maker.clone(),taker.clone(),50));
+        assert_eq!(TokenT::free_token((tokentype,10)),9950);
+        assert_eq!(TokenT::locked_token((tokentype,10)),0);
+        assert_eq!(TokenT::free_token((tokentype2,10)),4500);
+        assert_eq!(TokenT::free_token((tokentype,11)),50);
+        assert_eq!(TokenT::free_token((tokentype2,11)),5500);
+        assert_eq!(TokenT::locked_token((tokentype2,11)),0);
+
+        // the taker only has 10 left
+        assert_eq!(Dex::settle_signed_orders(Origin::signed(20),maker.clone(),taker.clone(),20),
+                   Err("fill amount exceeds the order's remaining amount"));
+        assert_ok!(Dex::settle_signed_orders(Origin::signed(20),maker.clone(),taker.clone(),10));
+
+        // the maker voids the rest of the quote
+        assert_eq!(Dex::cancel_signed_order(Origin::signed(11),maker.order.clone()),Err("not permitted"));
+        assert_ok!(Dex::cancel_signed_order(Origin::signed(10),maker.order.clone()));
+        let taker2 = sign_order(OffchainOrder{
+            maker:11, pair:order_pair.clone(), ordertype:OrderType::Buy,
+            amount:10, price:100, nonce:1, expiry:10,
+        });
+        assert_eq!(Dex::settle_signed_orders(Origin::signed(20),maker.clone(),taker2.clone(),10),Err("Canceled or Finished"));
+
+        // orders below the minimum nonce are void
+        let maker2 = sign_order(OffchainOrder{
+            maker:10, pair:order_pair.clone(), ordertype:OrderType::Sell,
+            amount:10, price:100, nonce:0, expiry:10,
+        });
+        assert_ok!(Dex::set_min_order_nonce(Origin::signed(10),1));
+        assert_eq!(Dex::settle_signed_orders(Origin::signed(20),maker2,taker2.clone(),10),Err("order nonce is no longer valid"));
+
+        // and so are expired ones
+        let maker3 = sign_order(OffchainOrder{
+            maker:10, pair:order_pair.clone(), ordertype:OrderType::Sell,
+            amount:10, price:100, nonce:1, expiry:10,
+        });
+        System::set_block_number(11);
+        assert_eq!(Dex::settle_signed_orders(Origin::signed(20),maker3,taker2,10),Err("order expired"));
+    });
+}
+#[test]
+fn signed_orders_are_bound_to_the_chain_and_priced_by_the_maker() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+
+        // 11 quotes a buy valid for longer, 10 signs a crossing sell
+        let quote = sign_order(OffchainOrder{
+            maker:11, pair:order_pair.clone(), ordertype:OrderType::Buy,
+            amount:100, price:100, nonce:0, expiry:20,
+        });
+        let sell = sign_order(OffchainOrder{
+            maker:10, pair:order_pair.clone(), ordertype:OrderType::Sell,
+            amount:100, price:90, nonce:0, expiry:10,
+        });
+
+        // an order signed without the chain's genesis hash does not verify
+        let mut replayed = sell.clone();
+        replayed.signature = TestSignature(10, sell.order.encode());
+        assert_eq!(Dex::settle_signed_orders(Origin::signed(20),quote.clone(),replayed,10),Err("invalid order signature"));
+
+        // whichever way the relayer passes them, the quote is the maker and sets the price
+        assert_ok!(Dex::settle_signed_orders(Origin::signed(20),sell.clone(),quote.clone(),10));
+        assert_ok!(Dex::settle_signed_orders(Origin::signed(20),quote.clone(),sell.clone(),10));
+        assert_eq!(TokenT::free_token((tokentype,11)),20);
+        assert_eq!(TokenT::free_token((tokentype2,10)),2000);
+        assert_eq!(TokenT::free_token((tokentype2,11)),8000);
+    });
+}
+#[test]
+fn liquidity_rewards_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let reward = new_token(b"RWD");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+        TokenT::depositing_token(&12,tokentype,10000);
+
+        assert_eq!(Dex::set_reward_params(Origin::signed(10),order_pair.clone(),1000,Permill::from_percent(20)),
+                   Err("Invalid origin"));
+        assert_ok!(Dex::set_reward_params(Origin::ROOT,order_pair.clone(),1000,Permill::from_percent(20)));
+        assert_ok!(Dex::set_reward_token(Origin::ROOT,reward));
+
+        // ask 100 and bid 90 are at the top of the book, the ask at 150 is outside the 20% band
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100));
+        assert_ok!(Dex::put_order_and_match(Origin::signed(12),order_pair.clone(),OrderType::Sell,100,150));
+        assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,100,90));
+
+        Dex::on_initialize(1);
+        assert_eq!(Dex::reward_points(10),500);
+        assert_eq!(Dex::reward_points(11),500);
+        assert_eq!(Dex::reward_points(12),0);
+
+        // nothing to pay out until the pot is funded
+        assert_eq!(Dex::claim_rewards(Origin::signed(10)),Err("Insufficient available balance"));
+        TokenT::depositing_token(&Dex::reward_pot(),reward,700);
+        assert_ok!(Dex::claim_rewards(Origin::signed(10)));
+        assert_eq!(TokenT::free_token((reward,10)),500);
+        assert_eq!(Dex::reward_points(10),0);
+        assert_eq!(Dex::claim_rewards(Origin::signed(10)),Err("no rewards to claim"));
+        assert_eq!(Dex::claim_rewards(Origin::signed(11)),Err("Insufficient available balance"));
+        assert_eq!(Dex::reward_points(11),500);
+    });
+}
+
+#[test]
+fn tiered_fee_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+
+        TokenT::depositing_token(&10,tokentype,100000);
+        TokenT::depositing_token(&11,tokentype2,100000);
+        TokenT::depositing_token(&12,tokentype,100000);
+
+        let tier0 = FeeTier{ volume:0, maker_fee:10000, taker_fee:20000 };      // 1% / 2%
+        let tier1 = FeeTier{ volume:10000, maker_fee:-10000, taker_fee:10000 }; // -1% / 1%
+        assert_eq!(Dex::set_fee_schedule(Origin::ROOT,vec![tier1,tier0]),Err("fee tiers must be sorted by volume"));
+        assert_eq!(Dex::set_fee_schedule(Origin::ROOT,vec![FeeTier{ volume:0, maker_fee:-20000, taker_fee:10000 }]),
+                   Err("maker rebate exceeds the taker fee"));
+        // accounts below the first tier would have no rate to pay
+        assert_eq!(Dex::set_fee_schedule(Origin::ROOT,vec![tier1]),Err("the first fee tier must start at zero volume"));
+        assert_eq!(Dex::fee_tier_of(&10,tokentype2),None);
+        assert_ok!(Dex::set_fee_schedule(Origin::ROOT,vec![tier0,tier1]));
+        assert_eq!(Dex::fee_tier_of(&10,tokentype2),Some(0));
+
+        // 10 makes, 11 takes 1000 at 10 in tier 0
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,1000,10));
+        assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,1000,10));
+        assert_eq!(TokenT::free_token((tokentype2,10)),9900);   // 1% maker fee on 10000
+        assert_eq!(TokenT::free_token((tokentype,11)),980);     // 2% taker fee on 1000
+        assert_eq!(TokenT::free_token((tokentype2,Dex::fee_pot())),100);
+        assert_eq!(TokenT::free_token((tokentype,Dex::fee_pot())),20);
+
+        // volume is counted in the quote currency, and only towards pairs quoted in it
+        assert_eq!(Dex::traded_volume(&10,tokentype2),10000);
+        assert_eq!(Dex::traded_volume(&10,tokentype),0);
+        assert_eq!(Dex::fee_tier_of(&10,tokentype2),Some(1));
+        assert_eq!(Dex::fee_tier_of(&10,tokentype),Some(0));
+        assert_eq!(Dex::fee_tier_of(&11,tokentype2),Some(1));
+        assert_eq!(Dex::fee_tier_of(&12,tokentype2),Some(0));
+
+        // 11 now makes in tier 1 and earns a rebate out of the collected fees
+        assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,500,10));
+        assert_ok!(Dex::put_order_and_match(Origin::signed(12),order_pair.clone(),OrderType::Sell,500,10));
+        assert_eq!(TokenT::free_token((tokentype2,12)),4900);   // 2% taker fee on 5000
+        assert_eq!(TokenT::free_token((tokentype,11)),1485);    // 980 + 500 + 1% rebate
+        assert_eq!(TokenT::free_token((tokentype2,Dex::fee_pot())),200);
+        assert_eq!(TokenT::free_token((tokentype,Dex::fee_pot())),15);
+
+        // volume falls out of the window
+        System::set_block_number(31);
+        assert_eq!(Dex::traded_volume(&10,tokentype2),0);
+        assert_eq!(Dex::fee_tier_of(&10,tokentype2),Some(0));
+    });
+}
+
+#[test]
+fn frozen_account_orders_are_canceled() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+        TokenT::depositing_token(&10,tokentype,1000);
+        TokenT::depositing_token(&11,tokentype2,100000);
+
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,10));
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,50,12));
+        assert_eq!(Dex::open_orders_of((10,tokentype)),vec![1,2]);
+        assert_eq!(Dex::open_orders_of((10,tokentype2)),vec![1,2]);
+        assert_eq!(TokenT::locked_token((tokentype,10)),150);
+
+        // the issuer of AAA freezes 10
+        assert_ok!(TokenT::freeze_account(Origin::signed(1),tokentype,10));
+        assert_eq!(Dex::order_info(1).unwrap().status,OrderStatus::Canceled);
+        assert_eq!(Dex::order_info(2).unwrap().status,OrderStatus::Canceled);
+        assert_eq!(TokenT::locked_token((tokentype,10)),0);
+        assert_eq!(TokenT::free_token((tokentype,10)),1000);
+        assert!(Dex::open_orders_of((10,tokentype)).is_empty());
+        assert!(Dex::open_orders_of((10,tokentype2)).is_empty());
+        assert_eq!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,10,10),
+                   Err("account may not trade this pair"));
+
+        // a frozen buyer could not receive what it buys
+        assert_ok!(TokenT::freeze_account(Origin::signed(1),tokentype,11));
+        assert_eq!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,10,10),
+                   Err("account may not trade this pair"));
+        assert_eq!(TokenT::free_token((tokentype2,11)),100000);
+    });
+}
+
+#[test]
+fn token_fee_payment_test() {
+    with_externalities(&mut new_test_ext(), || {
+        // token 0 is the mock's native currency
+        let native = new_token(b"NAT");
+        let fee_token = new_token(b"FEE");
+        TokenT::depositing_token(&10,fee_token,1000);
+        TokenT::depositing_token(&11,native,10000);
+        let pay = |tip| TakeTokenFees::<Test>::in_currency(fee_token,tip)
+            .validate(&10,&(),DispatchInfo::default(),10);
+
+        // only whitelisted currencies are accepted
+        assert!(pay(100).is_err());
+        assert_eq!(Dex::set_fee_currency(Origin::signed(1),fee_token,Some(FeeRate::Dex{ min_depth:10 })),Err("Invalid origin"));
+        assert_eq!(Dex::set_fee_currency(Origin::ROOT,fee_token,Some(FeeRate::Dex{ min_depth:0 })),
+                   Err("fee rate of zero depth"));
+        assert_eq!(Dex::set_fee_currency(Origin::ROOT,fee_token,Some(FeeRate::Fixed{ native:0, currency:1 })),
+                   Err("fee rate of zero native units"));
+
+        // 1 token for every 10 native
+        assert_ok!(Dex::set_fee_currency(Origin::ROOT,fee_token,Some(FeeRate::Fixed{ native:10, currency:1 })));
+        assert!(pay(100).is_ok());
+        assert_eq!(TokenT::free_token((fee_token,10)),990);
+        assert_eq!(TokenT::free_token((fee_token,Dex::transaction_fee_pot())),10);
+
+        // at the dex rate there is nothing to convert at until someone bids
+        assert_ok!(Dex::set_fee_currency(Origin::ROOT,fee_token,Some(FeeRate::Dex{ min_depth:10 })));
+        assert!(pay(100).is_err());
+        assert_eq!(TokenT::free_token((fee_token,10)),990);
+
+        let order_pair = OrderPair{
+            first:fee_token,
+            second:native,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+        // a quote thinner than the minimum depth is not trusted
+        assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,5,20));
+        assert_eq!(Dex::best_level(order_pair.clone(),OrderType::Buy),Some((20,5)));
+        assert!(pay(100).is_err());
+        assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,5,20));
+        assert_eq!(Dex::best_price(order_pair.clone(),OrderType::Buy),Some(20));
+        assert!(pay(100).is_ok());
+        assert_eq!(TokenT::free_token((fee_token,10)),985);
+        // rounded up in favour of the pot
+        assert!(pay(101).is_ok());
+        assert_eq!(TokenT::free_token((fee_token,10)),979);
+
+        // more than the payer holds
+        assert!(pay(20000).is_err());
+        assert_eq!(TokenT::free_token((fee_token,10)),979);
+
+        assert_ok!(Dex::set_fee_currency(Origin::ROOT,fee_token,None));
+        assert!(pay(100).is_err());
+
+        // governance pays out what the pot collected
+        assert_noop!(Dex::spend_transaction_fees(Origin::signed(10),fee_token,12,21),"Invalid origin");
+        assert_ok!(Dex::spend_transaction_fees(Origin::ROOT,fee_token,12,21));
+        assert_eq!(TokenT::free_token((fee_token,12)),21);
+        assert_eq!(TokenT::free_token((fee_token,Dex::transaction_fee_pot())),0);
+    });
+}
+
+#[test]
+fn archive_closed_orders_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,100000);
+
+        // order 1 fills against 20 takers but only keeps the first few counterparties
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,20,100));
+        for _ in 0..20 {
+            assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,1,100));
+        }
+        assert_eq!(Dex::order_info(1).unwrap().status,OrderStatus::Finished);
+        assert_eq!(Dex::order_info(1).unwrap().fill_index.len(),Dex::match_caps().fill_index as usize);
+
+        // order 22 is canceled one block later
+        System::set_block_number(1);
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,50,100));
+        assert_ok!(Dex::cancel_order(Origin::signed(10),order_pair.clone(),22));
+
+        Dex::on_initialize(9);
+        assert!(Dex::order_info(1).is_some());
+        Dex::on_initialize(10);
+        assert!(Dex::order_info(1).is_none());
+        assert!(Dex::order_info(21).is_none());
+        assert!(Dex::order_info(22).is_some());
+        Dex::on_initialize(11);
+        assert!(Dex::order_info(22).is_none());
+        assert_eq!(Dex::cancel_order(Origin::signed(10),order_pair.clone(),22),Err("cant find order"));
+    });
+}
+
+#[test]
+fn orders_are_archived_without_retention() {
+    with_externalities(&mut new_test_ext(), || {
+        set_order_retention(0);
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+        TokenT::depositing_token(&10,tokentype,10000);
+
+        // canceled after its block's queue was drained, the order goes with the next one
+        System::set_block_number(5);
+        Dex::on_initialize(5);
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,50,100));
+        assert_ok!(Dex::cancel_order(Origin::signed(10),order_pair.clone(),1));
+        Dex::on_initialize(6);
+        assert!(Dex::order_info(1).is_none());
+    });
+}
+
+#[test]
+fn genesis_config_opens_markets() {
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    let order_pair = OrderPair{ first:0, second:1 };
+    let tier = FeeTier{ volume:0, maker_fee:1000, taker_fee:2000 };
+    GenesisConfig::<Test>{
+        pairs: vec![(order_pair.clone(), RewardParams{ emission:5, band:Permill::from_percent(1) })],
+        reward_token: Some(2),
+        fee_schedule: vec![tier],
+        match_caps: MatchCaps{ fill_index:4, sampled_orders:10 },
+    }.assimilate_storage(&mut t).unwrap();
+    let mut ext: runtime_io::TestExternalities<primitives::Blake2Hasher> = t.into();
+    with_externalities(&mut ext, || {
+        assert_eq!(Dex::order_pair_list(),vec![order_pair.clone()]);
+        assert_eq!(Dex::reward_params_of(order_pair).emission,5);
+        assert_eq!(Dex::reward_token(),Some(2));
+        assert_eq!(Dex::fee_schedule(),vec![tier]);
+        assert_eq!(Dex::match_caps().fill_index,4);
+        assert_eq!(Dex::storage_version(),STORAGE_VERSION);
+    });
+}
+
+fn legacy_pair() -> OrderPairV1 {
+    // token types as the genesis of version 0 created them
+    token::TokenTypeAndPrecision::insert(vec![1u8,2u8],1000);
+    token::TokenTypeAndPrecision::insert(vec![3u8,4u8],1000);
+    OrderPairV1{
+        first:vec![1u8,2u8],
+        second:vec![3u8,4u8],
+    }
+}
+
+fn put_legacy_order(order: &OrderInfoV1<Test>) {
+    hashed::put(&runtime_io::blake2_256, &migration::map_key(b"OrderInfor", &order.index), order);
+}
+
+fn legacy_order(index: u128) -> Option<OrderInfoV1<Test>> {
+    hashed::get(&runtime_io::blake2_256, &migration::map_key(b"OrderInfor", &index))
+}
+
+fn new_legacy_order(who: u64, ordertype: OrderType, pair: &OrderPairV1, amount: u64, price: u64, index: u128) -> OrderInfoV1<Test> {
+    OrderInformationV1{
+        who, ordertype, pair: pair.clone(), amount, price, left: amount,
+        status: OrderStatus::Valid, index, time: 0, fill_index: Vec::new(),
+    }
+}
+
+#[test]
+fn migrate_legacy_orders_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let order_pair = legacy_pair();
+        // state as left behind before closed orders were pruned
+        let mut finished = new_legacy_order(10, OrderType::Sell, &order_pair, 100, 100, 1);
+        finished.left = 0;
+        finished.status = OrderStatus::Finished;
+        finished.fill_index = (2..60).collect();
+        let mut open = new_legacy_order(11, OrderType::Buy, &order_pair, 100, 90, 2);
+        open.fill_index = (3..60).collect();
+        put_legacy_order(&finished);
+        put_legacy_order(&open);
+        OrderIndex::put(2);
+        StorageVersion::put(0);
+
+        Dex::on_initialize(1);
+        assert!(Dex::order_info(1).is_none());
+        assert!(legacy_order(1).is_none());
+        let open = Dex::order_info(2).unwrap();
+        assert_eq!(open.fill_index.len(),Dex::match_caps().fill_index as usize);
+        assert_eq!(open.pair,OrderPair{
+            first:TokenT::legacy_token_id(vec![1u8,2u8]).unwrap(),
+            second:TokenT::legacy_token_id(vec![3u8,4u8]).unwrap(),
+        });
+        assert_eq!(Dex::storage_version(),STORAGE_VERSION);
+
+        // all three steps ran in the block and took room from its extrinsics, later blocks pay nothing
+        assert_eq!(System::all_extrinsics_weight(),3 * ORDER_MIGRATION_BATCH_WEIGHT);
+        Dex::on_initialize(2);
+        assert_eq!(System::all_extrinsics_weight(),3 * ORDER_MIGRATION_BATCH_WEIGHT);
+    });
+}
+
+#[test]
+fn migration_spans_blocks_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let order_pair = legacy_pair();
+        // more version 0 orders than one block migrates
+        let total = ORDER_MIGRATION_BATCH + 10;
+        for index in 1..=total {
+            let mut order = new_legacy_order(10, OrderType::Sell, &order_pair, 1, 100, index);
+            order.left = 0;
+            order.status = OrderStatus::Finished;
+            put_legacy_order(&order);
+        }
+        OrderIndex::put(total);
+        StorageVersion::put(0);
+
+        Dex::on_initialize(1);
+        assert_eq!(Dex::storage_version(),0);
+        assert!(legacy_order(ORDER_MIGRATION_BATCH).is_none());
+        assert!(legacy_order(ORDER_MIGRATION_BATCH + 1).is_some());
+
+        // extrinsics wait for the migration
+        assert_eq!(Dex::cancel_order(Origin::signed(10),OrderPair::default(),1),Err("dex storage is being migrated"));
+        assert_noop!(Dex::set_match_caps(Origin::ROOT,MatchCaps{ fill_index:4, sampled_orders:10 }),
+                     "dex storage is being migrated");
+
+        // a step only looks at the orders stored when it started
+        let mut late = new_legacy_order(10, OrderType::Sell, &order_pair, 1, 100, total + 1);
+        late.status = OrderStatus::Finished;
+        put_legacy_order(&late);
+        OrderIndex::put(total + 1);
+
+        Dex::on_initialize(2);
+        assert_eq!(Dex::storage_version(),1);
+        assert!(legacy_order(total).is_none());
+        assert!(legacy_order(total + 1).is_some());
+
+        // every later step walks the order indexes again, a batch per block
+        Dex::on_initialize(3);
+        Dex::on_initialize(4);
+        assert_eq!(Dex::storage_version(),STORAGE_VERSION);
+    });
+}
+
+#[test]
+fn migrate_to_token_ids_test() {
+    with_externalities(&mut new_test_ext(), || {
+        let old_pair = legacy_pair();
+        // a version 1 book with one resting sell order
+        let order = new_legacy_order(10, OrderType::Sell, &old_pair, 100, 100, 1);
+        put_legacy_order(&order);
+        let bid = BidDetailV1{
+            id: 1, pair: old_pair.clone(), order_type: OrderType::Sell, user: 10, price: 100, amount: 100, time: 0,
+        };
+        hashed::put(&runtime_io::blake2_256, &migration::map_key(b"BidOf", &1u128), &bid);
+        let node = Node::new(Bid{ nodeid: 7, price: 100, sum: 100, list: vec![1u128], useless: 0u64 });
+        <BidListCache<Test>>::insert(7, node);
+        let book_key = (old_pair.clone(), OrderType::Sell);
+        let book_index: BookIndexV1<Test> = MultiNodeIndex::new(7, book_key.clone());
+        hashed::put(&runtime_io::blake2_256, &migration::map_key(b"BidListHeaderFor", &book_key), &book_index);
+        hashed::put(&runtime_io::blake2_256, &migration::map_key(b"BidListTailFor", &book_key), &book_index);
+        hashed::put(&runtime_io::blake2_256, &migration::map_key(b"RewardParamsOf", &old_pair),
+                    &RewardParams{ emission: 10, band: Permill::from_percent(5) });
+        unhashed::put(&runtime_io::twox_128(&migration::value_key(b"OrderPairList")), &vec![old_pair.clone()]);
+        unhashed::put(&runtime_io::twox_128(&migration::value_key(b"RewardToken")), &vec![3u8,4u8]);
+        // an entry that is no version 1 order is left alone
+        let mut garbage = new_legacy_order(10, OrderType::Sell, &old_pair, 100, 100, 2).encode();
+        garbage.push(0);
+        hashed::put_raw(&runtime_io::blake2_256, &migration::map_key(b"OrderInfor", &2u128), &garbage);
+        OrderIndex::put(2);
+        StorageVersion::put(1);
+
+        Dex::on_initialize(1);
+        assert_eq!(Dex::storage_version(),STORAGE_VERSION);
+        let first = TokenT::legacy_token_id(vec![1u8,2u8]).unwrap();
+        let second = TokenT::legacy_token_id(vec![3u8,4u8]).unwrap();
+        let pair = OrderPair{ first, second };
+        assert_eq!(Dex::order_pair_list(),vec![pair.clone()]);
+        assert_eq!(Dex::order_info(1).unwrap().pair,pair);
+        assert_eq!(Dex::bid_of(1).unwrap().pair,pair);
+        assert_eq!(Dex::bidlist_header_for((pair.clone(),OrderType::Sell)).unwrap().index(),7);
+        assert_eq!(Dex::bidlist_tail_for((pair.clone(),OrderType::Sell)).unwrap().index(),7);
+        assert_eq!(Dex::reward_params_of(pair.clone()).emission,10);
+        assert_eq!(Dex::reward_token(),Some(second));
+        assert_eq!(hashed::get_raw(&runtime_io::blake2_256, &migration::map_key(b"OrderInfor", &2u128)),Some(garbage));
+    });
+}
+
+#[test]
+fn pairs_are_listed_and_delisted() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,10000);
+
+        assert_eq!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100),
+                   Err("order pair is not listed"));
+        assert_noop!(Dex::list_pair(Origin::signed(10),order_pair.clone()),"Invalid origin");
+        assert_noop!(Dex::list_pair(Origin::ROOT,OrderPair{ first:tokentype, second:tokentype }),
+                     "a pair needs two currencies");
+        assert_noop!(Dex::list_pair(Origin::ROOT,OrderPair{ first:tokentype, second:99 }),"invalid tokentype");
+        assert_ok!(Dex::list_pair(Origin::ROOT,order_pair.clone()));
+        assert_noop!(Dex::list_pair(Origin::ROOT,order_pair.clone()),"already exist orderpair");
+
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,100,100));
+        assert_ok!(Dex::set_reward_params(Origin::ROOT,order_pair.clone(),1000,Permill::from_percent(20)));
+
+        assert_noop!(Dex::delist_pair(Origin::signed(10),order_pair.clone()),"Invalid origin");
+        assert_ok!(Dex::delist_pair(Origin::ROOT,order_pair.clone()));
+        assert_noop!(Dex::delist_pair(Origin::ROOT,order_pair.clone()),"order pair is not listed");
+        assert_eq!(Dex::reward_params_of(order_pair.clone()).emission,0);
+        assert_eq!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,100,100),
+                   Err("order pair is not listed"));
+
+        // the resting order is left to its owner
+        assert_ok!(Dex::cancel_order(Origin::signed(10),order_pair.clone(),1));
+        assert_eq!(TokenT::free_token((tokentype,10)),10000);
+    });
+}
+
+#[test]
+fn liquidity_rewards_take_turns_between_pairs() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let mut pairs = Vec::new();
+        for _ in 0..11 {
+            let order_pair = OrderPair{ first:tokentype, second:new_token(b"BBB") };
+            assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+            pairs.push(order_pair);
+        }
+        let last = pairs[10].clone();
+        TokenT::depositing_token(&10,tokentype,10000);
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),last.clone(),OrderType::Sell,100,100));
+        assert_ok!(Dex::set_reward_params(Origin::ROOT,last.clone(),100,Permill::from_percent(20)));
+
+        // ten pairs a block, the last one comes round every other block and earns for both
+        Dex::on_initialize(1);
+        assert_eq!(Dex::reward_points(10),0);
+        Dex::on_initialize(2);
+        assert_eq!(Dex::reward_points(10),200);
+        Dex::on_initialize(3);
+        assert_eq!(Dex::reward_points(10),200);
+        Dex::on_initialize(4);
+        assert_eq!(Dex::reward_points(10),400);
+    });
+}
+
+#[test]
+fn match_caps_are_tunable() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        assert_ok!(Dex::add_new_order_pair(order_pair.clone()));
+        TokenT::depositing_token(&10,tokentype,10000);
+        TokenT::depositing_token(&11,tokentype2,100000);
+
+        let caps = MatchCaps{ fill_index:3, sampled_orders:1 };
+        assert_noop!(Dex::set_match_caps(Origin::signed(10),caps),"Invalid origin");
+        assert_noop!(Dex::set_match_caps(Origin::ROOT,MatchCaps{ fill_index:0, sampled_orders:1 }),
+                     "fill index cap of zero");
+        assert_noop!(Dex::set_match_caps(Origin::ROOT,MatchCaps{ fill_index:3, sampled_orders:0 }),
+                     "sampled orders cap of zero");
+        assert_ok!(Dex::set_match_caps(Origin::ROOT,caps));
+
+        assert_ok!(Dex::put_order_and_match(Origin::signed(10),order_pair.clone(),OrderType::Sell,20,100));
+        for _ in 0..5 {
+            assert_ok!(Dex::put_order_and_match(Origin::signed(11),order_pair.clone(),OrderType::Buy,1,100));
+        }
+        assert_eq!(Dex::order_info(1).unwrap().fill_index,vec![2,3,4]);
+
+        // only the first of two resting asks earns
+        TokenT::depositing_token(&12,tokentype,10000);
+        assert_ok!(Dex::put_order_and_match(Origin::signed(12),order_pair.clone(),OrderType::Sell,100,100));
+        assert_ok!(Dex::set_reward_params(Origin::ROOT,order_pair.clone(),1000,Permill::from_percent(20)));
+        Dex::on_initialize(1);
+        assert_eq!(Dex::reward_points(10),1000);
+        assert_eq!(Dex::reward_points(12),0);
+    });
+}
+
+// vote `call` through a public referendum and run blocks until it is enacted
+fn enact_by_referendum(call: crate::Call<Test>) {
+    let next_block = || {
+        let now = System::block_number() + 1;
+        System::set_block_number(now);
+        Democracy::on_initialize(now);
+    };
+    let referendum = Democracy::referendum_count();
+    assert_ok!(Democracy::propose(Origin::signed(1),Box::new(mock::Call::Dex(call)),1));
+    while Democracy::referendum_count() == referendum {
+        next_block();
+    }
+    let aye = democracy::Vote{ aye:true, conviction:democracy::Conviction::None };
+    assert_ok!(Democracy::vote(Origin::signed(1),referendum,aye));
+    for _ in 0..(VotingPeriod::get() + EnactmentPeriod::get()) {
+        next_block();
+    }
+}
+
+#[test]
+fn parameters_change_through_referenda() {
+    with_externalities(&mut new_test_ext(), || {
+        let tokentype = new_token(b"AAA");
+        let tokentype2 = new_token(b"BBB");
+        let order_pair = OrderPair{
+            first:tokentype,
+            second:tokentype2,
+        };
+        let tier = FeeTier{ volume:0, maker_fee:1000, taker_fee:2000 };
+        let _ = Balances::deposit_creating(&1,100);
+
+        enact_by_referendum(crate::Call::list_pair(order_pair.clone()));
+        assert_eq!(Dex::order_pair_list(),vec![order_pair.clone()]);
+
+        enact_by_referendum(crate::Call::set_fee_schedule(vec![tier]));
+        enact_by_referendum(crate::Call::set_reward_params(order_pair.clone(),50,Permill::from_percent(1)));
+        enact_by_referendum(crate::Call::set_match_caps(MatchCaps{ fill_index:2, sampled_orders:5 }));
+        assert_eq!(Dex::fee_schedule(),vec![tier]);
+        assert_eq!(Dex::reward_params_of(order_pair.clone()),RewardParams{ emission:50, band:Permill::from_percent(1) });
+        assert_eq!(Dex::match_caps(),MatchCaps{ fill_index:2, sampled_orders:5 });
+
+        enact_by_referendum(crate::Call::delist_pair(order_pair.clone()));
+        assert!(Dex::order_pair_list().is_empty());
+    });
+}
+
+/// Run the harness on 200 seeded inputs. A failing seed reproduces the run, and its bytes make a
+/// fuzzing corpus entry.
+fn run_seeds(params: harness::Params) {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    for seed in 0..200 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let data: Vec<u8> = (0..1024).map(|_| rng.gen()).collect();
+        let run = std::panic::catch_unwind(|| harness::run_with(&data, params));
+        assert!(run.is_ok(), "seed {} breaks an invariant with {:?}", seed, params);
+    }
+}
+
+#[test]
+fn random_operations_keep_the_book_consistent() {
+    run_seeds(harness::Params::default());
+}
+
+#[test]
+fn random_operations_keep_deposits_and_dust_consistent() {
+    run_seeds(harness::Params{ holder_deposit:3, min_balance:5 });
+}
+
 /*
 fn asd(){
 
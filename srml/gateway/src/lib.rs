@@ -0,0 +1,338 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Gateway Module
+//!
+//! Moves tokens of the token module between this chain and an external chain.
+//!
+//! ## Overview
+//!
+//! - `request_withdraw` burns tokens here and emits `WithdrawRequested`, for the relayers to
+//!   release as much on the external chain to the given external address.
+//! - A deposit on the external chain is minted here once `Threshold` of the `Relayers` attested
+//!   it. A deposit is known by the id of its external transaction and minted at most once.
+//! - A deposit that reached the threshold but could not be minted, say because its token is not
+//!   bridged at the moment, is kept as rejected until root retries it with `retry_deposit`.
+//!
+//! Relayers attest from the offchain worker of their node. It fetches recent deposits with a GET
+//! request to the `Endpoint` root set, or to the URL in the node's persistent offchain storage
+//! under `ENDPOINT_KEY`, and submits those it has not attested yet as one `attest_deposits`
+//! transaction signed with the node's relayer key of type `KEY_TYPE`. The endpoint answers with
+//! the SCALE encoded `Vec<Deposit>`, the runtime having no JSON parser.
+//!
+//! Tokens cross the gateway only once root bridged them.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod mock;
+mod tests;
+
+use rstd::prelude::*;
+use app_crypto::{KeyTypeId, RuntimeAppPublic};
+use codec::{Encode, Decode};
+use primitives::offchain::StorageKind;
+use runtime_io::offchain::http;
+use sr_primitives::traits::Hash;
+use support::{decl_module, decl_event, decl_storage, ensure, print, StorageValue, StorageMap};
+use support::dispatch::Result;
+use system::{ensure_signed, ensure_root};
+use system::offchain::SubmitSignedTransaction;
+use token::{Token, TokenId};
+
+/// Key type of the keys relayers attest deposits with.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"gtwy");
+
+/// Key of the persistent offchain storage entry overriding `Endpoint` on a node.
+pub const ENDPOINT_KEY: &[u8] = b"srml-gateway::endpoint";
+
+/// The most deposits one `attest_deposits` may attest.
+pub const MAX_ATTESTATIONS: usize = 100;
+
+const MAX_ADDRESS_LEN: usize = 64;
+const MAX_TX_ID_LEN: usize = 64;
+
+pub mod sr25519 {
+	mod app_sr25519 {
+		use app_crypto::{app_crypto, sr25519};
+		app_crypto!(sr25519, crate::KEY_TYPE);
+
+		impl From<Signature> for sr_primitives::AnySignature {
+			fn from(sig: Signature) -> Self {
+				sr25519::Signature::from(sig).into()
+			}
+		}
+	}
+
+	/// A relayer keypair using sr25519 as its crypto.
+	#[cfg(feature = "std")]
+	pub type RelayerPair = app_sr25519::Pair;
+
+	/// A relayer signature using sr25519 as its crypto.
+	pub type RelayerSignature = app_sr25519::Signature;
+
+	/// A relayer identifier using sr25519 as its crypto.
+	pub type RelayerId = app_sr25519::Public;
+}
+
+/// Identifier of a withdrawal, for the relayers to release it on the external chain once.
+pub type WithdrawalId = u64;
+
+/// A deposit made on the external chain.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Deposit<AccountId> {
+	/// Id of the external transaction making the deposit.
+	pub tx: Vec<u8>,
+	/// The token deposited.
+	pub token: TokenId,
+	/// The account the deposit is minted to.
+	pub dest: AccountId,
+	/// The amount deposited.
+	pub amount: u64,
+}
+
+/// A deposit to an account of the runtime.
+pub type DepositOf<T> = Deposit<<T as system::Trait>::AccountId>;
+
+pub trait Trait: system::Trait + token::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+	/// A dispatchable call type.
+	type Call: From<Call<Self>>;
+
+	/// The key relayers attest with, each one signing for the account it converts into.
+	type RelayerId: RuntimeAppPublic + Into<Self::AccountId>;
+
+	/// A transaction submitter.
+	type SubmitTransaction: SubmitSignedTransaction<Self, <Self as Trait>::Call>;
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Burn `amount` of `token` of the sender, to be released to `external_address` on the
+		/// external chain.
+		pub fn request_withdraw(origin, #[compact] token:TokenId, #[compact] amount:u64,
+								external_address:Vec<u8>) -> Result {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::is_bridged(token), "token is not bridged");
+			ensure!(amount > 0, "zero withdrawal");
+			ensure!(!external_address.is_empty() && external_address.len() <= MAX_ADDRESS_LEN,
+				"invalid external address");
+
+			<token::Module<T>>::ensure_can_hold(token, &who)?;
+			<token::Module<T> as Token<_>>::burn(&who, token, amount)?;
+			let id = Self::next_withdrawal_id();
+			NextWithdrawalId::put(id + 1);
+			Self::deposit_event(RawEvent::WithdrawRequested(id, token, who, amount, external_address));
+			Ok(())
+		}
+
+		/// Attest external deposits as a relayer. Deposits already minted or attested by the
+		/// sender are skipped, for relayers lagging behind to still get theirs through.
+		pub fn attest_deposits(origin, deposits:Vec<DepositOf<T>>) -> Result {
+			let who = ensure_signed(origin)?;
+			let relayers = Self::relayers();
+			ensure!(relayers.contains(&who), "not a relayer");
+			ensure!(deposits.len() <= MAX_ATTESTATIONS, "too many deposits");
+
+			for deposit in deposits {
+				Self::attest(&who, &relayers, deposit);
+			}
+			Ok(())
+		}
+
+		/// Mint a deposit that was rejected when it reached the threshold, once whatever stood in
+		/// its way is resolved. It stays rejected if it still cannot be minted.
+		pub fn retry_deposit(origin, tx:Vec<u8>) -> Result {
+			ensure_root(origin)?;
+			let deposit = Self::rejected_deposit(&tx).ok_or("no rejected deposit")?;
+			Self::mint_deposit(&deposit)?;
+			<Rejected<T>>::remove(&tx);
+			Ok(())
+		}
+
+		/// Set the relayers and how many of them mint a deposit. Attestations of relayers no
+		/// longer in the set stop counting.
+		pub fn set_relayers(origin, relayers:Vec<T::AccountId>, threshold:u32) -> Result {
+			ensure_root(origin)?;
+			ensure!(threshold > 0 && threshold as usize <= relayers.len(), "invalid relayer threshold");
+			<Relayers<T>>::put(relayers);
+			Threshold::put(threshold);
+			Self::deposit_event(RawEvent::RelayersSet(threshold));
+			Ok(())
+		}
+
+		/// Let `token` cross the gateway, or stop it from crossing.
+		pub fn set_bridged(origin, #[compact] token:TokenId, bridged:bool) -> Result {
+			ensure_root(origin)?;
+			<token::Module<T> as Token<_>>::vaild_tokentype(token)?;
+			Bridged::insert(token, bridged);
+			Self::deposit_event(RawEvent::BridgedSet(token, bridged));
+			Ok(())
+		}
+
+		/// Set the URL relayers fetch external deposits from.
+		pub fn set_endpoint(origin, endpoint:Vec<u8>) -> Result {
+			ensure_root(origin)?;
+			Endpoint::put(endpoint);
+			Ok(())
+		}
+
+		// Runs after every block.
+		fn offchain_worker(_now: T::BlockNumber) {
+			if let Err(e) = Self::relay() {
+				print(e);
+			}
+		}
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
+		/// Tokens were burned to be released on the external chain: id, token, account, amount,
+		/// external address.
+		WithdrawRequested(WithdrawalId, TokenId, AccountId, u64, Vec<u8>),
+		/// A relayer attested the deposit of an external transaction.
+		DepositAttested(Vec<u8>, AccountId),
+		/// The deposit of an external transaction was minted: transaction, token, account, amount.
+		DepositMinted(Vec<u8>, TokenId, AccountId, u64),
+		/// The deposit of an external transaction reached the threshold but could not be minted.
+		/// It is kept for root to retry.
+		DepositRejected(Vec<u8>),
+		/// The relayers were replaced, this many now minting a deposit.
+		RelayersSet(u32),
+		/// A token was let across the gateway, or stopped from crossing.
+		BridgedSet(TokenId, bool),
+	}
+);
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Gateway {
+		/// Accounts attesting external deposits.
+		pub Relayers get(relayers) config(): Vec<T::AccountId>;
+		/// How many relayers attesting a deposit mint it, at least one.
+		pub Threshold get(threshold) config(): u32;
+		/// URL of the service relayers fetch external deposits from.
+		pub Endpoint get(endpoint) config(): Vec<u8>;
+		/// Tokens that may cross the gateway.
+		pub Bridged get(is_bridged): map TokenId => bool;
+
+		/// The id the next withdrawal gets.
+		pub NextWithdrawalId get(next_withdrawal_id): WithdrawalId;
+
+		/// Relayers that attested the deposit of an external transaction and the hash of the
+		/// deposit they attested, by transaction. Removed once the deposit is processed.
+		pub Attestations get(attestations): map Vec<u8> => Vec<(T::AccountId, T::Hash)>;
+		/// External transactions whose deposit was minted.
+		pub Processed get(is_processed): map Vec<u8> => bool;
+		/// Deposits that reached the threshold but could not be minted, by transaction, until root
+		/// retries them.
+		pub Rejected get(rejected_deposit): map Vec<u8> => Option<DepositOf<T>>;
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Record the attestation of `who`, minting the deposit once enough relayers attested the
+	/// same deposit for its transaction.
+	fn attest(who: &T::AccountId, relayers: &[T::AccountId], deposit: DepositOf<T>) {
+		if deposit.tx.is_empty() || deposit.tx.len() > MAX_TX_ID_LEN || Self::is_settled(&deposit) {
+			return;
+		}
+		let mut attestations = Self::attestations(&deposit.tx);
+		if attestations.iter().any(|(relayer, _)| relayer == who) {
+			return;
+		}
+		let hash = T::Hashing::hash_of(&deposit);
+		attestations.push((who.clone(), hash));
+		Self::deposit_event(RawEvent::DepositAttested(deposit.tx.clone(), who.clone()));
+
+		let count = attestations.iter()
+			.filter(|(relayer, attested)| *attested == hash && relayers.contains(relayer))
+			.count();
+		if count < Self::threshold().max(1) as usize {
+			<Attestations<T>>::insert(&deposit.tx, attestations);
+			return;
+		}
+
+		<Attestations<T>>::remove(&deposit.tx);
+		if Self::mint_deposit(&deposit).is_err() {
+			Self::deposit_event(RawEvent::DepositRejected(deposit.tx.clone()));
+			<Rejected<T>>::insert(&deposit.tx, deposit);
+		}
+	}
+
+	/// Mint `deposit` and mark its transaction processed, changing nothing if it cannot be minted.
+	fn mint_deposit(deposit: &DepositOf<T>) -> Result {
+		ensure!(Self::is_bridged(deposit.token), "token is not bridged");
+		<token::Module<T> as Token<_>>::mint(&deposit.dest, deposit.token, deposit.amount)?;
+		Processed::insert(&deposit.tx, true);
+		Self::deposit_event(
+			RawEvent::DepositMinted(deposit.tx.clone(), deposit.token, deposit.dest.clone(), deposit.amount)
+		);
+		Ok(())
+	}
+
+	/// Whether `deposit` was minted or rejected, either way no longer attested.
+	fn is_settled(deposit: &DepositOf<T>) -> bool {
+		Self::is_processed(&deposit.tx) || <Rejected<T>>::exists(&deposit.tx)
+	}
+
+	/// Attest the deposits the endpoint reports which the relayer of this node has not, if the
+	/// node has a relayer key.
+	pub(crate) fn relay() -> Result {
+		let relayers = Self::relayers();
+		let relayer = T::RelayerId::all().into_iter()
+			.map(Into::into)
+			.find(|account: &T::AccountId| relayers.contains(account));
+		let relayer = match relayer {
+			Some(relayer) => relayer,
+			None => return Ok(()),
+		};
+		let endpoint = runtime_io::local_storage_get(StorageKind::PERSISTENT, ENDPOINT_KEY)
+			.unwrap_or_else(Self::endpoint);
+		if endpoint.is_empty() {
+			return Ok(());
+		}
+
+		let deposits: Vec<_> = Self::fetch_deposits(&endpoint)?.into_iter()
+			.filter(|deposit| !Self::is_settled(deposit)
+				&& !Self::attestations(&deposit.tx).iter().any(|(attester, _)| *attester == relayer))
+			.take(MAX_ATTESTATIONS)
+			.collect();
+		if deposits.is_empty() {
+			return Ok(());
+		}
+		// one transaction per block, as each would take the same nonce
+		let call = Call::attest_deposits(deposits);
+		T::SubmitTransaction::sign_and_submit(call, relayer)
+			.map_err(|_| "Gateway error: submitting attestations failed")
+	}
+
+	fn fetch_deposits(endpoint: &[u8]) -> rstd::result::Result<Vec<DepositOf<T>>, &'static str> {
+		let url = rstd::str::from_utf8(endpoint).map_err(|_| "Gateway error: endpoint is not utf8")?;
+		let request: http::Request = http::Request::get(url);
+		let response = request.send()
+			.map_err(|_| "Gateway error: deposit request failed")?
+			.wait()
+			.map_err(|_| "Gateway error: deposit request failed")?;
+		ensure!(response.code == 200, "Gateway error: unexpected deposit response status");
+		let body: Vec<u8> = response.body().collect();
+		Decode::decode(&mut &body[..]).map_err(|_| "Gateway error: undecodable deposits")
+	}
+}
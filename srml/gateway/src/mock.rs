@@ -0,0 +1,158 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Test utilities
+
+#![cfg(test)]
+
+use crate::{Module, Trait};
+use sr_primitives::Perbill;
+use sr_primitives::testing::{Header, UintAuthorityId, TestXt};
+use sr_primitives::traits::{IdentityLookup, BlakeTwo256};
+use primitives::{H256, Blake2Hasher};
+use support::{impl_outer_origin, impl_outer_dispatch, parameter_types};
+use {runtime_io, system};
+
+impl_outer_origin!{
+	pub enum Origin for Test {}
+}
+
+impl_outer_dispatch! {
+	pub enum Call for Test where origin: Origin {
+		gateway::Gateway,
+	}
+}
+
+/// An extrinsic type used for tests.
+pub type Extrinsic = TestXt<Call, ()>;
+type SubmitTransaction = system::offchain::TransactionSubmitter<UintAuthorityId, Test, Extrinsic>;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Test;
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+
+impl system::Trait for Test {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Call = Call;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type WeightMultiplierUpdate = ();
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+	pub const TransferFee: u64 = 0;
+	pub const CreationFee: u64 = 0;
+	pub const TransactionBaseFee: u64 = 0;
+	pub const TransactionByteFee: u64 = 0;
+}
+
+impl balances::Trait for Test {
+	type Balance = u64;
+	type OnFreeBalanceZero = ();
+	type OnNewAccount = ();
+	type Event = ();
+	type TransactionPayment = ();
+	type TransferPayment = ();
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type TransferFee = TransferFee;
+	type CreationFee = CreationFee;
+	type TransactionBaseFee = TransactionBaseFee;
+	type TransactionByteFee = TransactionByteFee;
+	type WeightToFee = ();
+}
+
+parameter_types! {
+	pub const TokenDeposit: u64 = 0;
+	pub const HolderDeposit: u64 = 0;
+}
+
+impl token::Trait for Test {
+	type Event = ();
+	type Token = token::Module<Self>;
+	type Currency = balances::Module<Self>;
+	type TokenDeposit = TokenDeposit;
+	type OnAccountFrozen = ();
+	type HolderDeposit = HolderDeposit;
+	type DustRemoval = ();
+}
+
+/// Signs for any account, the signature being of no interest to the tests.
+impl system::offchain::CreateTransaction<Test, Extrinsic> for Test {
+	type Signature = u64;
+
+	fn create_transaction<F: system::offchain::Signer<u64, u64>>(
+		call: Call,
+		account: u64,
+		_nonce: u64,
+	) -> Option<(Call, (u64, ()))> {
+		F::sign(account, &call)?;
+		Some((call, (account, ())))
+	}
+}
+
+impl Trait for Test {
+	type Event = ();
+	type Call = Call;
+	type RelayerId = UintAuthorityId;
+	type SubmitTransaction = SubmitTransaction;
+}
+
+pub type Gateway = Module<Test>;
+pub type TokenModule = token::Module<Test>;
+
+/// Genesis with relayers 10, 11 and 12, two of them minting a deposit, and token 0 bridged.
+/// Account 1 holds 1000 of token 0.
+pub fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	token::GenesisConfig::<Test> {
+		key: 1,
+		tokens: vec![token::TokenInfo {
+			symbol: b"BTC".to_vec(),
+			name: b"Bitcoin".to_vec(),
+			decimals: 8,
+			issuer: 1,
+			max_supply: 1_000_000,
+		}],
+		balances: vec![(0, 1, 1000)],
+	}.assimilate_storage(&mut t).unwrap();
+	crate::GenesisConfig::<Test> {
+		relayers: vec![10, 11, 12],
+		threshold: 2,
+		endpoint: b"http://localhost:9933/deposits".to_vec(),
+	}.assimilate_storage(&mut t).unwrap();
+	let mut ext: runtime_io::TestExternalities<Blake2Hasher> = t.into();
+	runtime_io::with_externalities(&mut ext, || crate::Bridged::insert(0, true));
+	ext
+}
@@ -0,0 +1,162 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests for the gateway module.
+
+#![cfg(test)]
+
+use super::*;
+use crate::mock::*;
+use offchain::testing::{TestOffchainExt, PendingRequest};
+use runtime_io::with_externalities;
+use sr_primitives::testing::UintAuthorityId;
+use support::{assert_ok, assert_noop};
+
+fn deposit(tx: &[u8], dest: u64, amount: u64) -> DepositOf<Test> {
+	Deposit { tx: tx.to_vec(), token: 0, dest, amount }
+}
+
+#[test]
+fn withdrawals_burn_tokens() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_ok!(TokenModule::create_token(Origin::signed(1), b"ETH".to_vec(), b"Ether".to_vec(), 18, 1000));
+		assert_noop!(Gateway::request_withdraw(Origin::signed(1), 1, 100, b"0xabc".to_vec()),
+			"token is not bridged");
+		assert_noop!(Gateway::request_withdraw(Origin::signed(1), 0, 0, b"0xabc".to_vec()), "zero withdrawal");
+		assert_noop!(Gateway::request_withdraw(Origin::signed(1), 0, 100, vec![]), "invalid external address");
+		assert_noop!(Gateway::request_withdraw(Origin::signed(2), 0, 100, b"0xabc".to_vec()),
+			"Insufficient available balance");
+
+		assert_ok!(Gateway::request_withdraw(Origin::signed(1), 0, 100, b"0xabc".to_vec()));
+		assert_eq!(TokenModule::free_token((0, 1)), 900);
+		assert_eq!(TokenModule::total_issuance(0), 900);
+		assert_eq!(Gateway::next_withdrawal_id(), 1);
+	});
+}
+
+#[test]
+fn deposits_are_minted_once_at_the_threshold() {
+	with_externalities(&mut new_test_ext(), || {
+		let tx = b"0x01".to_vec();
+		assert_noop!(Gateway::attest_deposits(Origin::signed(5), vec![deposit(&tx, 2, 50)]), "not a relayer");
+
+		assert_ok!(Gateway::attest_deposits(Origin::signed(10), vec![deposit(&tx, 2, 50)]));
+		// attesting twice counts once
+		assert_ok!(Gateway::attest_deposits(Origin::signed(10), vec![deposit(&tx, 2, 50)]));
+		// a conflicting attestation does not add up with the first
+		assert_ok!(Gateway::attest_deposits(Origin::signed(11), vec![deposit(&tx, 2, 500)]));
+		assert_eq!(TokenModule::free_token((0, 2)), 0);
+		assert_eq!(Gateway::attestations(&tx).len(), 2);
+
+		assert_ok!(Gateway::attest_deposits(Origin::signed(12), vec![deposit(&tx, 2, 50)]));
+		assert_eq!(TokenModule::free_token((0, 2)), 50);
+		assert_eq!(TokenModule::total_issuance(0), 1050);
+		assert!(Gateway::is_processed(&tx));
+		assert!(Gateway::attestations(&tx).is_empty());
+
+		// a processed deposit is never minted again
+		assert_ok!(Gateway::attest_deposits(Origin::signed(10), vec![deposit(&tx, 2, 50)]));
+		assert_ok!(Gateway::attest_deposits(Origin::signed(11), vec![deposit(&tx, 2, 50)]));
+		assert_eq!(TokenModule::free_token((0, 2)), 50);
+	});
+}
+
+#[test]
+fn deposits_that_cannot_be_minted_are_rejected() {
+	with_externalities(&mut new_test_ext(), || {
+		// above the max supply of the token
+		let tx = b"0x02".to_vec();
+		assert_ok!(Gateway::attest_deposits(Origin::signed(10), vec![deposit(&tx, 2, 1_000_000)]));
+		assert_ok!(Gateway::attest_deposits(Origin::signed(11), vec![deposit(&tx, 2, 1_000_000)]));
+		assert!(!Gateway::is_processed(&tx));
+		assert_eq!(Gateway::rejected_deposit(&tx), Some(deposit(&tx, 2, 1_000_000)));
+		assert_eq!(TokenModule::total_issuance(0), 1000);
+		// rejected deposits are not attested again
+		assert_ok!(Gateway::attest_deposits(Origin::signed(12), vec![deposit(&tx, 2, 1_000_000)]));
+		assert!(Gateway::attestations(&tx).is_empty());
+
+		// of a token not bridged for now
+		let tx = b"0x04".to_vec();
+		assert_ok!(Gateway::set_bridged(Origin::ROOT, 0, false));
+		assert_ok!(Gateway::attest_deposits(Origin::signed(10), vec![deposit(&tx, 2, 50)]));
+		assert_ok!(Gateway::attest_deposits(Origin::signed(11), vec![deposit(&tx, 2, 50)]));
+		assert!(Gateway::rejected_deposit(&tx).is_some());
+
+		// root retries once the token is bridged again, a deposit still failing stays rejected
+		assert_noop!(Gateway::retry_deposit(Origin::signed(1), tx.clone()), "RequireRootOrigin");
+		assert_noop!(Gateway::retry_deposit(Origin::ROOT, tx.clone()), "token is not bridged");
+		assert_ok!(Gateway::set_bridged(Origin::ROOT, 0, true));
+		assert_ok!(Gateway::retry_deposit(Origin::ROOT, tx.clone()));
+		assert_eq!(TokenModule::free_token((0, 2)), 50);
+		assert!(Gateway::is_processed(&tx));
+		assert_eq!(Gateway::rejected_deposit(&tx), None);
+		assert_noop!(Gateway::retry_deposit(Origin::ROOT, tx), "no rejected deposit");
+		assert_noop!(Gateway::retry_deposit(Origin::ROOT, b"0x02".to_vec()),
+			"mint exceeds the max supply of the token");
+	});
+}
+
+#[test]
+fn relayers_can_be_replaced() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(Gateway::set_relayers(Origin::signed(1), vec![10], 1), "RequireRootOrigin");
+		assert_noop!(Gateway::set_relayers(Origin::ROOT, vec![10], 2), "invalid relayer threshold");
+		assert_noop!(Gateway::set_relayers(Origin::ROOT, vec![10], 0), "invalid relayer threshold");
+
+		let tx = b"0x03".to_vec();
+		assert_ok!(Gateway::attest_deposits(Origin::signed(10), vec![deposit(&tx, 2, 50)]));
+		assert_ok!(Gateway::set_relayers(Origin::ROOT, vec![11, 12, 13], 2));
+		// the attestation of 10 stopped counting
+		assert_ok!(Gateway::attest_deposits(Origin::signed(11), vec![deposit(&tx, 2, 50)]));
+		assert_eq!(TokenModule::free_token((0, 2)), 0);
+		assert_ok!(Gateway::attest_deposits(Origin::signed(13), vec![deposit(&tx, 2, 50)]));
+		assert_eq!(TokenModule::free_token((0, 2)), 50);
+	});
+}
+
+#[test]
+fn offchain_worker_attests_fetched_deposits() {
+	let mut ext = new_test_ext();
+	let (offchain, state) = TestOffchainExt::new();
+	ext.set_offchain_externalities(offchain);
+
+	with_externalities(&mut ext, || {
+		// a node without a relayer key stays idle
+		UintAuthorityId::set_all_keys(vec![5]);
+		assert_ok!(Gateway::relay());
+		assert!(state.read().requests.is_empty());
+
+		let seen = deposit(b"0x04", 2, 50);
+		let new = deposit(b"0x05", 3, 70);
+		assert_ok!(Gateway::attest_deposits(Origin::signed(11), vec![seen.clone()]));
+		state.write().expect_request(0, PendingRequest {
+			method: "GET".into(),
+			uri: "http://localhost:9933/deposits".into(),
+			sent: true,
+			response: vec![seen, new.clone()].encode(),
+			..Default::default()
+		});
+
+		UintAuthorityId::set_all_keys(vec![11]);
+		assert_ok!(Gateway::relay());
+
+		let transaction = state.write().transactions.pop().unwrap();
+		assert!(state.read().transactions.is_empty());
+		let ex: Extrinsic = Decode::decode(&mut &*transaction).unwrap();
+		assert_eq!(ex.0, Some((11, ())));
+		assert_eq!(ex.1, crate::mock::Call::Gateway(crate::Call::attest_deposits(vec![new])));
+	});
+}
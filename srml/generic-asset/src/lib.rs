@@ -852,6 +852,64 @@ impl<T: Trait> Module<T> {
 	}
 }
 
+/// Every asset of this module, told apart by its asset id.
+impl<T: Trait> support::traits::MultiCurrency<T::AccountId> for Module<T>
+where
+	T::AssetId: MaybeSerializeDebug
+{
+	type CurrencyId = T::AssetId;
+	type Balance = T::Balance;
+
+	fn currency_exists(asset_id: T::AssetId) -> bool {
+		<TotalIssuance<T>>::exists(&asset_id)
+	}
+
+	fn total_issuance(asset_id: T::AssetId) -> T::Balance {
+		Self::total_issuance(asset_id)
+	}
+
+	fn free_balance(asset_id: T::AssetId, who: &T::AccountId) -> T::Balance {
+		Self::free_balance(&asset_id, who)
+	}
+
+	fn reserved_balance(asset_id: T::AssetId, who: &T::AccountId) -> T::Balance {
+		Self::reserved_balance(&asset_id, who)
+	}
+
+	fn transfer(asset_id: T::AssetId, source: &T::AccountId, dest: &T::AccountId, value: T::Balance) -> Result {
+		Self::make_transfer(&asset_id, source, dest, value)
+	}
+
+	fn reserve(asset_id: T::AssetId, who: &T::AccountId, value: T::Balance) -> Result {
+		Self::reserve(&asset_id, who, value)
+	}
+
+	fn unreserve(asset_id: T::AssetId, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		Self::unreserve(&asset_id, who, value)
+	}
+
+	fn repatriate_reserved(
+		asset_id: T::AssetId,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		value: T::Balance,
+	) -> result::Result<T::Balance, &'static str> {
+		Ok(Self::repatriate_reserved(&asset_id, slashed, beneficiary, value))
+	}
+
+	fn slash(asset_id: T::AssetId, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		let remaining = Self::slash(&asset_id, who, value).unwrap_or_else(Zero::zero);
+		<TotalIssuance<T>>::mutate(&asset_id, |issued| *issued = issued.saturating_sub(value - remaining));
+		remaining
+	}
+
+	fn slash_reserved(asset_id: T::AssetId, who: &T::AccountId, value: T::Balance) -> T::Balance {
+		let remaining = Self::slash_reserved(&asset_id, who, value).unwrap_or_else(Zero::zero);
+		<TotalIssuance<T>>::mutate(&asset_id, |issued| *issued = issued.saturating_sub(value - remaining));
+		remaining
+	}
+}
+
 pub trait AssetIdProvider {
 	type AssetId;
 	fn asset_id() -> Self::AssetId;
@@ -1286,3 +1286,23 @@ fn burn_should_raise_event() {
 		},
 	);
 }
+
+#[test]
+fn multi_currency_slash_should_reduce_total_issuance() {
+	use support::traits::MultiCurrency;
+
+	with_externalities(&mut ExtBuilder::default().free_balance((16000, 1, 100)).build(), || {
+		assert!(<GenericAsset as MultiCurrency<u64>>::currency_exists(16000));
+		assert!(!<GenericAsset as MultiCurrency<u64>>::currency_exists(16005));
+		assert_ok!(<GenericAsset as MultiCurrency<u64>>::reserve(16000, &1, 40));
+
+		// free funds are slashed first
+		assert_eq!(<GenericAsset as MultiCurrency<u64>>::slash(16000, &1, 70), 0);
+		assert_eq!(GenericAsset::free_balance(&16000, &1), 0);
+		assert_eq!(GenericAsset::reserved_balance(&16000, &1), 30);
+		assert_eq!(GenericAsset::total_issuance(16000), 30);
+
+		assert_eq!(<GenericAsset as MultiCurrency<u64>>::slash_reserved(16000, &1, 50), 20);
+		assert_eq!(GenericAsset::total_issuance(16000), 0);
+	});
+}
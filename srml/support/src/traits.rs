@@ -546,6 +546,69 @@ pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
 	) -> result::Result<Self::Balance, &'static str>;
 }
 
+/// Balances in several currencies, each told apart by a currency id.
+///
+/// Unlike `Currency` no imbalances are handed out: anything slashed is destroyed and the
+/// issuance of the currency is reduced by the implementation.
+pub trait MultiCurrency<AccountId> {
+	/// Identifies one of the currencies.
+	type CurrencyId: Codec + Copy + Eq + MaybeSerializeDebug;
+
+	/// The balance of an account in any of the currencies.
+	type Balance: SimpleArithmetic + Codec + Copy + MaybeSerializeDebug + Default;
+
+	/// Whether balances can be held in `currency`.
+	fn currency_exists(currency: Self::CurrencyId) -> bool;
+
+	/// The total amount of `currency` in existence.
+	fn total_issuance(currency: Self::CurrencyId) -> Self::Balance;
+
+	/// Whether `who` may receive and move `currency`. Only currencies restricting their holders
+	/// say no.
+	fn can_hold(_currency: Self::CurrencyId, _who: &AccountId) -> bool {
+		true
+	}
+
+	/// The part of `who`'s `currency` that can be transferred or reserved.
+	fn free_balance(currency: Self::CurrencyId, who: &AccountId) -> Self::Balance;
+
+	/// The part of `who`'s `currency` set aside by `reserve`.
+	fn reserved_balance(currency: Self::CurrencyId, who: &AccountId) -> Self::Balance;
+
+	/// Move `value` of free `currency` from `source` to `dest`.
+	fn transfer(
+		currency: Self::CurrencyId,
+		source: &AccountId,
+		dest: &AccountId,
+		value: Self::Balance,
+	) -> result::Result<(), &'static str>;
+
+	/// Move `value` of `who`'s `currency` from free to reserved, or nothing if the free
+	/// balance is lower.
+	fn reserve(currency: Self::CurrencyId, who: &AccountId, value: Self::Balance) -> result::Result<(), &'static str>;
+
+	/// Move up to `value` of `who`'s `currency` from reserved to free. Returns the part of
+	/// `value` that was not reserved.
+	fn unreserve(currency: Self::CurrencyId, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Move up to `value` of reserved `currency` of `slashed` to the free balance of
+	/// `beneficiary`. Returns the part of `value` that was not reserved.
+	fn repatriate_reserved(
+		currency: Self::CurrencyId,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		value: Self::Balance,
+	) -> result::Result<Self::Balance, &'static str>;
+
+	/// Destroy up to `value` of `who`'s `currency`, free balance first. Returns the part of
+	/// `value` that could not be slashed.
+	fn slash(currency: Self::CurrencyId, who: &AccountId, value: Self::Balance) -> Self::Balance;
+
+	/// Destroy up to `value` of `who`'s reserved `currency`. Returns the part of `value` that
+	/// could not be slashed.
+	fn slash_reserved(currency: Self::CurrencyId, who: &AccountId, value: Self::Balance) -> Self::Balance;
+}
+
 /// An identifier for a lock. Used for disambiguating different locks so that
 /// they can be individually replaced or removed.
 pub type LockIdentifier = [u8; 8];
@@ -605,6 +605,16 @@ impl<T: Trait> Module<T> {
 		AllExtrinsicsWeight::get().unwrap_or_default()
 	}
 
+	/// Add `weight` to the weight of the current block without checking it against the limit.
+	///
+	/// For work done outside of extrinsics, such as in `on_initialize`, to take room from the
+	/// extrinsics of the same block.
+	pub fn register_extra_weight_unchecked(weight: Weight) {
+		AllExtrinsicsWeight::mutate(|current_weight| {
+			*current_weight = Some(current_weight.unwrap_or_default().saturating_add(weight));
+		});
+	}
+
 	pub fn all_extrinsics_len() -> u32 {
 		AllExtrinsicsLen::get().unwrap_or_default()
 	}
@@ -16,91 +16,279 @@
 
 //! # Token Module
 //!
-//! - [`sudo::Trait`](./trait.Trait.html)
+//! - [`token::Trait`](./trait.Trait.html)
 //! - [`Call`](./enum.Call.html)
+//! - [`Token`](./trait.Token.html)
 //!
 //! ## Overview
 //!
-//! The Sudo module allows for a single account (called the "sudo key")
-//! to execute dispatchable functions that require a `Root` call
-//! or designate a new account to replace them as the sudo key.
-//! Only one account can be the sudo key at a time.
+//! The Token module is a registry of fungible tokens next to the native currency. Any account
+//! may create a token, becoming its issuer, and the module keeps the balances, locks,
+//! allowances and vesting schedules of every token. Other modules move tokens through the
+//! [`Token`] trait or through `MultiCurrency`, which the DEX uses to lock orders and settle
+//! trades.
 //!
-//! ## Interface
-//!
-//! ### Dispatchable Functions
+//! ### Terminology
 //!
-//! Only the sudo key can call the dispatchable functions from the Sudo module.
+//! - **Issuer:** The account that created a token, or was handed it. Only the issuer mints and
+//!   burns a token, freezes its holders and manages its allowlist, minimum balance and snapshots.
+//! - **Free balance:** The part of a holding that can be moved. Tokens under a lock are not.
+//! - **Lock:** An amount of a holding held under a `LockIdentifier`. Locks overlap, only the
+//!   largest counts, except the reserve under `RESERVE_LOCK_ID` which comes on top of them.
+//! - **Holder deposit:** `HolderDeposit` of the native currency reserved from whoever creates a
+//!   holding, returned when the holding is removed.
+//! - **Dust:** A holding below the token's minimum balance. It is burned and the holding removed,
+//!   unless it is locked.
+//! - **Wrapped native token:** A token backed one to one by native currency reserved in the
+//!   native pot, minted by `deposit_native` and burned by `withdraw_native`.
 //!
-//! * `transfer_free_token` - Make a `Root` call to a dispatchable function.
-//! * `add_new_tokentype` - Assign a new account to be the sudo key.
+//! ## Interface
 //!
-//! ## Usage
+//! ### Dispatchable Functions
 //!
-//! ### Executing Privileged Functions
+//! Any signed account:
 //!
-//! The Sudo module itself is not intended to be used within other modules.
-//! Instead, you can build "privileged functions" (i.e. functions that require `Root` origin) in other modules.
-//! You can execute these privileged functions by calling `sudo` with the sudo key account.
-//! Privileged functions cannot be directly executed via an extrinsic.
+//! * `transfer_free_token` - Transfer free tokens to another account.
+//! * `transfer_batch`, `transfer_batch_multi` - Transfer free tokens to many accounts at once, all or none.
+//! * `create_token` - Register a new token and become its issuer, reserving `TokenDeposit`.
+//! * `approve` - Set how much of the sender's tokens a spender may move.
+//! * `increase_allowance`, `decrease_allowance` - Adjust an allowance relative to its current value.
+//! * `transfer_from` - Move tokens of an owner to another account, spending an allowance.
+//! * `deposit_native` - Put native currency into the native pot and receive as many wrapped tokens.
+//! * `withdraw_native` - Burn wrapped tokens and get as much native currency back from the pot.
+//! * `vested_transfer` - Transfer tokens that stay locked for the receiver until they vest.
+//! * `vest` - Unlock the part of the sender's vesting tokens released so far.
+//! * `distribute` - Share tokens out to the holders of a token at a snapshot, pro rata.
+//! * `claim` - Collect the sender's share of a distribution.
 //!
-//! Learn more about privileged functions and `Root` origin in the [`Origin`] type documentation.
+//! The issuer of the token:
 //!
-//! ### Simple Code Snippet
+//! * `mint_token` - Issue new tokens to an account, up to the token's max supply.
+//! * `burn_token` - Destroy free tokens of an account.
+//! * `transfer_issuer` - Hand the issuer role of a token to another account.
+//! * `freeze_account`, `thaw_account` - Stop and resume all movements of an account's tokens.
+//! * `set_allowlist_only` - Restrict a token to allowlisted accounts.
+//! * `set_allowlisted` - Add an account to or remove it from the allowlist of a token.
+//! * `snapshot` - Record the balances of a token as of now.
+//! * `set_min_balance` - Set the balance below which holdings of a token are removed as dust.
 //!
-//! This is an example of a module that exposes a privileged function:
+//! Root:
 //!
-//! ```
-//! use support::{decl_module, dispatch::Result};
-//! use system::ensure_root;
+//! * `force_create_token` - Register a new token for any issuer without a deposit.
+//! * `create_wrapped_native` - Register the token wrapping the native currency.
+//! * `migrate_legacy_balances` - Move balances kept under a storage version 0 token type to its `TokenId`.
+//! * `index_holders` - List holders from before the holder index among the holders of a token.
 //!
-//! pub trait Trait: system::Trait {}
+//! ### Events
 //!
-//! decl_module! {
-//!     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-//!         pub fn privileged_function(origin) -> Result {
-//!             ensure_root(origin)?;
+//! Every change of a balance, lock, allowance, issuer or account permission deposits an
+//! [`Event`](./enum.Event.html): `Transferred`, `Minted` and `Burned` for movements, `Locked`
+//! and `Unlocked` for locks, `DustRemoved` for holdings removed as dust, and one event for each
+//! of the issuer's settings.
 //!
-//!             // do something...
+//! ### Public Functions
 //!
-//!             Ok(())
-//!         }
-//!     }
-//! }
-//! # fn main() {}
-//! ```
+//! * `holders` - A page of the accounts holding a token.
+//! * `balances_of` - The free and locked balance of every token an account holds.
 //!
 //! ## Genesis Config
 //!
-//! The Sudo module depends on the [`GenesisConfig`](./struct.GenesisConfig.html).
-//! You need to set an initial superuser account as the sudo `key`.
+//! Tokens listed in `tokens` are registered with ids from 0 in order, and `balances` are issued
+//! to their holders free of the holder deposit.
 //!
 //! ## Related Modules
 //!
-//! * [Consensus](../srml_consensus/index.html)
-//! * [Democracy](../srml_democracy/index.html)
+//! * [DEX](../srml_dex/index.html)
+//! * [Gateway](../srml_gateway/index.html)
 //!
 //! [`Call`]: ./enum.Call.html
 //! [`Trait`]: ./trait.Trait.html
-//! [`Origin`]: https://docs.substrate.dev/docs/substrate-types
+//! [`Token`]: ./trait.Token.html
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use rstd::prelude::*;
 use sr_primitives::{
-	traits::{StaticLookup, Dispatchable, SimpleArithmetic}, weights::SimpleDispatchInfo, DispatchError,
+	traits::{StaticLookup, Dispatchable, SimpleArithmetic, AccountIdConversion, SaturatedConversion, Zero,
+		CheckedAdd, CheckedSub},
+	weights::{SimpleDispatchInfo, Weight, WeighData, ClassifyDispatch, DispatchClass}, DispatchError, ModuleId,
 };
 use support::{StorageValue, StorageMap, Parameter, decl_module, decl_event, decl_storage, ensure,
              dispatch::Result};
-use support::traits::{Currency,ExistenceRequirement,WithdrawReason};
-use system::ensure_signed;
+use support::storage::hashed;
+use support::traits::{Currency,ReservableCurrency,ExistenceRequirement,WithdrawReason,Get};
+pub use support::traits::LockIdentifier;
+use system::{ensure_signed, ensure_root};
 use generic_asset;
 use codec::{Encode, Decode, Codec};
 
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 #[cfg(feature = "std")]
 #[macro_use]
 extern crate serde_derive;
 
+/// Storage layout version of this module, see `Module::on_runtime_upgrade`.
+/// Version 1 keys balances by `TokenId` instead of the token type bytes.
+const STORAGE_VERSION: u32 = 1;
+
+/// Token types created at genesis before the registry existed.
+const LEGACY_GENESIS_TOKENS: [[u8; 2]; 2] = [[1, 2], [3, 4]];
+
+/// The lock balances locked before locks were named are held under. The DEX was
+/// the only module locking tokens then, and locks its orders under the same id.
+pub const LEGACY_LOCK_ID: LockIdentifier = *b"dexorder";
+
+/// The lock `MultiCurrency` reserves tokens under. Balances locked before locks were named
+/// were all order locks of the DEX, which reserves through `MultiCurrency` now.
+///
+/// Unlike other locks it does not overlap: reserved tokens are held on top of the largest
+/// other lock, so they can never be tokens another lock holds.
+pub const RESERVE_LOCK_ID: LockIdentifier = LEGACY_LOCK_ID;
+
+/// The lock tokens received through `vested_transfer` are held under until they vest.
+pub const VESTING_LOCK_ID: LockIdentifier = *b"vesting ";
+
+/// The account holding the native currency backing the wrapped native token, all of it reserved.
+const NATIVE_POT_ID: ModuleId = ModuleId(*b"py/wnatv");
+
+/// The account holding the tokens of distributions until their holders claim them.
+const DISTRIBUTION_POT_ID: ModuleId = ModuleId(*b"py/tkdiv");
+
+const MAX_SYMBOL_LEN: usize = 16;
+const MAX_NAME_LEN: usize = 64;
+
+/// The most holders `holders` returns at once.
+pub const MAX_HOLDERS_PAGE: u32 = 1000;
+
+/// The most accounts `migrate_legacy_balances` and `index_holders` take at once.
+pub const MAX_MIGRATION_ACCOUNTS: usize = 1000;
+
+/// The most vesting schedules of a token an account may have running at once.
+pub const MAX_VESTING_SCHEDULES: usize = 8;
+
+/// Compact identifier a token is assigned at creation.
+pub type TokenId = u32;
+
+/// Number of a snapshot of a token's balances, counting from 1 per token.
+pub type SnapshotId = u32;
+
+/// Identifier a distribution is assigned when made.
+pub type DistributionId = u32;
+
+/// Registry entry of a token.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct TokenInfo<AccountId> {
+	pub symbol: Vec<u8>,
+	pub name: Vec<u8>,
+	pub decimals: u8,
+	pub issuer: AccountId,
+	pub max_supply: u64,
+}
+
+pub type TokenInfoT<T> = TokenInfo<<T as system::Trait>::AccountId>;
+
+/// A lock on a token balance, named so the modules locking the same balance do not clash.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct TokenLock {
+	pub id: LockIdentifier,
+	pub amount: u64,
+}
+
+/// Release of tokens received through `vested_transfer`. Nothing is released before
+/// `start + cliff`, from then on everything released `per_block` since `start` is.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct VestingSchedule<BlockNumber> {
+	/// Amount transferred and locked at `start`.
+	pub locked: u64,
+	/// Amount released every block after `start`.
+	pub per_block: u64,
+	pub start: BlockNumber,
+	/// Blocks after `start` before the first release.
+	pub cliff: BlockNumber,
+}
+
+impl<BlockNumber: SimpleArithmetic + Copy> VestingSchedule<BlockNumber> {
+	/// Amount still locked at block `n`.
+	pub fn locked_at(&self, n: BlockNumber) -> u64 {
+		if n < self.start.saturating_add(self.cliff) {
+			return self.locked;
+		}
+		let vested_blocks: u64 = n.saturating_sub(self.start).saturated_into();
+		self.locked.saturating_sub(vested_blocks.saturating_mul(self.per_block))
+	}
+}
+
+/// A checkpoint of a token's balances. Balances themselves are recorded lazily, the first time
+/// each changes after the snapshot, see `Module::balance_at`.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct SnapshotInfo<BlockNumber> {
+	pub block: BlockNumber,
+	pub total_issuance: u64,
+}
+
+/// Tokens shared out among the holders of `token` at `snapshot`, each claiming
+/// `total * balance / supply`.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct Distribution {
+	pub token: TokenId,
+	pub snapshot: SnapshotId,
+	pub reward_token: TokenId,
+	pub total: u64,
+	/// Issuance of `token` at `snapshot`.
+	pub supply: u64,
+	/// Claimed so far, what is left when everyone claimed is rounding dust.
+	pub claimed: u64,
+}
+
+/// Weight of a batch of transfers, the given weight for each of its legs.
+pub struct TransferBatchWeight(pub Weight);
+
+impl<'a, AccountId> WeighData<(&'a TokenId, &'a Vec<(AccountId, u64)>)> for TransferBatchWeight {
+	fn weigh_data(&self, (_, legs): (&'a TokenId, &'a Vec<(AccountId, u64)>)) -> Weight {
+		self.0.saturating_mul(legs.len().max(1) as Weight)
+	}
+}
+
+impl<'a, AccountId> WeighData<(&'a Vec<(TokenId, AccountId, u64)>,)> for TransferBatchWeight {
+	fn weigh_data(&self, (legs,): (&'a Vec<(TokenId, AccountId, u64)>,)) -> Weight {
+		self.0.saturating_mul(legs.len().max(1) as Weight)
+	}
+}
+
+impl<T> ClassifyDispatch<T> for TransferBatchWeight {
+	fn classify_dispatch(&self, _: T) -> DispatchClass {
+		DispatchClass::Normal
+	}
+}
+
+/// Weight of a call working through a list of accounts, the given weight for each of them.
+pub struct PerAccountWeight(pub Weight);
+
+impl<'a, A, AccountId> WeighData<(&'a A, &'a Vec<AccountId>)> for PerAccountWeight {
+	fn weigh_data(&self, (_, accounts): (&'a A, &'a Vec<AccountId>)) -> Weight {
+		self.0.saturating_mul(accounts.len().max(1) as Weight)
+	}
+}
+
+impl<T> ClassifyDispatch<T> for PerAccountWeight {
+	fn classify_dispatch(&self, _: T) -> DispatchClass {
+		DispatchClass::Operational
+	}
+}
+
+pub type VestingScheduleT<T> = VestingSchedule<<T as system::Trait>::BlockNumber>;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
 #[derive(PartialEq, Eq, Clone, Copy, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
 pub enum TokenControl {
@@ -108,120 +296,322 @@ pub enum TokenControl {
 	Lock,
 }
 
+/// Handler for when an account may no longer move a token, because the issuer froze it or
+/// took it off the token's allowlist.
+pub trait OnAccountFrozen<AccountId> {
+	fn on_account_frozen(token:TokenId, who:&AccountId);
+}
+
+impl<AccountId> OnAccountFrozen<AccountId> for () {
+	fn on_account_frozen(_token:TokenId, _who:&AccountId) {}
+}
+
+/// Handler for dust removed from token balances that fell below the token's minimum balance.
+/// The dust is burned already, a handler may issue it again elsewhere.
+pub trait OnTokenDust {
+	fn on_dust(token:TokenId, amount:u64);
+}
+
+impl OnTokenDust for () {
+	fn on_dust(_token:TokenId, _amount:u64) {}
+}
+
 pub trait Token<AccountId>{
 	type Tokens: Copy + Default + SimpleArithmetic;
 
-	fn amount_free(who:&AccountId, tokentype:&Vec<u8>) -> Self::Tokens;
+	fn amount_free(who:&AccountId, token:TokenId) -> Self::Tokens;
 
-	fn amount_lock(who:&AccountId, tokentype:&Vec<u8>) -> Self::Tokens;
+	fn amount_lock(who:&AccountId, token:TokenId) -> Self::Tokens;
 
-	fn set_free_token(who:&AccountId, tokentype:&Vec<u8>, val:Self::Tokens);
+	fn set_free_token(who:&AccountId, token:TokenId, val:Self::Tokens);
 
-	fn set_lock_token(who:&AccountId, tokentype:&Vec<u8>, val:Self::Tokens);
+	fn set_lock_token(who:&AccountId, token:TokenId, val:Self::Tokens);
 
-	fn transfer(source: &AccountId, dest: &AccountId, tokentype:&Vec<u8>, value: Self::Tokens, ) -> Result;
+	fn transfer(source: &AccountId, dest: &AccountId, token:TokenId, value: Self::Tokens, ) -> Result;
 
-	fn lock(who:&AccountId, tokentype:&Vec<u8>, amount:Self::Tokens) -> Result;
+	/// The amount held by the lock `id` on `who`'s `token`.
+	fn lock_amount(id:LockIdentifier, who:&AccountId, token:TokenId) -> Self::Tokens;
 
-	fn unlock(who:&AccountId, tokentype:&Vec<u8>, amount:Self::Tokens) -> Result;
+	/// Create or replace the lock `id` on `who`'s `token`. Locks overlap, the largest one is
+	/// kept out of the free balance, along with the reserve under `RESERVE_LOCK_ID`.
+	/// A zero amount removes the lock.
+	fn set_lock(id:LockIdentifier, who:&AccountId, token:TokenId, amount:Self::Tokens) -> Result;
 
-	fn mint(dest:&AccountId, tokentype:&Vec<u8>, amount:Self::Tokens) -> Result;
+	/// Raise the lock `id` to `amount` unless it already holds more, creating it if needed.
+	fn extend_lock(id:LockIdentifier, who:&AccountId, token:TokenId, amount:Self::Tokens) -> Result;
 
-	fn burn(dest:&AccountId, tokentype:&Vec<u8>, amount:Self::Tokens) -> Result;
+	fn remove_lock(id:LockIdentifier, who:&AccountId, token:TokenId);
 
-	fn vaild_tokentype(tokentype:&Vec<u8>) -> Result;
+	/// Move `value` held by the lock `id` of `source` into the free balance of `dest`,
+	/// shrinking the lock by as much. Other locks on `source` do not stand in the way.
+	fn transfer_locked(id:LockIdentifier, source:&AccountId, dest:&AccountId, token:TokenId,
+					   value:Self::Tokens) -> Result;
+
+	fn mint(dest:&AccountId, token:TokenId, amount:Self::Tokens) -> Result;
+
+	fn burn(dest:&AccountId, token:TokenId, amount:Self::Tokens) -> Result;
+
+	fn vaild_tokentype(token:TokenId) -> Result;
+
+	/// How much of `owner`'s free `token` the `spender` may still move.
+	fn amount_allowed(owner:&AccountId, spender:&AccountId, token:TokenId) -> Self::Tokens;
+
+	/// Let `spender` move up to `amount` of `owner`'s free `token`, replacing any earlier allowance.
+	fn set_allowance(owner:&AccountId, spender:&AccountId, token:TokenId, amount:Self::Tokens) -> Result;
+
+	/// Move `value` of `owner`'s free `token` to `dest` on behalf of `spender`, using up its allowance.
+	fn transfer_with_allowance(spender:&AccountId, owner:&AccountId, dest:&AccountId, token:TokenId,
+							   value:Self::Tokens) -> Result;
 }
 
 impl<T:Trait> Token<T::AccountId> for Module<T>{
 	type Tokens = u64;
 
-	fn amount_free(who:&T::AccountId, tokentype:&Vec<u8>) -> Self::Tokens{
-        <FreeToken<T>>::get((tokentype.clone(),who.clone()))
+	fn amount_free(who:&T::AccountId, token:TokenId) -> Self::Tokens{
+        <FreeToken<T>>::get((token,who.clone()))
 	}
 
-	fn amount_lock(who:&T::AccountId, tokentype:&Vec<u8>) -> Self::Tokens{
-		<LockedToken<T>>::get((tokentype.clone(),who.clone()))
+	fn amount_lock(who:&T::AccountId, token:TokenId) -> Self::Tokens{
+		<LockedToken<T>>::get((token,who.clone()))
 	}
 
-	fn set_free_token(who:&T::AccountId, tokentype:&Vec<u8>, val:Self::Tokens){
-		<FreeToken<T>>::insert((tokentype.clone(),who.clone()),val);
+	fn set_free_token(who:&T::AccountId, token:TokenId, val:Self::Tokens){
+		Self::checkpoint(token, who);
+		if val == 0 {
+			<FreeToken<T>>::remove((token,who.clone()));
+		} else {
+			<FreeToken<T>>::insert((token,who.clone()),val);
+		}
 	}
 
-	fn set_lock_token(who:&T::AccountId, tokentype:&Vec<u8>, val:Self::Tokens){
-		<LockedToken<T>>::insert((tokentype.clone(),who.clone()),val);
+	fn set_lock_token(who:&T::AccountId, token:TokenId, val:Self::Tokens){
+		Self::checkpoint(token, who);
+		if val == 0 {
+			<LockedToken<T>>::remove((token,who.clone()));
+		} else {
+			<LockedToken<T>>::insert((token,who.clone()),val);
+		}
 	}
 
 	fn transfer(
 		source: &T::AccountId,
 		dest: &T::AccountId,
-		tokentype:&Vec<u8>,
+		token:TokenId,
 		value: Self::Tokens,
 	) -> Result{
-		Self::vaild_tokentype(tokentype)?;
+		Self::transfer_paying(source, dest, token, value, source)
+	}
 
-		if value > Self::amount_free(source, tokentype){
-			return Err("Insufficient available balance");
-		}
-		let source_new = Self::amount_free(source, tokentype) - value;
-		let dest_new = Self::amount_free(dest, tokentype) + value;
+	fn lock_amount(id:LockIdentifier, who:&T::AccountId, token:TokenId) -> Self::Tokens{
+		Self::locks_of(token, who).iter().find(|lock| lock.id == id).map_or(0, |lock| lock.amount)
+	}
 
-		Self::set_free_token(source,tokentype,source_new);
-		Self::set_free_token(dest,tokentype,dest_new);
+	fn set_lock(id:LockIdentifier, who:&T::AccountId, token:TokenId, value:Self::Tokens) -> Result{
+		Self::vaild_tokentype(token)?;
+		if value == 0 {
+			Self::remove_lock(id, who, token);
+			return Ok(());
+		}
+		ensure!(value <= Self::amount_free(who, token) + Self::amount_lock(who, token), "lock exceeds the balance");
+		// locks of frozen accounts may still shrink, for their orders to be cancelled
+		if value > Self::lock_amount(id, who, token) {
+			Self::ensure_can_hold(token, who)?;
+		}
 
-        Ok(())
+		let mut locks = Self::locks_of(token, who);
+		match locks.iter_mut().find(|lock| lock.id == id) {
+			Some(lock) => lock.amount = value,
+			None => locks.push(TokenLock{ id, amount: value }),
+		}
+		Self::update_locks(token, who, locks);
+		Self::deposit_event(RawEvent::Locked(token, who.clone(), id, value));
+		Ok(())
 	}
 
-	fn lock(who:&T::AccountId, tokentype:&Vec<u8>, value:Self::Tokens) -> Result{
-		Self::vaild_tokentype(tokentype)?;
+	fn extend_lock(id:LockIdentifier, who:&T::AccountId, token:TokenId, value:Self::Tokens) -> Result{
+		if value <= Self::lock_amount(id, who, token) {
+			return Ok(());
+		}
+		Self::set_lock(id, who, token, value)
+	}
 
-		if value > Self::amount_free(who, tokentype){
-			return Err("Insufficient available balance");
+	fn remove_lock(id:LockIdentifier, who:&T::AccountId, token:TokenId){
+		let mut locks = Self::locks_of(token, who);
+		let count = locks.len();
+		locks.retain(|lock| lock.id != id);
+		if locks.len() != count {
+			Self::update_locks(token, who, locks);
+			Self::deposit_event(RawEvent::Unlocked(token, who.clone(), id));
+			Self::reap_dust(token, who);
 		}
-		let free_new = Self::amount_free(who, tokentype) - value;
-		let lcok_new = Self::amount_lock(who, tokentype) + value;
+	}
+
+	fn transfer_locked(
+		id:LockIdentifier,
+		source:&T::AccountId,
+		dest:&T::AccountId,
+		token:TokenId,
+		value:Self::Tokens,
+	) -> Result{
+		Self::vaild_tokentype(token)?;
+		Self::ensure_can_hold(token, source)?;
+		Self::ensure_can_hold(token, dest)?;
 
-		Self::set_free_token(who,tokentype,free_new);
-		Self::set_lock_token(who,tokentype,lcok_new);
+		if source != dest {
+			Self::amount_free(dest, token).checked_add(value).ok_or("token balance overflow")?;
+		}
+		// settlement pays out to an account that ordered the token and paid the trading fee for
+		// it, so the holding it may create is free of the `HolderDeposit`
+		Self::debit_locked(id, source, token, value)?;
+		Self::credit(dest, token, value, None)?;
+		Self::reap_dust(token, source);
+		Self::deposit_event(RawEvent::Transferred(token, source.clone(), dest.clone(), value));
 		Ok(())
 	}
 
-	fn unlock(who:&T::AccountId, tokentype:&Vec<u8>, value:Self::Tokens) -> Result{
-		Self::vaild_tokentype(tokentype)?;
+	/// Holdings minted this way are free of the `HolderDeposit`: the gateway mints what was
+	/// paid in on the external chain, and a mint failing for want of a deposit would lose it.
+	fn mint(dest:&T::AccountId, token:TokenId, value:Self::Tokens) -> Result{
+		Self::mint_paying(dest, token, value, None)
+	}
+
+	fn burn(dest:&T::AccountId, token:TokenId, value:Self::Tokens) -> Result{
+		Self::vaild_tokentype(token)?;
 
-		if value > Self::amount_lock(who, tokentype){
+		if value > Self::amount_free(dest, token){
 			return Err("Insufficient available balance");
 		}
-		let free_new = Self::amount_free(who, tokentype) + value;
-		let lcok_new = Self::amount_lock(who, tokentype) - value;
+		let new_value = Self::amount_free(dest, token) - value;
+		Self::destroy(token, value)?;
+		Self::set_free_token(dest,token,new_value);
+		Self::deposit_event(RawEvent::Burned(token, dest.clone(), value));
+		Self::reap_dust(token, dest);
+		Ok(())
+	}
 
-		Self::set_free_token(who,tokentype,free_new);
-		Self::set_lock_token(who,tokentype,lcok_new);
+	fn vaild_tokentype(token:TokenId) -> Result{
+		if !<TokenRegistry<T>>::exists(token){
+			return Err("invalid tokentype");
+		}
 		Ok(())
 	}
 
-	fn mint(dest:&T::AccountId, tokentype:&Vec<u8>, value:Self::Tokens) -> Result{
-		Self::vaild_tokentype(tokentype)?;
+	fn amount_allowed(owner:&T::AccountId, spender:&T::AccountId, token:TokenId) -> Self::Tokens{
+		<Allowance<T>>::get((token, owner.clone(), spender.clone()))
+	}
+
+	fn set_allowance(owner:&T::AccountId, spender:&T::AccountId, token:TokenId, value:Self::Tokens) -> Result{
+		Self::vaild_tokentype(token)?;
 
-		Self::set_free_token(dest,tokentype,value);
+		let key = (token, owner.clone(), spender.clone());
+		if value == 0 {
+			<Allowance<T>>::remove(key);
+		} else {
+			<Allowance<T>>::insert(key, value);
+		}
+		Self::deposit_event(RawEvent::Approval(token, owner.clone(), spender.clone(), value));
 		Ok(())
 	}
 
-	fn burn(dest:&T::AccountId, tokentype:&Vec<u8>, value:Self::Tokens) -> Result{
-		Self::vaild_tokentype(tokentype)?;
+	fn transfer_with_allowance(
+		spender:&T::AccountId,
+		owner:&T::AccountId,
+		dest:&T::AccountId,
+		token:TokenId,
+		value:Self::Tokens,
+	) -> Result{
+		let allowed = Self::amount_allowed(owner, spender, token);
+		ensure!(value <= allowed, "transfer exceeds the allowance");
 
-		if value > Self::amount_free(dest, tokentype){
-			return Err("Insufficient available balance");
+		Self::transfer(owner, dest, token, value)?;
+		let key = (token, owner.clone(), spender.clone());
+		if allowed == value {
+			<Allowance<T>>::remove(key);
+		} else {
+			<Allowance<T>>::insert(key, allowed - value);
 		}
-		let new_value = Self::amount_free(dest, tokentype) - value;
-		Self::set_free_token(dest,tokentype,new_value);
 		Ok(())
 	}
+}
 
-	fn vaild_tokentype(tokentype:&Vec<u8>) -> Result{
-		if TokenTypeAndPrecision::get(tokentype) == None{
-			return Err("invalid tokentype");
+/// Tokens as currencies, the reserved balance being the lock under `RESERVE_LOCK_ID`. Like any
+/// lock it overlaps the other locks on the balance.
+impl<T:Trait> support::traits::MultiCurrency<T::AccountId> for Module<T>{
+	type CurrencyId = TokenId;
+	type Balance = u64;
+
+	fn can_hold(token:TokenId, who:&T::AccountId) -> bool{
+		Self::ensure_can_hold(token, who).is_ok()
+	}
+
+	fn currency_exists(token:TokenId) -> bool{
+		<TokenRegistry<T>>::exists(token)
+	}
+
+	fn total_issuance(token:TokenId) -> u64{
+		Self::total_issuance(token)
+	}
+
+	fn free_balance(token:TokenId, who:&T::AccountId) -> u64{
+		Self::amount_free(who, token)
+	}
+
+	fn reserved_balance(token:TokenId, who:&T::AccountId) -> u64{
+		Self::lock_amount(RESERVE_LOCK_ID, who, token)
+	}
+
+	fn transfer(token:TokenId, source:&T::AccountId, dest:&T::AccountId, value:u64) -> Result{
+		<Self as Token<_>>::transfer(source, dest, token, value)
+	}
+
+	fn reserve(token:TokenId, who:&T::AccountId, value:u64) -> Result{
+		ensure!(value <= Self::amount_free(who, token), "Insufficient available balance");
+		let reserved = Self::lock_amount(RESERVE_LOCK_ID, who, token);
+		Self::set_lock(RESERVE_LOCK_ID, who, token, reserved + value)
+	}
+
+	fn unreserve(token:TokenId, who:&T::AccountId, value:u64) -> u64{
+		let reserved = Self::lock_amount(RESERVE_LOCK_ID, who, token);
+		let actual = rstd::cmp::min(reserved, value);
+		let _ = Self::set_lock(RESERVE_LOCK_ID, who, token, reserved - actual);
+		value - actual
+	}
+
+	fn repatriate_reserved(
+		token:TokenId,
+		slashed:&T::AccountId,
+		beneficiary:&T::AccountId,
+		value:u64,
+	) -> rstd::result::Result<u64, &'static str>{
+		let actual = rstd::cmp::min(Self::backed_reserve(token, slashed), value);
+		if actual != 0 {
+			Self::transfer_locked(RESERVE_LOCK_ID, slashed, beneficiary, token, actual)?;
 		}
-		Ok(())
+		Ok(value - actual)
+	}
+
+	fn slash(token:TokenId, who:&T::AccountId, value:u64) -> u64{
+		let free = Self::amount_free(who, token);
+		let actual = rstd::cmp::min(free, value);
+		if Self::destroy(token, actual).is_err() {
+			return value;
+		}
+		Self::set_free_token(who, token, free - actual);
+		let remaining = <Self as support::traits::MultiCurrency<_>>::slash_reserved(token, who, value - actual);
+		Self::reap_dust(token, who);
+		remaining
+	}
+
+	fn slash_reserved(token:TokenId, who:&T::AccountId, value:u64) -> u64{
+		let actual = rstd::cmp::min(Self::backed_reserve(token, who), value);
+		if actual != 0 && actual <= Self::total_issuance(token)
+			&& Self::debit_locked(RESERVE_LOCK_ID, who, token, actual).is_ok() {
+			// cannot fail, the issuance was checked above
+			let _ = Self::destroy(token, actual);
+			Self::reap_dust(token, who);
+			return value - actual;
+		}
+		value
 	}
 }
 
@@ -231,6 +621,23 @@ pub trait Trait: system::Trait{
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 
 	type Token: Token<<Self as system::Trait>::AccountId>;
+
+	/// The currency the token creation deposit is reserved in.
+	type Currency: ReservableCurrency<Self::AccountId>;
+
+	/// Reserved from a signed account creating a token, for as long as the token exists.
+	type TokenDeposit: Get<BalanceOf<Self>>;
+
+	/// Told when an account may no longer move a token.
+	type OnAccountFrozen: OnAccountFrozen<Self::AccountId>;
+
+	/// Reserved from whoever creates a holder of a token, the sender of a transfer or the issuer
+	/// of a mint, until the holding is removed. Genesis, gateway mints and DEX settlement create
+	/// holdings without it.
+	type HolderDeposit: Get<BalanceOf<Self>>;
+
+	/// Handler for the dust of holdings removed below a token's minimum balance.
+	type DustRemoval: OnTokenDust;
 }
 
 decl_module! {
@@ -238,27 +645,379 @@ decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event() = default;
 
-        pub fn transfer_free_token(origin, dest:T::AccountId, tokentype:Vec<u8>, value:u64) -> Result{
+		fn on_initialize(_n: T::BlockNumber) {
+			Self::on_runtime_upgrade();
+		}
+
+        pub fn transfer_free_token(origin, dest:T::AccountId, #[compact] token:TokenId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::transfer(&sender,&dest,token,value)?;
+            Ok(())
+        }
+
+        /// Register a new token with the sender as its issuer. It gets the next free `TokenId`.
+        /// `TokenDeposit` is reserved from the sender.
+        pub fn create_token(origin, symbol:Vec<u8>, name:Vec<u8>, decimals:u8, max_supply:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::check_metadata(&symbol, &name)?;
+            T::Currency::reserve(&sender, T::TokenDeposit::get())
+                .map_err(|_| "not enough funds for the token deposit")?;
+            let token = Self::register_token(TokenInfo{ symbol, name, decimals, issuer: sender.clone(), max_supply });
+            Self::deposit_event(RawEvent::TokenCreated(token, sender));
+            Ok(())
+        }
+
+        /// Register a new token for `issuer` without a deposit.
+        pub fn force_create_token(origin, issuer:T::AccountId, symbol:Vec<u8>, name:Vec<u8>, decimals:u8,
+                                  max_supply:u64) -> Result{
+            ensure_root(origin)?;
+            Self::check_metadata(&symbol, &name)?;
+            let token = Self::register_token(TokenInfo{ symbol, name, decimals, issuer: issuer.clone(), max_supply });
+            Self::deposit_event(RawEvent::TokenCreated(token, issuer));
+            Ok(())
+        }
+
+        /// Issue `value` new tokens to `dest`. Only the issuer of the token may call this.
+        pub fn mint_token(origin, #[compact] token:TokenId, dest:T::AccountId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            Self::mint_paying(&dest, token, value, Some(&sender))
+        }
+
+        /// Destroy `value` free tokens of `who`. Only the issuer of the token may call this.
+        pub fn burn_token(origin, #[compact] token:TokenId, who:T::AccountId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            Self::burn(&who, token, value)
+        }
+
+        /// Make `new_issuer` the issuer of `token`. Only the current issuer may call this.
+        pub fn transfer_issuer(origin, #[compact] token:TokenId, new_issuer:T::AccountId) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            <TokenRegistry<T>>::mutate(token, |info| if let Some(info) = info {
+                info.issuer = new_issuer.clone();
+            });
+            Self::deposit_event(RawEvent::IssuerChanged(token, new_issuer));
+            Ok(())
+        }
+
+        /// Let `spender` move up to `value` of the sender's free `token`, replacing any earlier allowance.
+        pub fn approve(origin, spender:T::AccountId, #[compact] token:TokenId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::set_allowance(&sender, &spender, token, value)
+        }
+
+        /// Raise the allowance of `spender` over the sender's `token` by `value`.
+        pub fn increase_allowance(origin, spender:T::AccountId, #[compact] token:TokenId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            let allowed = Self::amount_allowed(&sender, &spender, token)
+                .checked_add(value).ok_or("allowance overflow")?;
+            Self::set_allowance(&sender, &spender, token, allowed)
+        }
+
+        /// Lower the allowance of `spender` over the sender's `token` by `value`.
+        pub fn decrease_allowance(origin, spender:T::AccountId, #[compact] token:TokenId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            let allowed = Self::amount_allowed(&sender, &spender, token)
+                .checked_sub(value).ok_or("allowance would drop below zero")?;
+            Self::set_allowance(&sender, &spender, token, allowed)
+        }
+
+        /// Move `value` of `owner`'s free `token` to `dest`, spending the sender's allowance.
+        pub fn transfer_from(origin, owner:T::AccountId, dest:T::AccountId, #[compact] token:TokenId, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::transfer_with_allowance(&sender, &owner, &dest, token, value)
+        }
+
+        /// Register the token wrapping the native currency 1:1. Its issuer is the native pot,
+        /// so only `deposit_native` and `withdraw_native` change its supply.
+        pub fn create_wrapped_native(origin, symbol:Vec<u8>, name:Vec<u8>, decimals:u8) -> Result{
+            ensure_root(origin)?;
+            ensure!(Self::wrapped_native().is_none(), "wrapped native token already exists");
+            Self::check_metadata(&symbol, &name)?;
+            let pot = Self::native_pot();
+            let token = Self::register_token(TokenInfo{
+                symbol, name, decimals, issuer: pot.clone(), max_supply: u64::max_value(),
+            });
+            WrappedNative::put(token);
+            Self::deposit_event(RawEvent::TokenCreated(token, pot));
+            Ok(())
+        }
+
+        /// Move `value` of the sender's native currency to the native pot, where it is reserved,
+        /// and mint as many wrapped native tokens to the sender.
+        pub fn deposit_native(origin, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            let token = Self::wrapped_native().ok_or("no wrapped native token")?;
+            let amount: BalanceOf<T> = value.saturated_into();
+            let pot = Self::native_pot();
+            Self::total_issuance(token).checked_add(value).ok_or("token supply overflow")?;
+            // a pot below the existential deposit would be reaped with the funds
+            ensure!(!T::Currency::total_balance(&pot).is_zero() || amount >= T::Currency::minimum_balance(),
+                "deposit below the existential deposit");
+            // the holder deposit is reserved after the withdrawal, check it can be before either
+            let total = amount.checked_add(&Self::holder_deposit_due(token, &sender, value))
+                .ok_or("not enough funds for the holder deposit")?;
+            let left = T::Currency::free_balance(&sender).checked_sub(&total)
+                .ok_or("not enough funds for the holder deposit")?;
+            T::Currency::ensure_can_withdraw(&sender, total, WithdrawReason::Reserve, left)?;
+
+            let imbalance = T::Currency::withdraw(&sender, amount, WithdrawReason::Transfer,
+                ExistenceRequirement::KeepAlive)?;
+            T::Currency::resolve_creating(&pot, imbalance);
+            T::Currency::reserve(&pot, amount)?;
+            Self::mint_paying(&sender, token, value, Some(&sender))
+        }
+
+        /// Burn `value` of the sender's wrapped native tokens and pay as much native currency out
+        /// of the native pot.
+        pub fn withdraw_native(origin, value:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            let token = Self::wrapped_native().ok_or("no wrapped native token")?;
+            ensure!(value <= Self::amount_free(&sender, token), "Insufficient available balance");
+            let issuance = Self::total_issuance(token).checked_sub(value).ok_or("token supply underflow")?;
+            let amount: BalanceOf<T> = value.saturated_into();
+            let pot = Self::native_pot();
+            let backing = T::Currency::reserved_balance(&pot);
+            ensure!(amount <= backing, "not enough native backing");
+            let rest = backing - amount;
+            ensure!(rest.is_zero() || rest >= T::Currency::minimum_balance(),
+                "withdrawal would leave the native pot below the existential deposit");
+            ensure!(!T::Currency::total_balance(&sender).is_zero() || amount >= T::Currency::minimum_balance(),
+                "withdrawal below the existential deposit");
+
+            Self::set_free_token(&sender, token, Self::amount_free(&sender, token) - value);
+            TotalIssuance::insert(token, issuance);
+            let (imbalance, _) = T::Currency::slash_reserved(&pot, amount);
+            T::Currency::resolve_creating(&sender, imbalance);
+            Self::deposit_event(RawEvent::Burned(token, sender.clone(), value));
+            Self::reap_dust(token, &sender);
+            Ok(())
+        }
+
+        /// Transfer `schedule.locked` of the sender's free `token` to `dest`, where it stays
+        /// locked under `VESTING_LOCK_ID` until released by `schedule`. Schedules add up with
+        /// those `dest` already has running, up to `MAX_VESTING_SCHEDULES`, and each must move
+        /// at least the token's minimum balance.
+        pub fn vested_transfer(origin, dest:T::AccountId, #[compact] token:TokenId,
+                               schedule:VestingScheduleT<T>) -> Result{
+            let sender = ensure_signed(origin)?;
+            ensure!(schedule.locked != 0 && schedule.per_block != 0, "invalid vesting schedule");
+            ensure!(schedule.locked >= Self::min_balance(token), "vested transfer below the minimum balance");
+            let now = <system::Module<T>>::block_number();
+            let mut schedules = Self::vesting((token, dest.clone()));
+            schedules.retain(|running| running.locked_at(now) != 0);
+            ensure!(schedules.len() < MAX_VESTING_SCHEDULES, "too many vesting schedules");
+            Self::transfer(&sender, &dest, token, schedule.locked)?;
+            schedules.push(schedule);
+            Self::update_vesting(token, &dest, schedules)
+        }
+
+        /// Unlock what vested so far of the sender's `token`.
+        pub fn vest(origin, #[compact] token:TokenId) -> Result{
+            let sender = ensure_signed(origin)?;
+            let schedules = Self::vesting((token, sender.clone()));
+            ensure!(!schedules.is_empty(), "no vesting schedule");
+            Self::update_vesting(token, &sender, schedules)
+        }
+
+        /// Stop all movements of `who`'s `token` until thawed. Only the issuer may call this.
+        pub fn freeze_account(origin, #[compact] token:TokenId, who:T::AccountId) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            ensure!(!Self::is_frozen((token, who.clone())), "account is frozen");
+            <Frozen<T>>::insert((token, who.clone()), true);
+            T::OnAccountFrozen::on_account_frozen(token, &who);
+            Self::deposit_event(RawEvent::AccountFrozen(token, who));
+            Ok(())
+        }
+
+        /// Let `who` move `token` again. Only the issuer may call this.
+        pub fn thaw_account(origin, #[compact] token:TokenId, who:T::AccountId) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            ensure!(Self::is_frozen((token, who.clone())), "account is not frozen");
+            <Frozen<T>>::remove((token, who.clone()));
+            Self::deposit_event(RawEvent::AccountThawed(token, who));
+            Ok(())
+        }
+
+        /// Allow only allowlisted accounts to hold `token`, or lift the restriction. Only the
+        /// issuer may call this, and only before any of the token is issued.
+        pub fn set_allowlist_only(origin, #[compact] token:TokenId, enabled:bool) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            if enabled {
+                ensure!(Self::total_issuance(token) == 0, "allowlist mode must be set before issuance");
+                AllowlistOnly::insert(token, true);
+            } else {
+                AllowlistOnly::remove(token);
+            }
+            Self::deposit_event(RawEvent::AllowlistModeSet(token, enabled));
+            Ok(())
+        }
+
+        /// Add `who` to the allowlist of `token`, or remove it. Only the issuer may call this.
+        pub fn set_allowlisted(origin, #[compact] token:TokenId, who:T::AccountId, allowed:bool) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            if allowed {
+                <Allowlisted<T>>::insert((token, who.clone()), true);
+            } else {
+                <Allowlisted<T>>::remove((token, who.clone()));
+                if Self::allowlist_only(token) {
+                    T::OnAccountFrozen::on_account_frozen(token, &who);
+                }
+            }
+            Self::deposit_event(RawEvent::AllowlistChanged(token, who, allowed));
+            Ok(())
+        }
+
+        /// Transfer the sender's free `token` to each `(dest, value)` of `legs`, all or none.
+        #[weight = TransferBatchWeight(10_000)]
+        pub fn transfer_batch(origin, #[compact] token:TokenId, legs:Vec<(T::AccountId, u64)>) -> Result{
+            let sender = ensure_signed(origin)?;
+            let legs = legs.into_iter().map(|(dest, value)| (token, dest, value)).collect::<Vec<_>>();
+            Self::transfer_legs(&sender, &legs)
+        }
+
+        /// Transfer the sender's free tokens as each `(token, dest, value)` of `legs` says, all or none.
+        #[weight = TransferBatchWeight(10_000)]
+        pub fn transfer_batch_multi(origin, legs:Vec<(TokenId, T::AccountId, u64)>) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::transfer_legs(&sender, &legs)
+        }
+
+        /// Record the balances of `token` as of now, for `distribute`. Only the issuer may call this.
+        pub fn snapshot(origin, #[compact] token:TokenId) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            let id = Self::current_snapshot(token).checked_add(1).ok_or("too many snapshots")?;
+            <Snapshots<T>>::insert((token, id), SnapshotInfo{
+                block: <system::Module<T>>::block_number(),
+                total_issuance: Self::total_issuance(token),
+            });
+            CurrentSnapshot::insert(token, id);
+            Self::deposit_event(RawEvent::SnapshotTaken(token, id));
+            Ok(())
+        }
+
+        /// Pay `total` of the sender's free `reward_token` into a distribution among the holders
+        /// of `token` at `snapshot`, who each `claim` their share.
+        pub fn distribute(origin, #[compact] token:TokenId, #[compact] snapshot:SnapshotId,
+                          #[compact] reward_token:TokenId, total:u64) -> Result{
             let sender = ensure_signed(origin)?;
-            //T::Token::transfer(&sender,&dest,&tokentype,value)?;
-            Self::transfer(&sender,&dest,&tokentype,value)?;
-            //<Self as Token<_>>::transfer(&sender,&dest,&tokentype,value)?;
+            let info = Self::snapshot_info((token, snapshot)).ok_or("unknown snapshot")?;
+            ensure!(total != 0, "nothing to distribute");
+            ensure!(info.total_issuance != 0, "no holders at the snapshot");
+            let id = Self::next_distribution_id();
+            let next = id.checked_add(1).ok_or("too many distributions")?;
+
+            Self::transfer(&sender, &Self::distribution_pot(), reward_token, total)?;
+            Distributions::insert(id, Distribution{
+                token, snapshot, reward_token, total, supply: info.total_issuance, claimed: 0,
+            });
+            NextDistributionId::put(next);
+            Self::deposit_event(RawEvent::DistributionMade(id, token, snapshot, reward_token, total));
             Ok(())
         }
 
-        pub fn add_new_tokentype(origin, tokentypt:Vec<u8>, precision:u64){
+        /// Collect the sender's share of `distribution`, in proportion to its balance at the snapshot.
+        pub fn claim(origin, #[compact] distribution:DistributionId) -> Result{
             let sender = ensure_signed(origin)?;
-            TokenTypeAndPrecision::insert(tokentypt,precision);
+            let mut info = Self::distribution(distribution).ok_or("unknown distribution")?;
+            ensure!(!Self::has_claimed((distribution, sender.clone())), "already claimed");
+            let balance = Self::balance_at(info.token, &sender, info.snapshot);
+            let share = (info.total as u128 * balance as u128 / info.supply as u128) as u64;
+            ensure!(share != 0, "nothing to claim");
+
+            // the claimant pays for holding a new token, the pot holds no native currency
+            Self::transfer_paying(&Self::distribution_pot(), &sender, info.reward_token, share, &sender)?;
+            info.claimed += share;
+            Distributions::insert(distribution, info);
+            <Claimed<T>>::insert((distribution, sender.clone()), true);
+            Self::deposit_event(RawEvent::DistributionClaimed(distribution, sender, share));
+            Ok(())
+        }
+
+        /// Remove holdings of `token` below `min_balance` as dust once they change, unless locked.
+        /// Only the issuer may call this.
+        pub fn set_min_balance(origin, #[compact] token:TokenId, min_balance:u64) -> Result{
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(token, &sender)?;
+            MinBalance::insert(token, min_balance);
+            Self::deposit_event(RawEvent::MinBalanceSet(token, min_balance));
+            Ok(())
+        }
+
+        /// List those of `accounts` holding `token` among its holders. Holders from before the
+        /// holder index cannot be enumerated on chain, so root points this at them in batches.
+        #[weight = PerAccountWeight(10_000)]
+        pub fn index_holders(origin, #[compact] token:TokenId, accounts:Vec<T::AccountId>) -> Result{
+            ensure_root(origin)?;
+            ensure!(accounts.len() <= MAX_MIGRATION_ACCOUNTS, "too many accounts");
+            Self::vaild_tokentype(token)?;
+            for who in accounts.iter() {
+                if !Self::is_new_holder(token, who) {
+                    Self::index_holder(token, who);
+                }
+            }
+            Ok(())
+        }
+
+        /// Move the storage version 0 balances of `tokentype` held by `accounts` to its `TokenId`.
+        /// Old balances cannot be enumerated on chain, so root points this at holders in batches.
+        /// Until then an account may not hold the token, see `ensure_can_hold`.
+        #[weight = PerAccountWeight(50_000)]
+        pub fn migrate_legacy_balances(origin, tokentype:Vec<u8>, accounts:Vec<T::AccountId>) -> Result{
+            ensure_root(origin)?;
+            ensure!(accounts.len() <= MAX_MIGRATION_ACCOUNTS, "too many accounts");
+            let token = Self::legacy_token(&tokentype).ok_or("invalid tokentype")?;
+            for who in accounts.iter() {
+                Self::migrate_legacy_balance(&tokentype, token, who);
+            }
+            Ok(())
         }
 	}
 }
 
 decl_event!(
 	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-		/// A sudo just took place.
-		Sudid(bool),
-		/// The sudoer just switched identity; the old key is supplied.
-		KeyChanged(AccountId),
+		/// Free tokens moved from one account to another.
+		Transferred(TokenId, AccountId, AccountId, u64),
+		/// A token was registered with the given issuer.
+		TokenCreated(TokenId, AccountId),
+		/// New tokens were issued to an account.
+		Minted(TokenId, AccountId, u64),
+		/// Free tokens of an account were destroyed.
+		Burned(TokenId, AccountId, u64),
+		/// A lock on an account's tokens was set to the given amount.
+		Locked(TokenId, AccountId, LockIdentifier, u64),
+		/// A lock on an account's tokens was removed.
+		Unlocked(TokenId, AccountId, LockIdentifier),
+		/// The issuer role of a token moved to a new account.
+		IssuerChanged(TokenId, AccountId),
+		/// An owner set the allowance of a spender: owner, spender, new allowance.
+		Approval(TokenId, AccountId, AccountId, u64),
+		/// The issuer froze an account's tokens.
+		AccountFrozen(TokenId, AccountId),
+		/// The issuer thawed an account's tokens.
+		AccountThawed(TokenId, AccountId),
+		/// Allowlist mode of a token was switched on or off.
+		AllowlistModeSet(TokenId, bool),
+		/// An account was added to or removed from the allowlist of a token.
+		AllowlistChanged(TokenId, AccountId, bool),
+		/// The balances of a token were recorded under a new snapshot.
+		SnapshotTaken(TokenId, SnapshotId),
+		/// A distribution was made: id, token, snapshot, reward token, total.
+		DistributionMade(DistributionId, TokenId, SnapshotId, TokenId, u64),
+		/// A holder claimed its share of a distribution.
+		DistributionClaimed(DistributionId, AccountId, u64),
+		/// The minimum balance of a token was set.
+		MinBalanceSet(TokenId, u64),
+		/// A holding below the minimum balance was removed, its dust burned.
+		DustRemoved(TokenId, AccountId, u64),
 	}
 );
 
@@ -267,78 +1026,544 @@ decl_storage! {
 		/// The `AccountId` of the sudo key.
 		Key get(key) config(): T::AccountId;
 
-        TokenTypeAndPrecision get(token_type_and_precision): map Vec<u8> => Option<u64>;
+		/// Metadata of every token, by id.
+		pub TokenRegistry get(token_info): map TokenId => Option<TokenInfoT<T>>;
+		/// The id the next created token gets.
+		pub NextTokenId get(next_token_id): TokenId;
+		/// Amount of each token in existence, free and locked.
+		pub TotalIssuance get(total_issuance): map TokenId => u64;
+
+		/// Holdings of each token below this are removed as dust, unless locked.
+		pub MinBalance get(min_balance): map TokenId => u64;
+		/// Who paid the `HolderDeposit` of a holding and how much, by (token, holder).
+		pub HolderDeposits get(holder_deposit): map (TokenId,T::AccountId) => Option<(T::AccountId, BalanceOf<T>)>;
+		/// Number of accounts holding each token.
+		pub HolderCount get(holder_count): map TokenId => u32;
+		/// Holders of each token in no particular order, by (token, position below `HolderCount`).
+		pub Holders get(holder_at): map (TokenId,u32) => Option<T::AccountId>;
+		/// Position of each holder in `Holders`, by (token, holder).
+		HolderIndex get(holder_index): map (TokenId,T::AccountId) => Option<u32>;
+
+		/// The part of each balance no lock holds.
+		FreeToken get(free_token) : map (TokenId,T::AccountId) => u64;
+		/// The part of each balance held by its largest lock.
+		LockedToken get(locked_token) : map (TokenId,T::AccountId) => u64;
+		/// Named locks on each balance, absent for balances without locks.
+		pub Locks get(locks): map (TokenId,T::AccountId) => Vec<TokenLock>;
+		/// What a spender may still move of an owner's free tokens, by (token, owner, spender).
+		pub Allowance get(allowance): map (TokenId,T::AccountId,T::AccountId) => u64;
+		/// Accounts whose tokens the issuer froze, by (token, account).
+		pub Frozen get(is_frozen): map (TokenId,T::AccountId) => bool;
+		/// Tokens only allowlisted accounts may hold.
+		pub AllowlistOnly get(allowlist_only): map TokenId => bool;
+		/// Accounts allowed to hold a token in allowlist mode, by (token, account).
+		pub Allowlisted get(is_allowlisted): map (TokenId,T::AccountId) => bool;
+		/// The latest snapshot of each token, 0 before the first.
+		pub CurrentSnapshot get(current_snapshot): map TokenId => SnapshotId;
+		/// When each snapshot was taken and the issuance then, by (token, snapshot).
+		pub Snapshots get(snapshot_info): map (TokenId,SnapshotId) => Option<SnapshotInfo<T::BlockNumber>>;
+		/// Balances, free and locked, as of the snapshots taken before they last changed. An entry
+		/// `(snapshot, balance)` is written on the first change after `snapshot`, ordered by snapshot.
+		pub BalanceCheckpoints get(balance_checkpoints): map (TokenId,T::AccountId) => Vec<(SnapshotId, u64)>;
 
-		FreeToken get(free_token) : map (Vec<u8>,T::AccountId) => u64;
-		LockedToken get(locked_token) : map (Vec<u8>,T::AccountId) => u64;
+		/// The id the next distribution gets.
+		pub NextDistributionId get(next_distribution_id): DistributionId;
+		/// Distributions by id.
+		pub Distributions get(distribution): map DistributionId => Option<Distribution>;
+		/// Whether an account claimed its share of a distribution, by (distribution, account).
+		pub Claimed get(has_claimed): map (DistributionId,T::AccountId) => bool;
+
+		/// Release schedule of the tokens each account holds under `VESTING_LOCK_ID`.
+		pub Vesting get(vesting): map (TokenId,T::AccountId) => Vec<VestingScheduleT<T>>;
+
+		/// Token types of storage version 0 and their precision, removed once registered.
+		pub TokenTypeAndPrecision get(token_type_and_precision): map Vec<u8> => Option<u64>;
+		/// The id each storage version 0 token type was registered under.
+		pub LegacyTokenId get(legacy_token_id): map Vec<u8> => Option<TokenId>;
+		/// The storage version 0 token type each token registered for one had.
+		pub LegacyTokenType get(legacy_token_type): map TokenId => Option<Vec<u8>>;
+
+		/// The token wrapping the native currency, its supply always equal to the native pot's
+		/// reserved balance.
+		pub WrappedNative get(wrapped_native): Option<TokenId>;
+
+		/// Version of the storage layout, behind `STORAGE_VERSION` until migrations finish.
+		pub StorageVersion get(storage_version) build(|_| STORAGE_VERSION): u32;
 	}
 	add_extra_genesis {
-		config(bt):  u64;
+		// tokens to register, getting ids from 0 in this order
+		config(tokens): Vec<TokenInfoT<T>>;
+		// initial free balances as `(token, account, amount)`
+		config(balances): Vec<(TokenId, T::AccountId, u64)>;
         build(|config: &GenesisConfig<T>|  {
-         <Module<T>>::init_token();
+            for info in config.tokens.iter() {
+                <Module<T>>::register_token(info.clone());
+            }
+            for (token, who, amount) in config.balances.iter() {
+                // genesis holdings are free of the `HolderDeposit`
+                <Module<T>>::issue(who, *token, *amount, None)
+                    .expect("genesis balance of an unknown token or above its max supply");
+            }
         });
 	}
 }
 
 impl<T: Trait> Module<T>{
 
-    pub fn init_token(){
-		TokenTypeAndPrecision::insert(vec![1u8,2],1000);
-		TokenTypeAndPrecision::insert(vec![3u8,4],1000);
+	/// Migrate token storage from the recorded version up to `STORAGE_VERSION`.
+	/// Each layout change adds an arm here; an arm returning `false` resumes next block.
+	pub fn on_runtime_upgrade() {
+		let mut version = Self::storage_version();
+		while version < STORAGE_VERSION {
+			let done = match version {
+				0 => Self::migrate_genesis_tokens(),
+				_ => true,
+			};
+			if !done { break; }
+			version += 1;
+			StorageVersion::put(version);
+		}
+	}
+
+	/// Version 0 -> 1: register the token types created at genesis. Other token types are
+	/// registered the first time `legacy_token` is asked for them.
+	fn migrate_genesis_tokens() -> bool {
+		for tokentype in LEGACY_GENESIS_TOKENS.iter() {
+			let _ = Self::legacy_token(&tokentype.to_vec());
+		}
+		true
+	}
+
+	/// The `TokenId` a storage version 0 token type is known by, registering it if needed.
+	pub fn legacy_token(tokentype:&Vec<u8>) -> Option<TokenId> {
+		if let Some(token) = Self::legacy_token_id(tokentype) {
+			return Some(token);
+		}
+		let precision = TokenTypeAndPrecision::take(tokentype)?;
+		let mut decimals = 0u8;
+		let mut rest = precision;
+		while rest >= 10 {
+			rest /= 10;
+			decimals += 1;
+		}
+		let token = Self::register_token(TokenInfo{
+			symbol: tokentype.clone(),
+			name: tokentype.clone(),
+			decimals,
+			issuer: Self::key(),
+			max_supply: u64::max_value(),
+		});
+		LegacyTokenId::insert(tokentype, token);
+		LegacyTokenType::insert(token, tokentype);
+		Some(token)
+	}
+
+	fn migrate_legacy_balance(tokentype:&Vec<u8>, token:TokenId, who:&T::AccountId) {
+		let free: u64 = hashed::take_or_default(&runtime_io::blake2_256,
+			&Self::legacy_balance_key(b"Token FreeToken", tokentype, who));
+		let locked: u64 = hashed::take_or_default(&runtime_io::blake2_256,
+			&Self::legacy_balance_key(b"Token LockedToken", tokentype, who));
+		Self::checkpoint(token, who);
+		if free != 0 {
+			<FreeToken<T>>::mutate((token, who.clone()), |balance| *balance += free);
+		}
+		if locked != 0 {
+			<LockedToken<T>>::mutate((token, who.clone()), |balance| *balance += locked);
+		}
+		TotalIssuance::mutate(token, |issuance| *issuance += free + locked);
+		if free != 0 || locked != 0 {
+			Self::index_holder(token, who);
+		}
+	}
+
+	/// Whether `who` still has storage version 0 balances of `token` to migrate.
+	pub fn has_legacy_balance(token:TokenId, who:&T::AccountId) -> bool {
+		match Self::legacy_token_type(token) {
+			Some(tokentype) => [&b"Token FreeToken"[..], &b"Token LockedToken"[..]].iter().any(|prefix|
+				hashed::exists(&runtime_io::blake2_256, &Self::legacy_balance_key(prefix, &tokentype, who))),
+			None => false,
+		}
+	}
+
+	/// Unhashed key of a balance entry as stored by version 0, keyed by token type bytes.
+	pub fn legacy_balance_key(prefix:&[u8], tokentype:&Vec<u8>, who:&T::AccountId) -> Vec<u8> {
+		let mut key = prefix.to_vec();
+		(tokentype, who).encode_to(&mut key);
+		key
+	}
+
+	fn check_metadata(symbol:&Vec<u8>, name:&Vec<u8>) -> Result {
+		ensure!(!symbol.is_empty() && symbol.len() <= MAX_SYMBOL_LEN, "invalid token symbol");
+		ensure!(name.len() <= MAX_NAME_LEN, "token name too long");
+		Ok(())
+	}
+
+	/// Keep the balance of `who` as of the latest snapshot of `token`, unless it was kept
+	/// already. Called before each change of the balance.
+	fn checkpoint(token:TokenId, who:&T::AccountId) {
+		let current = Self::current_snapshot(token);
+		if current == 0 { return; }
+		let key = (token, who.clone());
+		let mut checkpoints = <BalanceCheckpoints<T>>::get(&key);
+		if checkpoints.last().map_or(false, |(snapshot, _)| *snapshot == current) { return; }
+		let balance = <FreeToken<T>>::get(&key) + <LockedToken<T>>::get(&key);
+		checkpoints.push((current, balance));
+		<BalanceCheckpoints<T>>::insert(&key, checkpoints);
 	}
 
-	pub fn depositing_token(dest:&T::AccountId, tokentype:Vec<u8>, value:u64) -> Result{
-		Self::mint(dest,&tokentype,value)?;
+	/// The balance, free and locked, of `who`'s `token` when `snapshot` was taken.
+	pub fn balance_at(token:TokenId, who:&T::AccountId, snapshot:SnapshotId) -> u64 {
+		let checkpoints = Self::balance_checkpoints((token, who.clone()));
+		// the first checkpoint at or after the snapshot holds the balance from then on
+		let i = match checkpoints.binary_search_by_key(&snapshot, |(id, _)| *id) {
+			Ok(i) => i,
+			Err(i) => i,
+		};
+		match checkpoints.get(i) {
+			Some((_, balance)) => *balance,
+			None => Self::amount_free(who, token) + Self::amount_lock(who, token),
+		}
+	}
+
+	/// The account holding the tokens of distributions until their holders claim them.
+	pub fn distribution_pot() -> T::AccountId {
+		DISTRIBUTION_POT_ID.into_account()
+	}
+
+	/// Whether `who` may receive and move `token`: not frozen, allowlisted if the token
+	/// requires it, and done with migrating balances of storage version 0, which would not be
+	/// counted yet.
+	pub fn ensure_can_hold(token:TokenId, who:&T::AccountId) -> Result {
+		ensure!(!Self::is_frozen((token, who.clone())), "account is frozen");
+		ensure!(!Self::has_legacy_balance(token, who), "account has balances still to migrate");
+		if Self::allowlist_only(token) {
+			ensure!(Self::is_allowlisted((token, who.clone())), "account is not allowlisted");
+		}
 		Ok(())
 	}
 
-	pub fn delete_token(dest:&T::AccountId, tokentype:Vec<u8>, value:u64) -> Result{
-        Self::burn(dest,&tokentype,value)?;
+	fn ensure_issuer(token:TokenId, who:&T::AccountId) -> Result {
+		let info = Self::token_info(token).ok_or("invalid tokentype")?;
+		ensure!(info.issuer == *who, "not the token issuer");
+		Ok(())
+	}
+
+	/// Add a token to the registry and return the id it got.
+	pub fn register_token(info:TokenInfoT<T>) -> TokenId {
+		let token = Self::next_token_id();
+		<TokenRegistry<T>>::insert(token, info);
+		NextTokenId::put(token + 1);
+		token
+	}
+
+	/// Issue `value` of `token` to `dest`, `payer` paying the `HolderDeposit` if it creates a
+	/// holding.
+	fn mint_paying(dest:&T::AccountId, token:TokenId, value:u64, payer:Option<&T::AccountId>) -> Result {
+		Self::ensure_can_hold(token, dest)?;
+		Self::issue(dest, token, value, payer)?;
+		Self::deposit_event(RawEvent::Minted(token, dest.clone(), value));
 		Ok(())
 	}
 
-	pub fn token_increase_or_decrease(who:&T::AccountId, tokentype:&Vec<u8>, value:u64,
+	/// Add `value` to the free balance and the issuance of `token`, within its max supply.
+	fn issue(dest:&T::AccountId, token:TokenId, value:u64, payer:Option<&T::AccountId>) -> Result {
+		let info = Self::token_info(token).ok_or("invalid tokentype")?;
+
+		let issuance = Self::total_issuance(token).checked_add(value).ok_or("token supply overflow")?;
+		ensure!(issuance <= info.max_supply, "mint exceeds the max supply of the token");
+		Self::credit(dest,token,value,payer)?;
+		TotalIssuance::insert(token,issuance);
+		Ok(())
+	}
+
+	/// Take `value` of `token` out of the issuance after it was removed from a balance. Native
+	/// backing of destroyed wrapped tokens is destroyed with them. Fails, changing nothing, if
+	/// `value` exceeds the issuance.
+	fn destroy(token:TokenId, value:u64) -> Result {
+		let issuance = Self::total_issuance(token).checked_sub(value).ok_or("token supply underflow")?;
+		TotalIssuance::insert(token, issuance);
+		if Self::wrapped_native() == Some(token) {
+			let _ = T::Currency::slash_reserved(&Self::native_pot(), value.saturated_into());
+		}
+		Ok(())
+	}
+
+	/// The account holding the native currency backing the wrapped native token.
+	pub fn native_pot() -> T::AccountId {
+		NATIVE_POT_ID.into_account()
+	}
+
+	/// Whether the supply of the wrapped native token equals the native currency backing it.
+	pub fn native_backing_holds() -> bool {
+		match Self::wrapped_native() {
+			Some(token) => {
+				let backing: BalanceOf<T> = Self::total_issuance(token).saturated_into();
+				backing == T::Currency::reserved_balance(&Self::native_pot())
+			}
+			None => T::Currency::reserved_balance(&Self::native_pot()).is_zero(),
+		}
+	}
+
+	/// Move `value` of free `token` from `source` to `dest`, `payer` paying the `HolderDeposit`
+	/// if `dest` holds none of the token yet.
+	fn transfer_paying(source:&T::AccountId, dest:&T::AccountId, token:TokenId, value:u64,
+					   payer:&T::AccountId) -> Result {
+		Self::move_free(source, dest, token, value, payer)?;
+		Self::reap_dust(token, source);
+		Ok(())
+	}
+
+	/// `transfer_paying` leaving `source` as it is if it drops to dust.
+	fn move_free(source:&T::AccountId, dest:&T::AccountId, token:TokenId, value:u64,
+				 payer:&T::AccountId) -> Result {
+		Self::vaild_tokentype(token)?;
+		Self::ensure_can_hold(token, source)?;
+		Self::ensure_can_hold(token, dest)?;
+
+		if value > Self::amount_free(source, token){
+			return Err("Insufficient available balance");
+		}
+		if source != dest && Self::is_new_holder(token, dest) {
+			ensure!(value >= Self::min_balance(token), "value too low to create a holder");
+		}
+		// credited first, the deposit it may take is the last thing that can fail
+		Self::credit(dest,token,value,Some(payer))?;
+		let source_new = Self::amount_free(source, token) - value;
+		Self::set_free_token(source,token,source_new);
+		Self::deposit_event(RawEvent::Transferred(token, source.clone(), dest.clone(), value));
+		Ok(())
+	}
+
+	/// Make each `(token, dest, value)` transfer from `sender`, or none of them.
+	fn transfer_legs(sender:&T::AccountId, legs:&[(TokenId, T::AccountId, u64)]) -> Result {
+		// a failed dispatch keeps its storage changes, so every leg is checked before any moves
+		let mut spent: Vec<(TokenId, u64)> = Vec::new();
+		let mut created: Vec<(TokenId, T::AccountId)> = Vec::new();
+		for (token, dest, value) in legs.iter() {
+			Self::vaild_tokentype(*token)?;
+			Self::ensure_can_hold(*token, sender)?;
+			Self::ensure_can_hold(*token, dest)?;
+			let total = match spent.iter_mut().find(|(spent_token, _)| spent_token == token) {
+				Some((_, total)) => {
+					*total = total.checked_add(*value).ok_or("Insufficient available balance")?;
+					*total
+				}
+				None => {
+					spent.push((*token, *value));
+					*value
+				}
+			};
+			ensure!(total <= Self::amount_free(sender, *token), "Insufficient available balance");
+
+			let leg_holder = (*token, dest.clone());
+			if dest != sender && Self::is_new_holder(*token, dest) && !created.contains(&leg_holder) {
+				ensure!(*value >= Self::min_balance(*token), "value too low to create a holder");
+				created.push(leg_holder);
+			}
+		}
+		let deposits = T::HolderDeposit::get().saturating_mul((created.len() as u64).saturated_into());
+		ensure!(T::Currency::can_reserve(sender, deposits), "not enough funds for the holder deposit");
+
+		for (token, dest, value) in legs.iter() {
+			Self::move_free(sender, dest, *token, *value, sender)?;
+		}
+		for (token, _) in spent.iter() {
+			Self::reap_dust(*token, sender);
+		}
+		Ok(())
+	}
+
+	/// The `HolderDeposit` crediting `who` with `value` of `token` costs, zero if it creates no
+	/// holding.
+	fn holder_deposit_due(token:TokenId, who:&T::AccountId, value:u64) -> BalanceOf<T> {
+		if value != 0 && Self::is_new_holder(token, who) {
+			T::HolderDeposit::get()
+		} else {
+			Zero::zero()
+		}
+	}
+
+	fn is_new_holder(token:TokenId, who:&T::AccountId) -> bool {
+		Self::amount_free(who, token) == 0 && Self::amount_lock(who, token) == 0
+			&& !<Locks<T>>::exists((token, who.clone()))
+	}
+
+	/// Remove the holding of `who` if it is empty or dust below the minimum balance, burning the
+	/// dust and returning the holder deposit. Holdings under a lock are kept.
+	fn reap_dust(token:TokenId, who:&T::AccountId) {
+		let key = (token, who.clone());
+		if <Locks<T>>::exists(&key) || Self::amount_lock(who, token) != 0 { return; }
+		let dust = Self::amount_free(who, token);
+		if dust != 0 && dust >= Self::min_balance(token) { return; }
+
+		if dust != 0 {
+			if Self::destroy(token, dust).is_err() { return; }
+			Self::set_free_token(who, token, 0);
+			T::DustRemoval::on_dust(token, dust);
+			Self::deposit_event(RawEvent::DustRemoved(token, who.clone(), dust));
+		}
+		if let Some((payer, deposit)) = <HolderDeposits<T>>::take(&key) {
+			T::Currency::unreserve(&payer, deposit);
+		}
+		Self::unindex_holder(token, who);
+	}
+
+	/// Add `who` to the holders of `token` unless it is listed already.
+	fn index_holder(token:TokenId, who:&T::AccountId) {
+		let key = (token, who.clone());
+		if <HolderIndex<T>>::exists(&key) { return; }
+		let count = Self::holder_count(token);
+		<Holders<T>>::insert((token, count), who.clone());
+		<HolderIndex<T>>::insert(key, count);
+		HolderCount::insert(token, count + 1);
+	}
+
+	/// Remove `who` from the holders of `token`, the last holder taking its position.
+	fn unindex_holder(token:TokenId, who:&T::AccountId) {
+		let index = match <HolderIndex<T>>::take((token, who.clone())) {
+			Some(index) => index,
+			None => return,
+		};
+		let last = Self::holder_count(token) - 1;
+		if index != last {
+			if let Some(moved) = <Holders<T>>::get((token, last)) {
+				<Holders<T>>::insert((token, index), moved.clone());
+				<HolderIndex<T>>::insert((token, moved), index);
+			}
+		}
+		<Holders<T>>::remove((token, last));
+		HolderCount::insert(token, last);
+	}
+
+	/// Up to `count` holders of `token` from position `start` on, at most `MAX_HOLDERS_PAGE`.
+	pub fn holders(token:TokenId, start:u32, count:u32) -> Vec<T::AccountId> {
+		let end = start.saturating_add(count.min(MAX_HOLDERS_PAGE)).min(Self::holder_count(token));
+		(start..end).filter_map(|index| Self::holder_at((token, index))).collect()
+	}
+
+	/// The free and locked balance of every token `who` holds, as `(token, free, locked)`.
+	pub fn balances_of(who:&T::AccountId) -> Vec<(TokenId, u64, u64)> {
+		(0..Self::next_token_id())
+			.map(|token| (token, Self::amount_free(who, token), Self::amount_lock(who, token)))
+			.filter(|(_, free, locked)| *free != 0 || *locked != 0)
+			.collect()
+	}
+
+	/// Add `value` to the balance of `who`, covering any lock it does not fully back yet.
+	///
+	/// Every holding is created here. It costs `payer` the `HolderDeposit`, reserved before
+	/// anything changes; callers passing no payer say why the holding is free.
+	fn credit(who:&T::AccountId, token:TokenId, value:u64, payer:Option<&T::AccountId>) -> Result {
+		let free = Self::amount_free(who, token).checked_add(value).ok_or("token balance overflow")?;
+		if let Some(payer) = payer {
+			let deposit = Self::holder_deposit_due(token, who, value);
+			if !deposit.is_zero() {
+				T::Currency::reserve(payer, deposit).map_err(|_| "not enough funds for the holder deposit")?;
+				<HolderDeposits<T>>::insert((token, who.clone()), (payer.clone(), deposit));
+			}
+		}
+		Self::set_free_token(who, token, free);
+		if free != 0 {
+			Self::index_holder(token, who);
+		}
+		if <Locks<T>>::exists((token, who.clone())) {
+			Self::update_locks(token, who, Self::locks_of(token, who));
+		}
+		Ok(())
+	}
+
+	/// The locks on `who`'s `token`. Balances locked before locks were named count as one
+	/// lock under `LEGACY_LOCK_ID`.
+	pub fn locks_of(token:TokenId, who:&T::AccountId) -> Vec<TokenLock> {
+		let key = (token, who.clone());
+		if <Locks<T>>::exists(&key) {
+			return <Locks<T>>::get(&key);
+		}
+		match Self::amount_lock(who, token) {
+			0 => Vec::new(),
+			amount => vec![TokenLock{ id: LEGACY_LOCK_ID, amount }],
+		}
+	}
+
+	/// Take `value` out of the balance of `who` and the lock `id` on it, other locks aside.
+	fn debit_locked(id:LockIdentifier, who:&T::AccountId, token:TokenId, value:u64) -> Result {
+		let mut locks = Self::locks_of(token, who);
+		let lock = locks.iter_mut().find(|lock| lock.id == id).ok_or("Insufficient locked balance")?;
+		ensure!(value <= lock.amount, "Insufficient locked balance");
+		let total = Self::amount_free(who, token) + Self::amount_lock(who, token);
+		ensure!(value <= total, "Insufficient available balance");
+
+		lock.amount -= value;
+		locks.retain(|lock| lock.amount != 0);
+		Self::set_free_token(who, token, total - value);
+		Self::set_lock_token(who, token, 0);
+		Self::update_locks(token, who, locks);
+		Ok(())
+	}
+
+	/// The part of `who`'s reserved `token` the balance actually covers.
+	fn backed_reserve(token:TokenId, who:&T::AccountId) -> u64 {
+		let total = Self::amount_free(who, token) + Self::amount_lock(who, token);
+		rstd::cmp::min(Self::lock_amount(RESERVE_LOCK_ID, who, token), total)
+	}
+
+	/// Store `locks` and move the balance of `who` so its largest lock and its reserve are out
+	/// of the free part.
+	fn update_locks(token:TokenId, who:&T::AccountId, locks:Vec<TokenLock>) {
+		let total = Self::amount_free(who, token) + Self::amount_lock(who, token);
+		let reserved = locks.iter().filter(|lock| lock.id == RESERVE_LOCK_ID).map(|lock| lock.amount).sum::<u64>();
+		let largest = locks.iter().filter(|lock| lock.id != RESERVE_LOCK_ID).map(|lock| lock.amount).max().unwrap_or(0);
+		let locked = rstd::cmp::min(reserved.saturating_add(largest), total);
+		Self::set_free_token(who, token, total - locked);
+		Self::set_lock_token(who, token, locked);
+		if locks.is_empty() {
+			<Locks<T>>::remove((token, who.clone()));
+		} else {
+			<Locks<T>>::insert((token, who.clone()), locks);
+		}
+	}
+
+	/// Lock what is still vesting of `who`'s `token` at this block under all of `schedules`,
+	/// dropping each once everything it holds is released.
+	fn update_vesting(token:TokenId, who:&T::AccountId, mut schedules:Vec<VestingScheduleT<T>>) -> Result {
+		let now = <system::Module<T>>::block_number();
+		schedules.retain(|schedule| schedule.locked_at(now) != 0);
+		let locked = schedules.iter().fold(0u64, |sum, schedule| sum.saturating_add(schedule.locked_at(now)));
+		if schedules.is_empty() {
+			Self::remove_lock(VESTING_LOCK_ID, who, token);
+			<Vesting<T>>::remove((token, who.clone()));
+		} else {
+			// tokens may have left under an overlapping lock, this one cannot hold more than is left
+			let total = Self::amount_free(who, token) + Self::amount_lock(who, token);
+			Self::set_lock(VESTING_LOCK_ID, who, token, rstd::cmp::min(locked, total))?;
+			<Vesting<T>>::insert((token, who.clone()), schedules);
+		}
+		Ok(())
+	}
+
+	pub fn depositing_token(dest:&T::AccountId, token:TokenId, value:u64) -> Result{
+		Self::mint(dest,token,value)?;
+		Ok(())
+	}
+
+	pub fn delete_token(dest:&T::AccountId, token:TokenId, value:u64) -> Result{
+        Self::burn(dest,token,value)?;
+		Ok(())
+	}
+
+	pub fn token_increase_or_decrease(who:&T::AccountId, token:TokenId, value:u64,
 									  changetype:TokenControl ,add:bool)
 	{
 		let mut new = 0u64;
 		match changetype {
 			TokenControl::Free => {
-				let old = Self::amount_free(who,&tokentype);
+				let old = Self::amount_free(who,token);
 				if add { new = old.checked_add(value).unwrap();} else { new = old.checked_sub(value).unwrap(); }
-				Self::set_free_token(who,&tokentype,new);},
+				Self::set_free_token(who,token,new);},
 			TokenControl::Lock => {
-				let old = Self::amount_lock(who,&tokentype);
+				let old = Self::amount_lock(who,token);
 				if add { new = old.checked_add(value).unwrap();} else { new = old.checked_sub(value).unwrap(); }
-				Self::set_lock_token(who,&tokentype,new);},
-		}
-	}
-
-
-
-	// after the transcation , exchange the token
-	pub fn exchange_token(seller:&T::AccountId, buyer:&T::AccountId, tokentype_share:Vec<u8>,
-						  tokentype_money:Vec<u8>, amount:u64, price:u64, lock_price:u64){
-/*
-		println!("seller={:?} buyer={:?} tokentype_share={:?} tokentype_money={:?} amount={:?} price={:?} lock_price={:?}",
-				 seller,buyer,
-				 tokentype_share,
-				 tokentype_money,amount,price,lock_price);
-*/
-		let mut extra_lock_to_free = 0u64;
-		if lock_price > price {
-			extra_lock_to_free = amount * (lock_price - price);
-			Self::unlock(buyer,&tokentype_money,extra_lock_to_free);
-			//println!("extra_lock_to_free = {:?}",extra_lock_to_free);
+				Self::set_lock_token(who,token,new);},
 		}
-
-		let money = amount * price;
-		//println!("test1 {:?} ", Self::amount_lock(buyer,&tokentype_money));
-		//money exchange
-		Self::token_increase_or_decrease(buyer,&tokentype_money,money,TokenControl::Lock,false);
-		Self::token_increase_or_decrease(seller,&tokentype_money,money,TokenControl::Free,true);
-		// share exchange
-		Self::token_increase_or_decrease(seller,&tokentype_share,amount,TokenControl::Lock,false);
-		Self::token_increase_or_decrease(buyer,&tokentype_share,amount,TokenControl::Free,true);
 	}
-
-}
\ No newline at end of file
+}
@@ -0,0 +1,130 @@
+use support::{impl_outer_origin, impl_outer_event, parameter_types};
+use sr_primitives::Perbill;
+use sr_primitives::traits::IdentityLookup;
+use sr_primitives::testing::Header;
+use primitives::{H256, Blake2Hasher};
+use std::cell::RefCell;
+use support::traits::Get;
+use crate::Trait;
+
+pub type AccountId = u64;
+
+impl_outer_origin!{
+	pub enum Origin for Test {}
+}
+
+mod token {
+	pub use crate::Event;
+}
+
+impl_outer_event!{
+	pub enum TestEvent for Test {
+		token<T>,
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Test;
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+impl system::Trait for Test {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Call = ();
+	type Hash = H256;
+	type Hashing = ::sr_primitives::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type WeightMultiplierUpdate = ();
+	type Event = TestEvent;
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type MaximumBlockLength = MaximumBlockLength;
+	type Version = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 10;
+	pub const TransferFee: u64 = 0;
+	pub const CreationFee: u64 = 0;
+	pub const TransactionBaseFee: u64 = 0;
+	pub const TransactionByteFee: u64 = 0;
+}
+impl balances::Trait for Test {
+	type Balance = u64;
+	type OnFreeBalanceZero = ();
+	type OnNewAccount = ();
+	type Event = ();
+	type TransactionPayment = ();
+	type TransferPayment = ();
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type TransferFee = TransferFee;
+	type CreationFee = CreationFee;
+	type TransactionBaseFee = TransactionBaseFee;
+	type TransactionByteFee = TransactionByteFee;
+	type WeightToFee = ();
+}
+
+parameter_types! {
+	pub const TokenDeposit: u64 = 100;
+}
+
+thread_local! {
+	static HOLDER_DEPOSIT: RefCell<u64> = RefCell::new(0);
+}
+
+pub struct HolderDeposit;
+impl Get<u64> for HolderDeposit {
+	fn get() -> u64 { HOLDER_DEPOSIT.with(|v| *v.borrow()) }
+}
+
+/// Reserve `deposit` from whoever pays for creating a token holder.
+pub fn set_holder_deposit(deposit: u64) {
+	HOLDER_DEPOSIT.with(|v| *v.borrow_mut() = deposit);
+}
+impl Trait for Test {
+	type Token = crate::Module<Self>;
+	type Event = TestEvent;
+	type Currency = balances::Module<Self>;
+	type TokenDeposit = TokenDeposit;
+	type OnAccountFrozen = ();
+	type HolderDeposit = HolderDeposit;
+	type DustRemoval = ();
+}
+
+pub type System = system::Module<Test>;
+pub type TokenModule = crate::Module<Test>;
+pub type Balances = balances::Module<Test>;
+
+/// Genesis with account 1 as the key, issuer of the two genesis tokens, and holdings of account 5.
+/// Accounts 2 and 3 can afford the token deposit, account 4 cannot.
+pub fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+	set_holder_deposit(0);
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	balances::GenesisConfig::<Test> {
+		balances: vec![(2, 1000), (3, 1000), (4, 50)],
+		vesting: vec![],
+	}.assimilate_storage(&mut t).unwrap();
+	let genesis_token = |symbol: &[u8]| crate::TokenInfo {
+		symbol: symbol.to_vec(),
+		name: symbol.to_vec(),
+		decimals: 3,
+		issuer: 1,
+		max_supply: u64::max_value(),
+	};
+	crate::GenesisConfig::<Test> {
+		key: 1,
+		tokens: vec![genesis_token(&[1, 2]), genesis_token(&[3, 4])],
+		balances: vec![(0, 5, 300), (1, 5, 700)],
+	}.assimilate_storage(&mut t).unwrap();
+	t.into()
+}
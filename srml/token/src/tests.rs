@@ -0,0 +1,628 @@
+#![cfg(test)]
+
+use super::*;
+use runtime_io::with_externalities;
+use support::{assert_ok, assert_noop};
+use mock::*;
+
+const VOTE_LOCK: LockIdentifier = *b"vote    ";
+const VEST_LOCK: LockIdentifier = *b"vest    ";
+
+#[test]
+fn genesis_tokens_are_registered() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_eq!(TokenModule::next_token_id(), 2);
+		let info = TokenModule::token_info(0).unwrap();
+		assert_eq!(info.symbol, vec![1u8, 2u8]);
+		assert_eq!(info.decimals, 3);
+		assert_eq!(info.issuer, 1);
+		assert_eq!(TokenModule::free_token((1, 5)), 700);
+		assert_eq!(TokenModule::total_issuance(1), 700);
+		assert_eq!(TokenModule::storage_version(), STORAGE_VERSION);
+	});
+}
+
+#[test]
+fn create_token_works() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::create_token(Origin::signed(2), vec![], b"Empty".to_vec(), 6, 1000),
+			"invalid token symbol");
+		assert_noop!(TokenModule::create_token(Origin::signed(2), b"LONG".to_vec(), vec![0u8; 65], 6, 1000),
+			"token name too long");
+		assert_noop!(TokenModule::create_token(Origin::signed(4), b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000),
+			"not enough funds for the token deposit");
+		assert_ok!(TokenModule::create_token(Origin::signed(2), b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000));
+		assert_eq!(Balances::reserved_balance(&2), 100);
+		let info = TokenModule::token_info(2).unwrap();
+		assert_eq!(info.issuer, 2);
+		assert_eq!(info.max_supply, 1000);
+		assert_eq!(TokenModule::next_token_id(), 3);
+	});
+}
+
+#[test]
+fn force_create_token_requires_root() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::force_create_token(Origin::signed(2), 4, b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000),
+			"RequireRootOrigin");
+		assert_ok!(TokenModule::force_create_token(Origin::ROOT, 4, b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000));
+		assert_eq!(TokenModule::token_info(2).unwrap().issuer, 4);
+		assert_eq!(Balances::reserved_balance(&4), 0);
+	});
+}
+
+#[test]
+fn mint_and_burn_track_issuance() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_ok!(TokenModule::create_token(Origin::signed(2), b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000));
+		assert_noop!(TokenModule::mint_token(Origin::signed(2), 5, 3, 100), "invalid tokentype");
+		assert_noop!(TokenModule::mint_token(Origin::signed(3), 2, 3, 100), "not the token issuer");
+		assert_ok!(TokenModule::mint_token(Origin::signed(2), 2, 3, 600));
+		assert_ok!(TokenModule::mint_token(Origin::signed(2), 2, 3, 400));
+		assert_eq!(TokenModule::free_token((2, 3)), 1000);
+		assert_noop!(TokenModule::mint_token(Origin::signed(2), 2, 4, 1), "mint exceeds the max supply of the token");
+		assert_noop!(TokenModule::burn_token(Origin::signed(3), 2, 3, 300), "not the token issuer");
+		assert_noop!(TokenModule::burn_token(Origin::signed(2), 2, 3, 1001), "Insufficient available balance");
+		assert_ok!(TokenModule::burn_token(Origin::signed(2), 2, 3, 300));
+		assert_eq!(TokenModule::free_token((2, 3)), 700);
+		assert_eq!(TokenModule::total_issuance(2), 700);
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(3), 4, 2, 200));
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &4, 2, 50));
+		assert_eq!(TokenModule::total_issuance(2), 700);
+	});
+}
+
+#[test]
+fn balance_and_issuance_arithmetic_is_checked() {
+	with_externalities(&mut new_test_ext(), || {
+		<FreeToken<Test>>::insert((1, 6), u64::max_value());
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 100), "token balance overflow");
+		<FreeToken<Test>>::insert((1, 7), 800);
+		assert_noop!(TokenModule::burn(&7, 1, 800), "token supply underflow");
+		assert_eq!(TokenModule::total_issuance(1), 700);
+	});
+}
+
+#[test]
+fn transfer_issuer_works() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_ok!(TokenModule::create_token(Origin::signed(2), b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000));
+		assert_noop!(TokenModule::transfer_issuer(Origin::signed(3), 2, 3), "not the token issuer");
+		assert_ok!(TokenModule::transfer_issuer(Origin::signed(2), 2, 3));
+		assert_eq!(TokenModule::token_info(2).unwrap().issuer, 3);
+		assert_noop!(TokenModule::mint_token(Origin::signed(2), 2, 2, 10), "not the token issuer");
+		assert_ok!(TokenModule::mint_token(Origin::signed(3), 2, 2, 10));
+	});
+}
+
+#[test]
+fn migrate_legacy_balances_works() {
+	with_externalities(&mut new_test_ext(), || {
+		// a token type and balance written by storage version 0
+		let tokentype = b"OLD".to_vec();
+		TokenTypeAndPrecision::insert(&tokentype, 10000);
+		hashed::put(&runtime_io::blake2_256,
+			&TokenModule::legacy_balance_key(b"Token FreeToken", &tokentype, &7), &500u64);
+		hashed::put(&runtime_io::blake2_256,
+			&TokenModule::legacy_balance_key(b"Token LockedToken", &tokentype, &7), &20u64);
+
+		assert_noop!(TokenModule::migrate_legacy_balances(Origin::signed(9), tokentype.clone(), vec![7]),
+			"RequireRootOrigin");
+		assert_noop!(TokenModule::migrate_legacy_balances(Origin::ROOT, tokentype.clone(), vec![7; 1001]),
+			"too many accounts");
+		assert_noop!(TokenModule::migrate_legacy_balances(Origin::ROOT, b"NONE".to_vec(), vec![7]),
+			"invalid tokentype");
+
+		// the token is registered on first sight, its old holders may not touch it until migrated
+		let token = TokenModule::legacy_token(&tokentype).unwrap();
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(5), 7, token, 0),
+			"account has balances still to migrate");
+		assert!(TokenModule::ensure_can_hold(token, &7).is_err());
+		assert_ok!(TokenModule::ensure_can_hold(token, &8));
+
+		assert_ok!(TokenModule::migrate_legacy_balances(Origin::ROOT, tokentype.clone(), vec![7]));
+		assert_ok!(TokenModule::ensure_can_hold(token, &7));
+		assert_eq!(TokenModule::token_info(token).unwrap().decimals, 4);
+		assert_eq!(TokenModule::free_token((token, 7)), 500);
+		assert_eq!(TokenModule::locked_token((token, 7)), 20);
+		assert_eq!(TokenModule::total_issuance(token), 520);
+
+		// a second run finds nothing left to move
+		assert_ok!(TokenModule::migrate_legacy_balances(Origin::ROOT, tokentype, vec![7]));
+		assert_eq!(TokenModule::free_token((token, 7)), 500);
+	});
+}
+
+fn token_events() -> Vec<RawEvent<AccountId>> {
+	System::events().into_iter().filter_map(|record| match record.event {
+		TestEvent::token(event) => Some(event),
+		_ => None,
+	}).collect()
+}
+
+#[test]
+fn token_movements_deposit_events() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_ok!(TokenModule::create_token(Origin::signed(2), b"DOT".to_vec(), b"Polkadot".to_vec(), 10, 1000));
+		assert_ok!(TokenModule::mint_token(Origin::signed(2), 2, 3, 500));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(3), 4, 2, 200));
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &4, 2, 50));
+		TokenModule::remove_lock(VOTE_LOCK, &4, 2);
+		assert_ok!(TokenModule::burn_token(Origin::signed(2), 2, 3, 100));
+		assert_eq!(token_events(), vec![
+			RawEvent::TokenCreated(2, 2),
+			RawEvent::Minted(2, 3, 500),
+			RawEvent::Transferred(2, 3, 4, 200),
+			RawEvent::Locked(2, 4, VOTE_LOCK, 50),
+			RawEvent::Unlocked(2, 4, VOTE_LOCK),
+			RawEvent::Burned(2, 3, 100),
+		]);
+	});
+}
+
+#[test]
+fn allowances_work() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::approve(Origin::signed(5), 6, 9, 100), "invalid tokentype");
+		assert_ok!(TokenModule::approve(Origin::signed(5), 6, 1, 100));
+		assert_ok!(TokenModule::increase_allowance(Origin::signed(5), 6, 1, 50));
+		assert_noop!(TokenModule::decrease_allowance(Origin::signed(5), 6, 1, 151), "allowance would drop below zero");
+		assert_ok!(TokenModule::decrease_allowance(Origin::signed(5), 6, 1, 30));
+		assert_eq!(TokenModule::allowance((1, 5, 6)), 120);
+
+		assert_noop!(TokenModule::transfer_from(Origin::signed(7), 5, 7, 1, 10), "transfer exceeds the allowance");
+		assert_noop!(TokenModule::transfer_from(Origin::signed(6), 5, 7, 1, 121), "transfer exceeds the allowance");
+		assert_ok!(TokenModule::transfer_from(Origin::signed(6), 5, 7, 1, 100));
+		assert_eq!(TokenModule::free_token((1, 5)), 600);
+		assert_eq!(TokenModule::free_token((1, 7)), 100);
+		assert_eq!(TokenModule::amount_allowed(&5, &6, 1), 20);
+
+		// the allowance does not protect against a short balance
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 590));
+		assert_noop!(TokenModule::transfer_from(Origin::signed(6), 5, 7, 1, 20), "Insufficient available balance");
+		assert_ok!(TokenModule::transfer_from(Origin::signed(6), 5, 7, 1, 10));
+
+		assert_ok!(TokenModule::approve(Origin::signed(5), 6, 1, 0));
+		assert!(!<Allowance<Test>>::exists((1, 5, 6)));
+	});
+}
+
+#[test]
+fn named_locks_overlap() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 701), "lock exceeds the balance");
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 300));
+		assert_ok!(TokenModule::set_lock(VEST_LOCK, &5, 1, 200));
+		// the largest lock is held, the others overlap with it
+		assert_eq!(TokenModule::free_token((1, 5)), 400);
+		assert_eq!(TokenModule::locked_token((1, 5)), 300);
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 401), "Insufficient available balance");
+
+		assert_ok!(TokenModule::extend_lock(VEST_LOCK, &5, 1, 100));
+		assert_eq!(TokenModule::lock_amount(VEST_LOCK, &5, 1), 200);
+		assert_ok!(TokenModule::extend_lock(VEST_LOCK, &5, 1, 500));
+		assert_eq!(TokenModule::free_token((1, 5)), 200);
+
+		TokenModule::remove_lock(VEST_LOCK, &5, 1);
+		assert_eq!(TokenModule::free_token((1, 5)), 400);
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 0));
+		assert_eq!(TokenModule::free_token((1, 5)), 700);
+		assert!(!<Locks<Test>>::exists((1, 5)));
+	});
+}
+
+#[test]
+fn transfer_locked_ignores_other_locks() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 700));
+		assert_ok!(TokenModule::set_lock(VEST_LOCK, &5, 1, 100));
+		assert_noop!(TokenModule::transfer_locked(VEST_LOCK, &5, &6, 1, 101), "Insufficient locked balance");
+		assert_ok!(TokenModule::transfer_locked(VEST_LOCK, &5, &6, 1, 100));
+		assert_eq!(TokenModule::free_token((1, 6)), 100);
+		// the vote lock is now larger than the balance and holds all of it
+		assert_eq!(TokenModule::free_token((1, 5)), 0);
+		assert_eq!(TokenModule::locked_token((1, 5)), 600);
+		assert_eq!(TokenModule::lock_amount(VEST_LOCK, &5, 1), 0);
+
+		// new funds are held until the lock is backed again
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(6), 5, 1, 100));
+		assert_eq!(TokenModule::free_token((1, 5)), 0);
+		assert_eq!(TokenModule::locked_token((1, 5)), 700);
+	});
+}
+
+#[test]
+fn unnamed_locked_balance_is_a_legacy_lock() {
+	with_externalities(&mut new_test_ext(), || {
+		// locked before locks were named
+		<FreeToken<Test>>::insert((1, 5), 500);
+		<LockedToken<Test>>::insert((1, 5), 200);
+		assert_eq!(TokenModule::lock_amount(LEGACY_LOCK_ID, &5, 1), 200);
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 100));
+		assert_eq!(TokenModule::locks_of(1, &5).len(), 2);
+		// the legacy lock is the reserve, held on top of the other locks
+		assert_eq!(TokenModule::locked_token((1, 5)), 300);
+		assert_ok!(TokenModule::transfer_locked(LEGACY_LOCK_ID, &5, &6, 1, 200));
+		assert_eq!(TokenModule::free_token((1, 5)), 400);
+		assert_eq!(TokenModule::locked_token((1, 5)), 100);
+	});
+}
+
+#[test]
+fn multi_currency_reserves_under_a_lock() {
+	use support::traits::MultiCurrency;
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::reserve(1, &5, 701), "Insufficient available balance");
+		assert_ok!(TokenModule::reserve(1, &5, 300));
+		assert_eq!(TokenModule::free_balance(1, &5), 400);
+		assert_eq!(TokenModule::reserved_balance(1, &5), 300);
+		assert_eq!(TokenModule::lock_amount(RESERVE_LOCK_ID, &5, 1), 300);
+
+		assert_eq!(TokenModule::unreserve(1, &5, 100), 0);
+		assert_eq!(TokenModule::repatriate_reserved(1, &5, &6, 250), Ok(50));
+		assert_eq!(TokenModule::free_token((1, 6)), 200);
+		assert_eq!(TokenModule::reserved_balance(1, &5), 0);
+		assert_eq!(TokenModule::free_balance(1, &5), 500);
+
+		// free funds are slashed first and the issuance shrinks by what was slashed
+		assert_ok!(TokenModule::reserve(1, &5, 100));
+		assert_eq!(TokenModule::slash(1, &5, 450), 0);
+		assert_eq!(TokenModule::free_balance(1, &5), 0);
+		assert_eq!(TokenModule::reserved_balance(1, &5), 50);
+		assert_eq!(TokenModule::slash_reserved(1, &5, 80), 30);
+		assert_eq!(TokenModule::total_issuance(1), 200);
+	});
+}
+
+#[test]
+fn vested_transfer_releases_after_the_cliff() {
+	use support::traits::MultiCurrency;
+	with_externalities(&mut new_test_ext(), || {
+		System::set_block_number(1);
+		let schedule = VestingSchedule{ locked: 300, per_block: 10, start: 10, cliff: 5 };
+		assert_noop!(TokenModule::vested_transfer(Origin::signed(5), 6, 1, VestingSchedule{ per_block: 0, ..schedule }),
+			"invalid vesting schedule");
+		assert_ok!(TokenModule::vested_transfer(Origin::signed(5), 6, 1, schedule));
+		assert_eq!(TokenModule::free_token((1, 6)), 0);
+		assert_eq!(TokenModule::lock_amount(VESTING_LOCK_ID, &6, 1), 300);
+
+		// vesting tokens can neither be moved nor reserved for orders
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(6), 7, 1, 1), "Insufficient available balance");
+		assert_noop!(TokenModule::reserve(1, &6, 1), "Insufficient available balance");
+
+		System::set_block_number(14);
+		assert_ok!(TokenModule::vest(Origin::signed(6), 1));
+		assert_eq!(TokenModule::free_token((1, 6)), 0);
+
+		// what vested since the start is released at the cliff
+		System::set_block_number(15);
+		assert_ok!(TokenModule::vest(Origin::signed(6), 1));
+		assert_eq!(TokenModule::free_token((1, 6)), 50);
+		assert_eq!(TokenModule::lock_amount(VESTING_LOCK_ID, &6, 1), 250);
+
+		// reserves stack on top of the vesting lock
+		assert_ok!(TokenModule::reserve(1, &6, 50));
+		assert_eq!(TokenModule::free_token((1, 6)), 0);
+		assert_eq!(TokenModule::locked_token((1, 6)), 300);
+
+		System::set_block_number(45);
+		assert_ok!(TokenModule::vest(Origin::signed(6), 1));
+		assert!(TokenModule::vesting((1, 6)).is_empty());
+		assert_eq!(TokenModule::lock_amount(VESTING_LOCK_ID, &6, 1), 0);
+		assert_eq!(TokenModule::free_token((1, 6)), 250);
+		assert_eq!(TokenModule::reserved_balance(1, &6), 50);
+		assert_noop!(TokenModule::vest(Origin::signed(6), 1), "no vesting schedule");
+	});
+}
+
+#[test]
+fn vesting_schedules_add_up() {
+	with_externalities(&mut new_test_ext(), || {
+		System::set_block_number(1);
+		let schedule = VestingSchedule{ locked: 100, per_block: 10, start: 1, cliff: 0 };
+		assert_ok!(TokenModule::vested_transfer(Origin::signed(5), 6, 1, schedule));
+
+		// someone else's grant does not stand in the way of another, nor replace it
+		assert_ok!(TokenModule::vested_transfer(Origin::signed(5), 6, 1,
+			VestingSchedule{ locked: 1, per_block: 1, start: 1, cliff: 1000 }));
+		assert_ok!(TokenModule::vested_transfer(Origin::signed(5), 6, 1, schedule));
+		assert_eq!(TokenModule::vesting((1, 6)).len(), 3);
+		assert_eq!(TokenModule::lock_amount(VESTING_LOCK_ID, &6, 1), 201);
+
+		System::set_block_number(6);
+		assert_ok!(TokenModule::vest(Origin::signed(6), 1));
+		assert_eq!(TokenModule::lock_amount(VESTING_LOCK_ID, &6, 1), 101);
+		assert_eq!(TokenModule::free_token((1, 6)), 100);
+
+		// released schedules make room for new ones, running ones are capped
+		System::set_block_number(11);
+		for _ in 0..MAX_VESTING_SCHEDULES - 1 {
+			assert_ok!(TokenModule::vested_transfer(Origin::signed(5), 6, 1, VestingSchedule{ locked: 20, per_block: 1, start: 11, cliff: 0 }));
+		}
+		assert_eq!(TokenModule::vesting((1, 6)).len(), MAX_VESTING_SCHEDULES);
+		assert_noop!(TokenModule::vested_transfer(Origin::signed(5), 6, 1, VestingSchedule{ locked: 20, per_block: 1, start: 11, cliff: 0 }),
+			"too many vesting schedules");
+
+		// grants of dust are refused once the token has a minimum balance
+		assert_ok!(TokenModule::set_min_balance(Origin::signed(1), 1, 10));
+		assert_noop!(TokenModule::vested_transfer(Origin::signed(5), 7, 1, VestingSchedule{ locked: 9, ..schedule }),
+			"vested transfer below the minimum balance");
+	});
+}
+
+#[test]
+fn frozen_accounts_cannot_move_tokens() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 100));
+		assert_noop!(TokenModule::freeze_account(Origin::signed(5), 1, 5), "not the token issuer");
+		assert_ok!(TokenModule::freeze_account(Origin::signed(1), 1, 5));
+		assert_noop!(TokenModule::freeze_account(Origin::signed(1), 1, 5), "account is frozen");
+
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 10), "account is frozen");
+		assert_noop!(TokenModule::mint_token(Origin::signed(1), 1, 5, 10), "account is frozen");
+		assert_noop!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 200), "account is frozen");
+		assert_noop!(TokenModule::transfer_locked(VOTE_LOCK, &5, &6, 1, 10), "account is frozen");
+		// locks may still be released
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &5, 1, 50));
+		assert_eq!(TokenModule::free_token((1, 5)), 650);
+
+		assert_ok!(TokenModule::thaw_account(Origin::signed(1), 1, 5));
+		assert_noop!(TokenModule::thaw_account(Origin::signed(1), 1, 5), "account is not frozen");
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 10));
+	});
+}
+
+#[test]
+fn allowlist_mode_restricts_holders() {
+	use support::traits::MultiCurrency;
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::set_allowlist_only(Origin::signed(1), 1, true),
+			"allowlist mode must be set before issuance");
+
+		let token = TokenModule::next_token_id();
+		assert_ok!(TokenModule::create_token(Origin::signed(2), b"REG".to_vec(), b"Regulated".to_vec(), 0, 1000));
+		assert_ok!(TokenModule::set_allowlist_only(Origin::signed(2), token, true));
+		assert_noop!(TokenModule::mint_token(Origin::signed(2), token, 5, 100), "account is not allowlisted");
+		assert_ok!(TokenModule::set_allowlisted(Origin::signed(2), token, 5, true));
+		assert_ok!(TokenModule::mint_token(Origin::signed(2), token, 5, 100));
+
+		assert!(!TokenModule::can_hold(token, &6));
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(5), 6, token, 10), "account is not allowlisted");
+		assert_ok!(TokenModule::set_allowlisted(Origin::signed(2), token, 6, true));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 6, token, 10));
+
+		assert_ok!(TokenModule::set_allowlisted(Origin::signed(2), token, 6, false));
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(6), 5, token, 10), "account is not allowlisted");
+		assert_ok!(TokenModule::set_allowlist_only(Origin::signed(2), token, false));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(6), 7, token, 10));
+	});
+}
+
+#[test]
+fn balances_at_snapshots_follow_later_transfers() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::snapshot(Origin::signed(5), 1), "not the token issuer");
+		assert_ok!(TokenModule::snapshot(Origin::signed(1), 1));
+		assert_eq!(TokenModule::current_snapshot(1), 1);
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 200));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 100));
+		assert_ok!(TokenModule::snapshot(Origin::signed(1), 1));
+		assert_ok!(TokenModule::snapshot(Origin::signed(1), 1));
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &6, 1, 100));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(6), 7, 1, 50));
+
+		// one checkpoint per account and snapshot, locked tokens count as held
+		assert_eq!(TokenModule::balance_checkpoints((1, 5)), vec![(1, 700)]);
+		assert_eq!(TokenModule::balance_checkpoints((1, 6)), vec![(1, 0), (3, 300)]);
+		assert_eq!(TokenModule::balance_at(1, &5, 1), 700);
+		assert_eq!(TokenModule::balance_at(1, &5, 3), 400);
+		assert_eq!(TokenModule::balance_at(1, &6, 1), 0);
+		assert_eq!(TokenModule::balance_at(1, &6, 2), 300);
+		assert_eq!(TokenModule::balance_at(1, &6, 3), 300);
+		assert_eq!(TokenModule::balance_at(1, &7, 1), 0);
+		assert_eq!(TokenModule::free_token((1, 7)), 50);
+	});
+}
+
+#[test]
+fn distributions_pay_pro_rata() {
+	with_externalities(&mut new_test_ext(), || {
+		// 5 holds all 700 of token 1, then hands 7 of every 10 to 6
+		assert_eq!(TokenModule::total_issuance(1), 700);
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 490));
+		assert_ok!(TokenModule::snapshot(Origin::signed(1), 1));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(6), 5, 1, 490));
+
+		assert_noop!(TokenModule::distribute(Origin::signed(1), 1, 2, 0, 100), "unknown snapshot");
+		assert_ok!(TokenModule::mint_token(Origin::signed(1), 0, 1, 100));
+		// paid out in token 0, of which 5 holds 300 already
+		assert_ok!(TokenModule::distribute(Origin::signed(1), 1, 1, 0, 100));
+		assert_eq!(TokenModule::free_token((0, TokenModule::distribution_pot())), 100);
+
+		assert_ok!(TokenModule::claim(Origin::signed(6), 0));
+		assert_noop!(TokenModule::claim(Origin::signed(6), 0), "already claimed");
+		assert_ok!(TokenModule::claim(Origin::signed(5), 0));
+		assert_noop!(TokenModule::claim(Origin::signed(7), 0), "nothing to claim");
+		assert_noop!(TokenModule::claim(Origin::signed(5), 1), "unknown distribution");
+		assert_eq!(TokenModule::free_token((0, 6)), 70);
+		assert_eq!(TokenModule::free_token((0, 5)), 330);
+		assert_eq!(TokenModule::distribution(0).unwrap().claimed, 100);
+	});
+}
+
+#[test]
+fn holders_pay_a_deposit_and_dust_is_reaped() {
+	with_externalities(&mut new_test_ext(), || {
+		set_holder_deposit(5);
+		// 5 has no native currency to pay for 6 holding token 1
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 100),
+			"not enough funds for the holder deposit");
+		// the issuer pays for the holdings it mints
+		assert_noop!(TokenModule::mint_token(Origin::signed(1), 1, 2, 100),
+			"not enough funds for the holder deposit");
+		assert_ok!(Balances::transfer(Origin::signed(3), 1, 100));
+		assert_ok!(TokenModule::mint_token(Origin::signed(1), 1, 2, 100));
+		assert_eq!(TokenModule::holder_deposit((1, 2)), Some((1, 5)));
+		assert_eq!(Balances::reserved_balance(&1), 5);
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(2), 3, 1, 50));
+		assert_eq!(Balances::reserved_balance(&2), 5);
+		assert_eq!(TokenModule::holder_deposit((1, 3)), Some((2, 5)));
+
+		assert_ok!(TokenModule::set_min_balance(Origin::signed(1), 1, 20));
+		assert_noop!(TokenModule::transfer_free_token(Origin::signed(2), 7, 1, 10), "value too low to create a holder");
+
+		// 3 is left with dust, which is burned, and the deposit goes back to 2
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(3), 2, 1, 40));
+		assert!(!<FreeToken<Test>>::exists((1, 3)));
+		assert_eq!(TokenModule::total_issuance(1), 790);
+		assert_eq!(TokenModule::holder_deposit((1, 3)), None);
+		assert_eq!(Balances::reserved_balance(&2), 0);
+
+		// locked holdings are kept until unlocked
+		assert_ok!(TokenModule::set_min_balance(Origin::signed(1), 1, 100));
+		assert_ok!(TokenModule::set_lock(VOTE_LOCK, &2, 1, 90));
+		assert_eq!(TokenModule::locked_token((1, 2)), 90);
+		TokenModule::remove_lock(VOTE_LOCK, &2, 1);
+		assert!(!<FreeToken<Test>>::exists((1, 2)));
+		assert_eq!(TokenModule::total_issuance(1), 700);
+		assert_eq!(Balances::reserved_balance(&1), 0);
+
+		// so do depositors for their wrapped native holding, checked before anything moves
+		let wrapped = create_wrapped_native();
+		assert_noop!(TokenModule::deposit_native(Origin::signed(4), 46), "not enough funds for the holder deposit");
+		assert_ok!(TokenModule::deposit_native(Origin::signed(3), 100));
+		assert_eq!(TokenModule::holder_deposit((wrapped, 3)), Some((3, 5)));
+		assert_eq!(Balances::reserved_balance(&3), 5);
+	});
+}
+
+#[test]
+fn holders_are_indexed_and_paged() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_eq!(TokenModule::holders(1, 0, 10), vec![5]);
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 6, 1, 100));
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(5), 7, 1, 100));
+		assert_ok!(TokenModule::mint_token(Origin::signed(1), 1, 8, 100));
+		assert_eq!(TokenModule::holder_count(1), 4);
+		assert_eq!(TokenModule::holders(1, 1, 2), vec![6, 7]);
+		assert_eq!(TokenModule::holders(1, 3, 10), vec![8]);
+		assert_eq!(TokenModule::holders(1, 4, 10), Vec::<u64>::new());
+
+		// emptied holdings leave the index, the last holder taking their place
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(6), 5, 1, 100));
+		assert_eq!(TokenModule::holders(1, 0, 10), vec![5, 8, 7]);
+		assert_eq!(TokenModule::balances_of(&7), vec![(1, 100, 0)]);
+		assert!(TokenModule::balances_of(&6).is_empty());
+
+		// holders from before the index are added on request, non-holders ignored
+		<FreeToken<Test>>::insert((0, 9), 50);
+		assert_noop!(TokenModule::index_holders(Origin::signed(2), 0, vec![9, 10]), "RequireRootOrigin");
+		assert_ok!(TokenModule::index_holders(Origin::ROOT, 0, vec![9, 10]));
+		assert_eq!(TokenModule::holders(0, 0, 10), vec![5, 9]);
+	});
+}
+
+#[test]
+fn batch_transfers_are_all_or_nothing() {
+	use support::dispatch::GetDispatchInfo;
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::transfer_batch(Origin::signed(5), 1, vec![(6, 400), (7, 400)]),
+			"Insufficient available balance");
+		assert_ok!(TokenModule::transfer_batch(Origin::signed(5), 1, vec![(6, 100), (7, 200), (6, 50)]));
+		assert_eq!(TokenModule::free_token((1, 5)), 350);
+		assert_eq!(TokenModule::free_token((1, 6)), 150);
+		assert_eq!(TokenModule::free_token((1, 7)), 200);
+		assert_eq!(token_events(), vec![
+			RawEvent::Transferred(1, 5, 6, 100),
+			RawEvent::Transferred(1, 5, 7, 200),
+			RawEvent::Transferred(1, 5, 6, 50),
+		]);
+
+		// a leg failing late stops the earlier ones too
+		assert_ok!(TokenModule::freeze_account(Origin::signed(1), 0, 8));
+		assert_noop!(TokenModule::transfer_batch_multi(Origin::signed(5), vec![(1, 6, 10), (0, 8, 10)]),
+			"account is frozen");
+		assert_ok!(TokenModule::transfer_batch_multi(Origin::signed(5), vec![(1, 6, 10), (0, 7, 20)]));
+		assert_eq!(TokenModule::free_token((1, 6)), 160);
+		assert_eq!(TokenModule::free_token((0, 7)), 20);
+
+		// weighed by the number of legs
+		let batch = Call::<Test>::transfer_batch(1, vec![(6, 1), (7, 1), (8, 1)]);
+		assert_eq!(batch.get_dispatch_info().weight, 30_000);
+		let multi = Call::<Test>::transfer_batch_multi(vec![(1, 6, 1), (0, 7, 1)]);
+		assert_eq!(multi.get_dispatch_info().weight, 20_000);
+	});
+}
+
+fn create_wrapped_native() -> TokenId {
+	assert_ok!(TokenModule::create_wrapped_native(Origin::ROOT, b"WNAT".to_vec(), b"Wrapped native".to_vec(), 0));
+	TokenModule::wrapped_native().unwrap()
+}
+
+#[test]
+fn deposit_and_withdraw_native_keep_the_backing() {
+	with_externalities(&mut new_test_ext(), || {
+		assert_noop!(TokenModule::deposit_native(Origin::signed(2), 100), "no wrapped native token");
+		let wrapped = create_wrapped_native();
+		assert_noop!(TokenModule::create_wrapped_native(Origin::ROOT, b"W".to_vec(), b"W".to_vec(), 0),
+			"wrapped native token already exists");
+		let pot = TokenModule::native_pot();
+
+		assert_noop!(TokenModule::deposit_native(Origin::signed(2), 5), "deposit below the existential deposit");
+		assert_ok!(TokenModule::deposit_native(Origin::signed(2), 300));
+		assert_eq!(Balances::free_balance(&2), 700);
+		assert_eq!(Balances::reserved_balance(&pot), 300);
+		assert_eq!(TokenModule::free_token((wrapped, 2)), 300);
+		assert!(TokenModule::native_backing_holds());
+		// would leave account 4 below the existential deposit
+		assert!(TokenModule::deposit_native(Origin::signed(4), 45).is_err());
+		assert_eq!(TokenModule::total_issuance(wrapped), 300);
+
+		// wrapped tokens are redeemable by whoever holds them
+		assert_ok!(TokenModule::transfer_free_token(Origin::signed(2), 3, wrapped, 100));
+		assert_ok!(TokenModule::withdraw_native(Origin::signed(3), 100));
+		assert_eq!(Balances::free_balance(&3), 1100);
+		assert_eq!(Balances::reserved_balance(&pot), 200);
+		assert!(TokenModule::native_backing_holds());
+
+		assert_noop!(TokenModule::withdraw_native(Origin::signed(2), 201), "Insufficient available balance");
+		assert_noop!(TokenModule::withdraw_native(Origin::signed(2), 195),
+			"withdrawal would leave the native pot below the existential deposit");
+		assert_ok!(TokenModule::withdraw_native(Origin::signed(2), 200));
+		assert_eq!(Balances::free_balance(&2), 900);
+		assert_eq!(Balances::reserved_balance(&pot), 0);
+		assert!(TokenModule::native_backing_holds());
+
+		// nobody can issue the wrapped token directly
+		assert_noop!(TokenModule::mint_token(Origin::signed(1), wrapped, 1, 10), "not the token issuer");
+	});
+}
+
+#[test]
+fn slashing_keeps_wrapped_native_backed() {
+	use support::traits::MultiCurrency;
+	with_externalities(&mut new_test_ext(), || {
+		let wrapped = create_wrapped_native();
+		assert_ok!(TokenModule::deposit_native(Origin::signed(2), 300));
+		assert_ok!(TokenModule::deposit_native(Origin::signed(3), 200));
+
+		// slashing a depositor does not touch what backs their wrapped tokens
+		let (_, remaining) = <Balances as Currency<_>>::slash(&2, 1000);
+		assert_eq!(remaining, 300);
+		assert_eq!(Balances::free_balance(&2), 0);
+		assert!(TokenModule::native_backing_holds());
+		assert_ok!(TokenModule::withdraw_native(Origin::signed(2), 300));
+		assert_eq!(Balances::free_balance(&2), 300);
+
+		// destroying wrapped tokens destroys their backing
+		assert_eq!(<TokenModule as MultiCurrency<_>>::slash(wrapped, &3, 50), 0);
+		assert!(TokenModule::native_backing_holds());
+		assert_ok!(<TokenModule as MultiCurrency<_>>::reserve(wrapped, &3, 100));
+		assert_eq!(<TokenModule as MultiCurrency<_>>::slash_reserved(wrapped, &3, 150), 50);
+		assert!(TokenModule::native_backing_holds());
+		assert_ok!(TokenModule::burn(&3, wrapped, 40));
+		assert_eq!(TokenModule::total_issuance(wrapped), 10);
+		assert_eq!(Balances::reserved_balance(&TokenModule::native_pot()), 10);
+		assert!(TokenModule::native_backing_holds());
+	});
+}
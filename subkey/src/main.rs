@@ -363,7 +363,7 @@ fn create_extrinsic(
 			system::CheckEra::<Runtime>::from(Era::Immortal),
 			system::CheckNonce::<Runtime>::from(i),
 			system::CheckWeight::<Runtime>::new(),
-			balances::TakeFees::<Runtime>::from(f),
+			node_runtime::TakeTokenFees::<Runtime>::from(f),
 			Default::default(),
 		)
 	};
@@ -103,7 +103,7 @@ where
 	let inherents = client.runtime_api().inherent_extrinsics(&prior_block_id, inherents)
 		.expect("Failed to create inherent extrinsics");
 
-	let block = create_block::<RA, _, _, _, _>(&client, transfer, inherents);
+	let block = create_block::<RA, _, _, _, _>(&client, vec![transfer], inherents);
 	info!(
 		"Created block {} with hash {}. Transferring {} from {} to {}.",
 		factory_state.block_no() + RA::Number::one(),
@@ -0,0 +1,197 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+/// This module implements the two DEX manufacturing modes. Both first fund
+/// `num` randomly created traders, one block each: the master account
+/// transfers native currency for the fees and mints both currencies of the
+/// traded pair to the trader. Then `rounds` blocks follow in which every
+/// trader submits one extrinsic.
+///
+/// # DexCrossing
+/// Traders take turns: the even ones quote asks just above the mid price,
+/// the odd ones buy above all asks, each filling one ask completely.
+///
+///   B asks, C buys from B, D asks, E buys from D, ...
+///   ... x `rounds`
+///
+/// # DexMixed
+/// Every trader cycles through a resting bid, a resting ask, an order
+/// crossing the spread and the cancellation of its resting ask, staggered
+/// so that every block holds all four. An ask taken in the meantime fails
+/// to be cancelled, as it would on a live market.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use log::info;
+use client::Client;
+use client::block_builder::api::BlockBuilder;
+use client::runtime_api::ConstructRuntimeApi;
+use primitives::{Blake2Hasher, Hasher};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, ProvideRuntimeApi, One, SaturatedConversion};
+
+use crate::{DexMode, RuntimeAdapter, create_block};
+
+/// The price all orders are placed around.
+const MID_PRICE: u64 = 1_000;
+/// How far from the mid price resting orders are placed, at most.
+const SPREAD: u64 = 10;
+/// The amount of every order, so that a crossing order fills one resting order.
+const ORDER_AMOUNT: u64 = 10;
+/// The amount of either currency minted to every trader.
+const MINTED_AMOUNT: u64 = 1_000_000_000;
+
+/// A currency of the pair the DEX modes trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Currency {
+	/// The currency being bought and sold.
+	Base,
+	/// The currency prices are given in.
+	Quote,
+}
+
+/// The side of an order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+	Buy,
+	Sell,
+}
+
+/// The orders submitted so far, to know the indices to cancel.
+///
+/// Indices are counted from the orders submitted, so like the other modes
+/// this only works on an empty database.
+#[derive(Default)]
+pub struct Book {
+	placed: u128,
+	resting_asks: HashMap<u64, u128>,
+}
+
+enum Action {
+	/// An order not crossing the spread, unless the book is empty on the other side.
+	Rest(Side, u64),
+	/// An order crossing the spread.
+	Cross(Side, u64),
+	Cancel(u128),
+}
+
+pub fn next<RA, Backend, Exec, Block, RtApi>(
+	mode: DexMode,
+	factory_state: &mut RA,
+	book: &mut Book,
+	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
+	version: u32,
+	genesis_hash: <RA::Block as BlockT>::Hash,
+	prior_block_hash: <RA::Block as BlockT>::Hash,
+	prior_block_id: BlockId<Block>,
+) -> Option<Block>
+where
+	Block: BlockT<Hash = <Blake2Hasher as Hasher>::Out>,
+	Exec: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + Clone,
+	Backend: client::backend::Backend<Block, Blake2Hasher> + Send,
+	Client<Backend, Exec, Block, RtApi>: ProvideRuntimeApi,
+	<Client<Backend, Exec, Block, RtApi> as ProvideRuntimeApi>::Api: BlockBuilder<Block>,
+	RtApi: ConstructRuntimeApi<Block, Client<Backend, Exec, Block, RtApi>> + Send + Sync,
+	RA: RuntimeAdapter,
+{
+	let block_no: u64 = factory_state.block_no().saturated_into();
+	let num: u64 = factory_state.num().saturated_into();
+	let rounds: u64 = factory_state.rounds().saturated_into();
+	if block_no >= num + rounds {
+		return None;
+	}
+
+	let extrinsics = if block_no < num {
+		let trader = factory_state.start_number() + factory_state.block_no();
+		let (master, master_key) = (RA::master_account_id(), RA::master_account_secret());
+		let to = RA::gen_random_account_id(&trader);
+		let fees = RA::minimum_balance() * RA::Balance::from(1_000u32);
+
+		info!("Funding trader {} with {} and {} of each currency.", to, fees, MINTED_AMOUNT);
+		vec![
+			factory_state.transfer_extrinsic(
+				&master, &master_key, &to, &fees, version, &genesis_hash, &prior_block_hash,
+			),
+			factory_state.mint_extrinsic(
+				&master, &master_key, &to, Currency::Base, MINTED_AMOUNT,
+				version, &genesis_hash, &prior_block_hash,
+			),
+			factory_state.mint_extrinsic(
+				&master, &master_key, &to, Currency::Quote, MINTED_AMOUNT,
+				version, &genesis_hash, &prior_block_hash,
+			),
+		]
+	} else {
+		let round = block_no - num;
+		info!("Round {} of {}: {} traders submit an extrinsic each.", round + 1, rounds, num);
+		(0..num).map(|trader| {
+			let seed = factory_state.start_number() + RA::Number::saturated_from(trader);
+			let from = (RA::gen_random_account_id(&seed), RA::gen_random_account_secret(&seed));
+			let action = next_action(mode, book, trader, round);
+			match action {
+				Action::Rest(side, price) | Action::Cross(side, price) => {
+					book.placed += 1;
+					if let Action::Rest(Side::Sell, _) = action {
+						book.resting_asks.insert(trader, book.placed);
+					}
+					factory_state.order_extrinsic(
+						&from.0, &from.1, side, ORDER_AMOUNT, price,
+						version, &genesis_hash, &prior_block_hash,
+					)
+				},
+				Action::Cancel(index) => factory_state.cancel_order_extrinsic(
+					&from.0, &from.1, index, version, &genesis_hash, &prior_block_hash,
+				),
+			}
+		}).collect()
+	};
+
+	let inherents = factory_state.inherent_extrinsics();
+	let inherents = client.runtime_api().inherent_extrinsics(&prior_block_id, inherents)
+		.expect("Failed to create inherent extrinsics");
+
+	let block = create_block::<RA, _, _, _, _>(&client, extrinsics, inherents);
+	factory_state.set_block_no(factory_state.block_no() + RA::Number::one());
+
+	info!("Created block {} with hash {}.", factory_state.block_no(), prior_block_hash);
+
+	Some(block)
+}
+
+/// What `trader` submits in trading round `round`.
+fn next_action(mode: DexMode, book: &mut Book, trader: u64, round: u64) -> Action {
+	let offset = trader / 2 % SPREAD + 1;
+	match mode {
+		DexMode::Crossing => match trader % 2 {
+			0 => Action::Rest(Side::Sell, MID_PRICE + offset),
+			_ => Action::Cross(Side::Buy, MID_PRICE + SPREAD),
+		},
+		DexMode::Mixed => match (trader + round) % 4 {
+			0 => Action::Rest(Side::Buy, MID_PRICE - offset),
+			1 => Action::Rest(Side::Sell, MID_PRICE + offset),
+			2 => match trader % 2 {
+				0 => Action::Cross(Side::Buy, MID_PRICE + SPREAD + 1),
+				_ => Action::Cross(Side::Sell, MID_PRICE - SPREAD - 1),
+			},
+			_ => match book.resting_asks.remove(&trader) {
+				Some(index) => Action::Cancel(index),
+				// nothing to cancel in the first rounds
+				None => Action::Rest(Side::Buy, MID_PRICE - offset),
+			},
+		},
+	}
+}
@@ -15,7 +15,8 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Simple transaction factory which distributes tokens from a master
-//! account to a specified number of newly created accounts.
+//! account to a specified number of newly created accounts, or lets
+//! such accounts trade on the DEX.
 //!
 //! The factory currently only works on an empty database!
 
@@ -23,6 +24,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::cmp::PartialOrd;
 use std::fmt::Display;
+use std::time::Instant;
 
 use log::info;
 
@@ -39,10 +41,12 @@ use sr_primitives::traits::{
 	Block as BlockT, Header as HeaderT, ProvideRuntimeApi, SimpleArithmetic,
 	One, Zero,
 };
-pub use crate::modes::Mode;
+pub use crate::modes::{Mode, DexMode};
+pub use crate::dex_mode::{Currency, Side};
 
 pub mod modes;
 mod complex_mode;
+mod dex_mode;
 mod simple_modes;
 
 pub trait RuntimeAdapter {
@@ -79,6 +83,43 @@ pub trait RuntimeAdapter {
 		prior_block_hash: &<Self::Block as BlockT>::Hash,
 	) -> <Self::Block as BlockT>::Extrinsic;
 
+	/// Mint `amount` of `currency` of the traded pair to `destination`.
+	fn mint_extrinsic(
+		&self,
+		sender: &Self::AccountId,
+		key: &Self::Secret,
+		destination: &Self::AccountId,
+		currency: Currency,
+		amount: u64,
+		version: u32,
+		genesis_hash: &<Self::Block as BlockT>::Hash,
+		prior_block_hash: &<Self::Block as BlockT>::Hash,
+	) -> <Self::Block as BlockT>::Extrinsic;
+
+	/// Place an order for `amount` of the traded pair at `price` and match it.
+	fn order_extrinsic(
+		&self,
+		sender: &Self::AccountId,
+		key: &Self::Secret,
+		side: Side,
+		amount: u64,
+		price: u64,
+		version: u32,
+		genesis_hash: &<Self::Block as BlockT>::Hash,
+		prior_block_hash: &<Self::Block as BlockT>::Hash,
+	) -> <Self::Block as BlockT>::Extrinsic;
+
+	/// Cancel the order with index `order` of the traded pair.
+	fn cancel_order_extrinsic(
+		&self,
+		sender: &Self::AccountId,
+		key: &Self::Secret,
+		order: u128,
+		version: u32,
+		genesis_hash: &<Self::Block as BlockT>::Hash,
+		prior_block_hash: &<Self::Block as BlockT>::Hash,
+	) -> <Self::Block as BlockT>::Extrinsic;
+
 	fn inherent_extrinsics(&self) -> InherentData;
 
 	fn minimum_balance() -> Self::Balance;
@@ -91,7 +132,8 @@ pub trait RuntimeAdapter {
 }
 
 /// Manufactures transactions. The exact amount depends on
-/// `mode`, `num` and `rounds`.
+/// `mode`, `num` and `rounds`. Reports the blocks produced, the
+/// extrinsics in them and the time it took when done.
 pub fn factory<RA, Backend, Exec, Block, RtApi, Sc>(
 	mut factory_state: RA,
 	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
@@ -108,7 +150,11 @@ where
 	RA: RuntimeAdapter,
 	<<RA as RuntimeAdapter>::Block as BlockT>::Hash: From<primitives::H256>,
 {
-	if *factory_state.mode() != Mode::MasterToNToM && factory_state.rounds() > RA::Number::one() {
+	let single_round = match factory_state.mode() {
+		Mode::MasterToN | Mode::MasterTo1 => true,
+		_ => false,
+	};
+	if single_round && factory_state.rounds() > RA::Number::one() {
 		let msg = "The factory can only be used with rounds set to 1 in this mode.".into();
 		return Err(cli::error::Error::Input(msg));
 	}
@@ -121,6 +167,10 @@ where
 	let genesis_hash = client.block_hash(Zero::zero())?
 		.expect("Genesis block always exists; qed").into();
 
+	let mut book = dex_mode::Book::default();
+	let (mut blocks, mut extrinsics) = (0usize, 0usize);
+	let started = Instant::now();
+
 	while let Some(block) = match factory_state.mode() {
 		Mode::MasterToNToM => complex_mode::next::<RA, _, _, _, _>(
 			&mut factory_state,
@@ -130,6 +180,16 @@ where
 			best_hash.into(),
 			best_block_id,
 		),
+		Mode::Dex(mode) => dex_mode::next::<RA, _, _, _, _>(
+			*mode,
+			&mut factory_state,
+			&mut book,
+			&client,
+			version,
+			genesis_hash,
+			best_hash.into(),
+			best_block_id,
+		),
 		_ => simple_modes::next::<RA, _, _, _, _>(
 			&mut factory_state,
 			&client,
@@ -141,18 +201,31 @@ where
 	} {
 		best_hash = block.header().hash();
 		best_block_id = BlockId::<Block>::hash(best_hash);
+		let in_block = block.extrinsics().len();
 		import_block(&client, block);
 
-		info!("Imported block at {}", factory_state.block_no());
+		blocks += 1;
+		extrinsics += in_block;
+		info!("Imported block at {} with {} extrinsics", factory_state.block_no(), in_block);
 	}
 
+	let elapsed = started.elapsed();
+	info!(
+		"Produced {} blocks with {} extrinsics, {:.2} per block, in {}.{:03} s",
+		blocks,
+		extrinsics,
+		extrinsics as f64 / blocks.max(1) as f64,
+		elapsed.as_secs(),
+		elapsed.subsec_millis(),
+	);
+
 	Ok(())
 }
 
-/// Create a baked block from a transfer extrinsic and timestamp inherent.
+/// Create a baked block from extrinsics and the timestamp inherent.
 pub fn create_block<RA, Backend, Exec, Block, RtApi>(
 	client: &Arc<Client<Backend, Exec, Block, RtApi>>,
-	transfer: <RA::Block as BlockT>::Extrinsic,
+	extrinsics: Vec<<RA::Block as BlockT>::Extrinsic>,
 	inherent_extrinsics: Vec<<Block as BlockT>::Extrinsic>,
 ) -> Block
 where
@@ -165,10 +238,12 @@ where
 	RA: RuntimeAdapter,
 {
 	let mut block = client.new_block(Default::default()).expect("Failed to create new block");
-	block.push(
-		Decode::decode(&mut &transfer.encode()[..])
-			.expect("Failed to decode transfer extrinsic")
-	).expect("Failed to push transfer extrinsic into block");
+	for extrinsic in extrinsics {
+		block.push(
+			Decode::decode(&mut &extrinsic.encode()[..])
+				.expect("Failed to decode extrinsic")
+		).expect("Failed to push extrinsic into block");
+	}
 
 	for inherent in inherent_extrinsics {
 		block.push(inherent).expect("Failed ...");
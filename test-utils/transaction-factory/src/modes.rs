@@ -15,16 +15,24 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! The transaction factory can operate in different modes. See
-//! the `simple_mode` and `complex_mode` modules for details.
+//! the `simple_mode`, `complex_mode` and `dex_mode` modules for details.
 
 use std::str::FromStr;
 
-/// Token distribution modes.
+/// Token distribution and trading modes.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
 	MasterToN,
 	MasterTo1,
-	MasterToNToM
+	MasterToNToM,
+	Dex(DexMode),
+}
+
+/// The trading modes, see the `dex_mode` module.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DexMode {
+	Crossing,
+	Mixed,
 }
 
 impl FromStr for Mode {
@@ -34,6 +42,8 @@ impl FromStr for Mode {
 			"MasterToN" => Ok(Mode::MasterToN),
 			"MasterTo1" => Ok(Mode::MasterTo1),
 			"MasterToNToM" => Ok(Mode::MasterToNToM),
+			"DexCrossing" => Ok(Mode::Dex(DexMode::Crossing)),
+			"DexMixed" => Ok(Mode::Dex(DexMode::Mixed)),
 			_ => Err(format!("Invalid mode: {}", mode)),
 		}
 	}